@@ -0,0 +1,276 @@
+use crate::{memory::BIOS, Cpu, Memory};
+use std::{fmt, str::FromStr};
+
+/// The size of the boot ROM of the DMG, MGB and SGB models
+pub const DMG_BOOT_ROM_SIZE: usize = 0x100;
+/// The size of the CGB boot ROM. It is mapped at 0x0000-0x00FF and 0x0200-0x08FF, the cartridge
+/// header at 0x0100-0x01FF stays visible.
+pub const CGB_BOOT_ROM_SIZE: usize = 0x900;
+
+/// The Nintendo logo is drawn from tile 1 onwards, followed by the ® tile
+const LOGO_TILES: u16 = 0x8010;
+const LOGO_HEADER: u16 = 0x0104;
+const LOGO_SIZE: u16 = 0x30;
+const REGISTERED_TILE: [u8; 8] = [0x3C, 0x42, 0xB9, 0xA5, 0xB9, 0xA5, 0x42, 0x3C];
+
+/// The Game Boy models, which each leave the CPU and the I/O registers in a different state when
+/// their boot ROM finishes.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum Model {
+    /// The early DMG boot ROM
+    Dmg0,
+    #[default]
+    Dmg,
+    /// The Game Boy Pocket
+    Mgb,
+    Sgb,
+    Sgb2,
+    Cgb,
+}
+
+impl Model {
+    /// Whether this is one of the monochrome models.
+    pub fn is_dmg(self) -> bool {
+        matches!(self, Model::Dmg0 | Model::Dmg | Model::Mgb)
+    }
+
+    /// The initial value of the internal counter of DIV. The boot ROMs of the SGB and CGB take a
+    /// variable amount of time, so those start at 0.
+    fn divider(self) -> u16 {
+        match self {
+            Model::Dmg0 => 0x1830,
+            Model::Dmg | Model::Mgb => 0xABCC,
+            Model::Sgb | Model::Sgb2 | Model::Cgb => 0x0000,
+        }
+    }
+
+    /// The I/O registers that are not 0 after the boot ROM finished.
+    fn io_registers(self) -> Vec<(u16, u8)> {
+        let mut registers = vec![
+            (0xFF00, 0xCF),
+            (0xFF02, 0x7E),
+            (0xFF07, 0xF8),
+            (0xFF0F, 0xE1),
+            (0xFF10, 0x80),
+            (0xFF11, 0xBF),
+            (0xFF12, 0xF3),
+            (0xFF13, 0xFF),
+            (0xFF14, 0xBF),
+            (0xFF16, 0x3F),
+            (0xFF18, 0xFF),
+            (0xFF19, 0xBF),
+            (0xFF1A, 0x7F),
+            (0xFF1B, 0xFF),
+            (0xFF1C, 0x9F),
+            (0xFF1D, 0xFF),
+            (0xFF1E, 0xBF),
+            (0xFF20, 0xFF),
+            (0xFF23, 0xBF),
+            (0xFF24, 0x77),
+            (0xFF25, 0xF3),
+            (
+                0xFF26,
+                if self.is_dmg() || self == Model::Cgb {
+                    0xF1
+                } else {
+                    0xF0
+                },
+            ),
+            (0xFF40, 0x91),
+            (0xFF41, if self == Model::Dmg0 { 0x81 } else { 0x85 }),
+            (0xFF46, if self == Model::Cgb { 0x00 } else { 0xFF }),
+            (0xFF47, 0xFC),
+        ];
+        // The object palettes are not initialized by the boot ROMs of the monochrome models
+        if !self.is_dmg() {
+            registers.extend_from_slice(&[(0xFF48, 0xFF), (0xFF49, 0xFF)]);
+        }
+        registers
+    }
+
+    /// The registers AF, BC, DE and HL. The half carry and carry flags of the DMG and MGB are set
+    /// when the header checksum is not 0.
+    fn cpu_registers(self, header_checksum: u8) -> [u16; 4] {
+        let carry = if header_checksum != 0 { 0x30 } else { 0x00 };
+        match self {
+            Model::Dmg0 => [0x0100, 0xFF13, 0x00C1, 0x8403],
+            Model::Dmg => [0x0180 | carry, 0x0013, 0x00D8, 0x014D],
+            Model::Mgb => [0xFF80 | carry, 0x0013, 0x00D8, 0x014D],
+            Model::Sgb => [0x0100, 0x0014, 0x0000, 0xC060],
+            Model::Sgb2 => [0xFF00, 0x0014, 0x0000, 0xC060],
+            Model::Cgb => [0x1180, 0x0000, 0xFF56, 0x000D],
+        }
+    }
+}
+
+impl FromStr for Model {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Model, String> {
+        Ok(match s.to_ascii_lowercase().as_str() {
+            "dmg0" => Model::Dmg0,
+            "dmg" => Model::Dmg,
+            "mgb" => Model::Mgb,
+            "sgb" => Model::Sgb,
+            "sgb2" => Model::Sgb2,
+            "cgb" => Model::Cgb,
+            _ => {
+                return Err(format!(
+                    "unknown model {:?}, expected dmg0, dmg, mgb, sgb, sgb2 or cgb",
+                    s
+                ))
+            }
+        })
+    }
+}
+
+/// The boot ROM that is mapped over the start of the cartridge until 0xFF50 is written.
+#[derive(Clone)]
+pub struct BootRom {
+    data: Vec<u8>,
+}
+
+impl BootRom {
+    /// Checks that `data` has the size of a DMG or CGB boot ROM.
+    pub fn new(data: Vec<u8>) -> Result<BootRom, BootRomError> {
+        match data.len() {
+            DMG_BOOT_ROM_SIZE | CGB_BOOT_ROM_SIZE => Ok(BootRom { data }),
+            len => Err(BootRomError::InvalidSize(len)),
+        }
+    }
+
+    /// Returns the byte at `address`, or `None` if the cartridge is visible there.
+    pub fn read(&self, address: u16) -> Option<u8> {
+        match address {
+            0x0100..=0x01FF => None,
+            _ => self.data.get(address as usize).copied(),
+        }
+    }
+}
+
+impl Default for BootRom {
+    /// The built-in DMG boot ROM.
+    fn default() -> BootRom {
+        BootRom {
+            data: BIOS.to_vec(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum BootRomError {
+    InvalidSize(usize),
+}
+
+impl fmt::Display for BootRomError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BootRomError::InvalidSize(len) => write!(
+                fmt,
+                "the boot rom is 0x{:X} bytes, expected 0x{:X} or 0x{:X}",
+                len, DMG_BOOT_ROM_SIZE, CGB_BOOT_ROM_SIZE
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BootRomError {}
+
+/// Skips the boot ROM, and puts the CPU and memory in the state that the boot ROM of `model`
+/// leaves them in. Execution continues at the entry point of the cartridge at 0x0100.
+pub fn skip_boot(cpu: &mut Cpu, memory: &mut Memory, model: Model) {
+    let header_checksum = memory.cartridge().header().header_checksum;
+    let [af, bc, de, hl] = model.cpu_registers(header_checksum);
+    cpu.set_af(af);
+    cpu.set_bc(bc);
+    cpu.set_de(de);
+    cpu.set_hl(hl);
+    cpu.set_sp(0xFFFE);
+    cpu.set_program_counter(0x0100);
+
+    memory.set_model(model);
+    memory.reset_io_registers(model.divider(), &model.io_registers());
+    if model != Model::Cgb {
+        draw_logo(memory);
+    }
+    memory.unmap_boot_rom();
+}
+
+/// Copies the logo from the cartridge header to VRAM, the way the monochrome boot ROMs do. Every
+/// bit of the logo becomes 2x2 pixels, which only use the lower bit plane.
+fn draw_logo(memory: &mut Memory) {
+    let mut address = LOGO_TILES;
+    for offset in 0..LOGO_SIZE {
+        let byte = memory.read_byte(LOGO_HEADER + offset);
+        for nibble in [byte >> 4, byte & 0x0F] {
+            let row = (0..4).fold(0, |row, bit| {
+                row | ((nibble >> bit & 1) * 0b11) << (bit * 2)
+            });
+            for _ in 0..2 {
+                memory.write_byte(address, row);
+                address += 2;
+            }
+        }
+    }
+    for row in REGISTERED_TILE.iter() {
+        memory.write_byte(address, *row);
+        address += 2;
+    }
+
+    // The logo is 12 tiles wide, on the 8th and 9th row of the tile map, with the ® behind it
+    for tile in 0x01..=0x18 {
+        let address = if tile <= 0x0C {
+            0x9903 + tile
+        } else {
+            0x9923 + tile - 0x0C
+        };
+        memory.write_byte(address, tile as u8);
+    }
+    memory.write_byte(0x9910, 0x19);
+}
+
+#[test]
+fn skip_boot_state() {
+    let mut rom = crate::cartridge::test_rom(&[]);
+    rom[0x104..0x106].copy_from_slice(&[0xCE, 0xED]);
+    rom[0x14D] = crate::cartridge::header_checksum_of(&rom);
+    let mut memory = Memory::new(
+        crate::Cartridge::new(rom).unwrap(),
+        Box::new(crate::opcodes::TestVideo),
+    );
+    let mut cpu = Cpu::default();
+    skip_boot(&mut cpu, &mut memory, Model::Dmg);
+
+    assert_eq!(cpu.program_counter(), 0x0100);
+    assert_eq!(
+        [cpu.af(), cpu.bc(), cpu.de(), cpu.hl()],
+        [0x01B0, 0x0013, 0x00D8, 0x014D]
+    );
+    assert_eq!(memory.read_byte(0x0000), 0x00, "the boot rom is unmapped");
+    assert_eq!(memory.read_byte(0xFF0F), 0xE1);
+    assert_eq!(memory.read_byte(0xFF40), 0x91);
+    assert_eq!(memory.read_byte(0xFF04), 0xAB);
+    // 0xCE is the top left of the logo: 1100 and 1110
+    assert_eq!(
+        (0..4)
+            .map(|row| memory.read_byte(0x8010 + row * 2))
+            .collect::<Vec<_>>(),
+        [0xF0, 0xF0, 0xFC, 0xFC]
+    );
+    assert_eq!(memory.read_byte(0x9904), 0x01);
+    assert_eq!(memory.read_byte(0x992F), 0x18);
+    assert_eq!(memory.read_byte(0x9910), 0x19);
+}
+
+#[test]
+fn boot_rom_size() {
+    assert_eq!(
+        BootRom::new(vec![0; 0x200]).err(),
+        Some(BootRomError::InvalidSize(0x200))
+    );
+    let cgb = BootRom::new(vec![0x12; CGB_BOOT_ROM_SIZE]).unwrap();
+    assert_eq!(cgb.read(0x00FF), Some(0x12));
+    assert_eq!(cgb.read(0x0100), None);
+    assert_eq!(cgb.read(0x08FF), Some(0x12));
+    assert_eq!(cgb.read(0x0900), None);
+}
@@ -0,0 +1,102 @@
+use crate::{
+    memory::{INTERRUPT_ADDRESS, INTERRUPT_FLAG_ADDRESS},
+    Interrupt,
+};
+
+/// Everything the CPU is connected to. The opcodes only access memory through this trait, so the
+/// CPU can run against `Memory`, against `FlatRam` in tests, or against a custom implementation
+/// that e.g. traces every access.
+pub trait Bus {
+    fn read_byte(&mut self, address: u16) -> u8;
+    fn write_byte(&mut self, address: u16, value: u8);
+
+    /// Advances the rest of the hardware by the given amount of clock cycles. Called by the CPU
+    /// every time it is clocked.
+    fn tick(&mut self, cycles: u16);
+
+    /// Resets the DIV register, as done by executing STOP.
+    fn reset_divider(&mut self) {
+        // Any write to DIV resets it
+        self.write_byte(0xFF04, 0);
+    }
+
+    /// The input lines of P1 in bits 0-3, which are low while a selected button or direction is
+    /// held down. Used to wake the CPU from STOP.
+    fn joypad_lines(&mut self) -> u8 {
+        0x0F
+    }
+
+    /// Reads a little-endian word.
+    fn read_word(&mut self, address: u16) -> u16 {
+        let low = self.read_byte(address);
+        let high = self.read_byte(address.wrapping_add(1));
+        (high as u16) << 8 | low as u16
+    }
+
+    /// Writes a little-endian word.
+    fn write_word(&mut self, address: u16, value: u16) {
+        self.write_byte(address, value as u8);
+        self.write_byte(address.wrapping_add(1), (value >> 8) as u8);
+    }
+
+    /// Requests an interrupt by setting its bit in the IF register. The CPU services it once
+    /// interrupts are enabled and the matching bit in the IE register is set.
+    fn request_interrupt(&mut self, interrupt: Interrupt) {
+        let requested = self.read_byte(INTERRUPT_FLAG_ADDRESS);
+        self.write_byte(INTERRUPT_FLAG_ADDRESS, requested | interrupt.mask());
+    }
+
+    /// Clears the IF bit of an interrupt that is being serviced.
+    fn acknowledge_interrupt(&mut self, interrupt: Interrupt) {
+        let requested = self.read_byte(INTERRUPT_FLAG_ADDRESS);
+        self.write_byte(INTERRUPT_FLAG_ADDRESS, requested & !interrupt.mask());
+    }
+
+    /// The interrupts that are both requested (IF) and enabled (IE).
+    fn pending_interrupts(&mut self) -> u8 {
+        let requested = self.read_byte(INTERRUPT_FLAG_ADDRESS);
+        let enabled = self.read_byte(INTERRUPT_ADDRESS);
+        requested & enabled & 0x1F
+    }
+}
+
+/// 64 KiB of plain RAM without any memory mapped hardware. Reads and writes go straight to the
+/// backing array, which makes it useful for running opcodes in isolation.
+pub struct FlatRam(pub Box<[u8; 0x10_000]>);
+
+impl Default for FlatRam {
+    fn default() -> FlatRam {
+        FlatRam(Box::new([0; 0x10_000]))
+    }
+}
+
+impl Bus for FlatRam {
+    fn read_byte(&mut self, address: u16) -> u8 {
+        self.0[address as usize]
+    }
+
+    fn write_byte(&mut self, address: u16, value: u8) {
+        self.0[address as usize] = value;
+    }
+
+    fn tick(&mut self, _cycles: u16) {}
+}
+
+#[test]
+fn execute_on_flat_ram() {
+    let mut ram = FlatRam::default();
+    // LD HL, $C000; LD (HL), $42; INC (HL)
+    ram.0[..6].copy_from_slice(&[0x21, 0x00, 0xC0, 0x36, 0x42, 0x34]);
+    let mut cpu = crate::Cpu::default();
+    for _ in 0..3 {
+        crate::opcodes::execute(&mut ram, &mut cpu);
+    }
+    assert_eq!(ram.read_byte(0xC000), 0x43);
+    assert_eq!(cpu.elapsed_cycles(), 12 + 12 + 12);
+
+    ram.write_word(0xC000, 0x1234);
+    assert_eq!(ram.0[0xC000..0xC002], [0x34, 0x12]);
+    ram.write_byte(INTERRUPT_ADDRESS, Interrupt::Timer.mask());
+    ram.request_interrupt(Interrupt::Timer);
+    assert_eq!(ram.pending_interrupts(), Interrupt::Timer.mask());
+}
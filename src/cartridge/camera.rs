@@ -0,0 +1,368 @@
+use super::{ram_offset, read_rom_bank, Mapper};
+use std::{fs::File, io::BufWriter, path::Path};
+
+pub const SENSOR_WIDTH: usize = 128;
+pub const SENSOR_HEIGHT: usize = 112;
+/// The size of a captured image, 16×14 tiles of 2 bits per pixel
+pub const IMAGE_SIZE: usize = SENSOR_WIDTH * SENSOR_HEIGHT / 4;
+
+/// The size of the RAM, which is always 128 KiB
+const RAM_SIZE: usize = 0x20000;
+/// A capture is written to RAM bank 0 at 0xA100
+const CAPTURE_OFFSET: usize = 0x100;
+/// $11B2-$11CF The photo number of each of the 30 album slots, 0xFF for unused slots
+const ALBUM_INDEX: usize = 0x11B2;
+const ALBUM_SLOTS: usize = 30;
+/// Album slot `n` is stored at 0x2000 + n * 0x1000, starting with the image
+const ALBUM_START: usize = 0x2000;
+const ALBUM_SLOT_SIZE: usize = 0x1000;
+
+/// $A000 Bit 0 starts a capture, and reads as 1 until it is done
+const REGISTER_CAPTURE: usize = 0x00;
+/// $A001 Bit 7 N, bits 5-6 VH (edge enhancement when both are set with N), bits 0-4 gain
+const REGISTER_GAIN: usize = 0x01;
+/// $A002-$A003 The exposure time, big-endian
+const REGISTER_EXPOSURE_HIGH: usize = 0x02;
+const REGISTER_EXPOSURE_LOW: usize = 0x03;
+/// $A004 Bits 4-6 select the edge enhancement ratio
+const REGISTER_EDGE: usize = 0x04;
+/// $A006-$A035 The dither matrix, three thresholds for each pixel of a 4×4 block
+const REGISTER_DITHER_MATRIX: usize = 0x06;
+const REGISTER_COUNT: usize = 0x36;
+
+const EDGE_RATIOS: [f32; 8] = [0.5, 0.75, 1.0, 1.25, 2.0, 3.0, 4.0, 5.0];
+
+/// Provides the image that the camera sensor sees.
+pub trait ImageSource {
+    /// Returns the brightness of a pixel of the 128×112 sensor, where 0 is black and 255 is
+    /// white. Called for every pixel of every capture.
+    fn pixel(&mut self, x: usize, y: usize) -> u8;
+}
+
+/// Generator functions of `(x, y)` can be used as a source directly.
+impl<F: FnMut(usize, usize) -> u8> ImageSource for F {
+    fn pixel(&mut self, x: usize, y: usize) -> u8 {
+        self(x, y)
+    }
+}
+
+/// An image loaded from a PNG file, converted to grayscale and scaled to the sensor size.
+pub struct ImageFile {
+    pixels: Vec<u8>,
+}
+
+impl ImageFile {
+    pub fn open(path: impl AsRef<Path>) -> Result<ImageFile, png::DecodingError> {
+        let mut decoder = png::Decoder::new(File::open(path)?);
+        decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+        let mut reader = decoder.read_info()?;
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buffer)?;
+
+        let channels = info.color_type.samples();
+        let luma = |pixel: &[u8]| match pixel.len() {
+            1 | 2 => pixel[0],
+            _ => {
+                ((pixel[0] as u32 * 299 + pixel[1] as u32 * 587 + pixel[2] as u32 * 114) / 1000)
+                    as u8
+            }
+        };
+        let (width, height) = (info.width as usize, info.height as usize);
+        let mut pixels = Vec::with_capacity(SENSOR_WIDTH * SENSOR_HEIGHT);
+        for y in 0..SENSOR_HEIGHT {
+            let row = &buffer[y * height / SENSOR_HEIGHT * info.line_size..];
+            for x in 0..SENSOR_WIDTH {
+                let offset = x * width / SENSOR_WIDTH * channels;
+                pixels.push(luma(&row[offset..offset + channels]));
+            }
+        }
+        Ok(ImageFile { pixels })
+    }
+}
+
+impl ImageSource for ImageFile {
+    fn pixel(&mut self, x: usize, y: usize) -> u8 {
+        self.pixels[y * SENSOR_WIDTH + x]
+    }
+}
+
+/// The source until another one is set: a diagonal gradient.
+fn gradient(x: usize, y: usize) -> u8 {
+    ((x + y) * 255 / (SENSOR_WIDTH + SENSOR_HEIGHT - 2)) as u8
+}
+
+/// The Pocket Camera, or Game Boy Camera, with a 128×112 image sensor and 128 KiB of RAM.
+///
+/// Setting bit 4 of the RAM bank maps the camera registers at 0xA000-0xBFFF instead of the RAM.
+/// A capture is processed with the exposure, gain, edge enhancement and dither matrix from the
+/// registers, and written as tiles to RAM bank 0 at 0xA100.
+pub struct PocketCamera {
+    ram: Vec<u8>,
+    ram_enabled: bool,
+    rom_bank: u8,
+    ram_bank: u8,
+    registers: [u8; REGISTER_COUNT],
+    /// The cycles until the current capture is done
+    capture_cycles: u32,
+    source: Box<dyn ImageSource>,
+}
+
+impl PocketCamera {
+    pub fn new() -> PocketCamera {
+        PocketCamera {
+            ram: vec![0; RAM_SIZE],
+            ram_enabled: false,
+            rom_bank: 1,
+            ram_bank: 0,
+            registers: [0; REGISTER_COUNT],
+            capture_cycles: 0,
+            source: Box::new(gradient),
+        }
+    }
+
+    fn registers_selected(&self) -> bool {
+        self.ram_bank & 0x10 > 0
+    }
+
+    fn exposure(&self) -> u16 {
+        u16::from_be_bytes([
+            self.registers[REGISTER_EXPOSURE_HIGH],
+            self.registers[REGISTER_EXPOSURE_LOW],
+        ])
+    }
+
+    fn start_capture(&mut self) {
+        let n = self.registers[REGISTER_GAIN] & 0x80 > 0;
+        self.capture_cycles = 32446 + if n { 0 } else { 512 } + 16 * self.exposure() as u32;
+    }
+
+    fn finish_capture(&mut self) {
+        let mut image = vec![0; SENSOR_WIDTH * SENSOR_HEIGHT];
+        for (index, pixel) in image.iter_mut().enumerate() {
+            *pixel = self
+                .source
+                .pixel(index % SENSOR_WIDTH, index / SENSOR_WIDTH);
+        }
+        let tiles = process(&self.registers, &image);
+        self.ram[CAPTURE_OFFSET..CAPTURE_OFFSET + IMAGE_SIZE].copy_from_slice(&tiles);
+        self.registers[REGISTER_CAPTURE] &= !1;
+    }
+}
+
+impl Default for PocketCamera {
+    fn default() -> PocketCamera {
+        PocketCamera::new()
+    }
+}
+
+/// Runs the capture pipeline of the sensor on a 128×112 grayscale image, and returns the image as
+/// 2bpp tiles.
+///
+/// The brightness is scaled by the exposure time (0x1000 keeps it as is) and approximately by the
+/// gain. With N and VH set, the difference with the 4 neighbouring pixels is added to each pixel,
+/// scaled by the edge ratio. Finally every pixel is compared with the three thresholds of its
+/// position in the 4×4 dither matrix.
+pub fn process(registers: &[u8], image: &[u8]) -> Vec<u8> {
+    let exposure = u16::from_be_bytes([
+        registers[REGISTER_EXPOSURE_HIGH],
+        registers[REGISTER_EXPOSURE_LOW],
+    ]);
+    let gain = 1.0 + ((registers[REGISTER_GAIN] & 0x1F) as f32 - 4.0) * 0.025;
+    let scale = gain * exposure as f32 / 0x1000 as f32;
+    let exposed = |x: isize, y: isize| {
+        let x = x.clamp(0, SENSOR_WIDTH as isize - 1) as usize;
+        let y = y.clamp(0, SENSOR_HEIGHT as isize - 1) as usize;
+        image[y * SENSOR_WIDTH + x] as f32 * scale
+    };
+    let edge_enhancement = registers[REGISTER_GAIN] & 0xE0 == 0xE0;
+    let edge_ratio = EDGE_RATIOS[(registers[REGISTER_EDGE] >> 4 & 0b111) as usize];
+
+    let mut tiles = vec![0; IMAGE_SIZE];
+    for y in 0..SENSOR_HEIGHT {
+        for x in 0..SENSOR_WIDTH {
+            let (sx, sy) = (x as isize, y as isize);
+            let mut value = exposed(sx, sy);
+            if edge_enhancement {
+                let neighbours = exposed(sx - 1, sy)
+                    + exposed(sx + 1, sy)
+                    + exposed(sx, sy - 1)
+                    + exposed(sx, sy + 1);
+                value += (value * 4.0 - neighbours) * edge_ratio;
+            }
+
+            let thresholds = REGISTER_DITHER_MATRIX + ((x & 3) + (y & 3) * 4) * 3;
+            let color = match value {
+                value if value < registers[thresholds] as f32 => 3,
+                value if value < registers[thresholds + 1] as f32 => 2,
+                value if value < registers[thresholds + 2] as f32 => 1,
+                _ => 0,
+            };
+
+            let tile = (y / 8) * (SENSOR_WIDTH / 8) + x / 8;
+            let offset = tile * 16 + (y % 8) * 2;
+            let bit = 7 - x % 8;
+            tiles[offset] |= (color & 1) << bit;
+            tiles[offset + 1] |= (color >> 1) << bit;
+        }
+    }
+    tiles
+}
+
+impl Mapper for PocketCamera {
+    fn read_rom(&self, rom: &[u8], address: u16) -> u8 {
+        let bank = if address < 0x4000 {
+            0
+        } else {
+            self.rom_bank as usize
+        };
+        read_rom_bank(rom, bank, address)
+    }
+
+    fn write_rom(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000..=0x1FFF => self.ram_enabled = value & 0x0F == 0x0A,
+            0x2000..=0x3FFF => self.rom_bank = value & 0x3F,
+            0x4000..=0x5FFF => self.ram_bank = value & 0x1F,
+            _ => {}
+        }
+    }
+
+    fn read_ram(&self, address: u16) -> u8 {
+        if self.registers_selected() {
+            // Only the capture register can be read, and the registers repeat every 0x80 bytes
+            return match address as usize & 0x7F {
+                REGISTER_CAPTURE => self.registers[REGISTER_CAPTURE],
+                _ => 0x00,
+            };
+        }
+        self.ram[ram_offset(self.ram.len(), self.ram_bank as usize, address)]
+    }
+
+    fn write_ram(&mut self, address: u16, value: u8) {
+        if self.registers_selected() {
+            match address as usize & 0x7F {
+                REGISTER_CAPTURE => {
+                    let capturing = self.registers[REGISTER_CAPTURE] & 1 > 0;
+                    self.registers[REGISTER_CAPTURE] = value & 0b111;
+                    if value & 1 > 0 && !capturing {
+                        self.start_capture();
+                    }
+                }
+                register if register < REGISTER_COUNT => self.registers[register] = value,
+                _ => {}
+            }
+        } else if self.ram_enabled {
+            let offset = ram_offset(self.ram.len(), self.ram_bank as usize, address);
+            self.ram[offset] = value;
+        }
+    }
+
+    fn tick(&mut self, cycles: u16) {
+        if self.registers[REGISTER_CAPTURE] & 1 == 0 {
+            return;
+        }
+        self.capture_cycles = self.capture_cycles.saturating_sub(cycles as u32);
+        if self.capture_cycles == 0 {
+            self.finish_capture();
+        }
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn ram_mut(&mut self) -> &mut [u8] {
+        &mut self.ram
+    }
+
+    fn set_image_source(&mut self, source: Box<dyn ImageSource>) {
+        self.source = source;
+    }
+}
+
+/// Converts 2bpp tiles of a 128×112 image to grayscale pixels, row by row.
+pub fn image_pixels(tiles: &[u8]) -> Vec<u8> {
+    const SHADES: [u8; 4] = [255, 170, 85, 0];
+    let mut pixels = vec![0; SENSOR_WIDTH * SENSOR_HEIGHT];
+    for (index, pixel) in pixels.iter_mut().enumerate() {
+        let (x, y) = (index % SENSOR_WIDTH, index / SENSOR_WIDTH);
+        let tile = (y / 8) * (SENSOR_WIDTH / 8) + x / 8;
+        let offset = tile * 16 + (y % 8) * 2;
+        let bit = 7 - x % 8;
+        let color = (tiles[offset] >> bit & 1) | (tiles[offset + 1] >> bit & 1) << 1;
+        *pixel = SHADES[color as usize];
+    }
+    pixels
+}
+
+/// Returns the images of the photo album in the save RAM, by album slot.
+pub fn album(ram: &[u8]) -> Vec<(usize, &[u8])> {
+    if ram.len() < RAM_SIZE {
+        return Vec::new();
+    }
+    (0..ALBUM_SLOTS)
+        .filter(|slot| ram[ALBUM_INDEX + slot] != 0xFF)
+        .map(|slot| {
+            let start = ALBUM_START + slot * ALBUM_SLOT_SIZE;
+            (slot, &ram[start..start + IMAGE_SIZE])
+        })
+        .collect()
+}
+
+/// Writes every photo in the album of the save RAM to `directory` as `photo_NN.png`, and returns
+/// the number of photos.
+pub fn export_album(ram: &[u8], directory: impl AsRef<Path>) -> Result<usize, png::EncodingError> {
+    let photos = album(ram);
+    for (slot, tiles) in &photos {
+        let path = directory
+            .as_ref()
+            .join(format!("photo_{:02}.png", slot + 1));
+        let mut encoder = png::Encoder::new(
+            BufWriter::new(File::create(path)?),
+            SENSOR_WIDTH as u32,
+            SENSOR_HEIGHT as u32,
+        );
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&image_pixels(tiles))?;
+    }
+    Ok(photos.len())
+}
+
+#[test]
+fn camera_capture() {
+    let mut camera = PocketCamera::new();
+    camera.set_image_source(Box::new(|x: usize, _y: usize| if x < 64 { 0 } else { 255 }));
+    camera.write_rom(0x4000, 0x10);
+    camera.write_ram(0xA002, 0x10);
+    camera.write_ram(0xA003, 0x00);
+    for (index, threshold) in [0x40, 0x80, 0xC0].iter().cycle().take(48).enumerate() {
+        camera.write_ram(0xA006 + index as u16, *threshold);
+    }
+    assert_eq!(camera.read_ram(0xA006), 0x00, "only 0xA000 can be read");
+
+    camera.write_ram(0xA000, 0x01);
+    assert_eq!(camera.read_ram(0xA000), 0x01);
+    for _ in 0..=(32446 + 512 + 16 * 0x1000) / 4 {
+        camera.tick(4);
+    }
+    assert_eq!(camera.read_ram(0xA000), 0x00, "the capture is done");
+
+    camera.write_rom(0x4000, 0x00);
+    let pixels = image_pixels(&camera.ram()[CAPTURE_OFFSET..CAPTURE_OFFSET + IMAGE_SIZE]);
+    assert_eq!(pixels[0], 0, "black");
+    assert_eq!(pixels[SENSOR_WIDTH - 1], 255, "white");
+    assert_eq!(pixels[SENSOR_WIDTH * SENSOR_HEIGHT - 1], 255);
+
+    // Copy the capture into the first album slot
+    let mut ram = camera.ram().to_vec();
+    ram[ALBUM_INDEX..ALBUM_INDEX + ALBUM_SLOTS]
+        .iter_mut()
+        .for_each(|slot| *slot = 0xFF);
+    ram[ALBUM_INDEX] = 0;
+    ram.copy_within(CAPTURE_OFFSET..CAPTURE_OFFSET + IMAGE_SIZE, ALBUM_START);
+    let album = album(&ram);
+    assert_eq!(album.len(), 1);
+    assert_eq!(image_pixels(album[0].1), pixels);
+}
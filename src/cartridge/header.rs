@@ -0,0 +1,323 @@
+use core::{fmt, ops::RangeInclusive};
+
+/// $0134-$0143 Title, the last bytes are reused for the manufacturer code and CGB flag
+const TITLE: RangeInclusive<usize> = 0x0134..=0x0143;
+/// $013F-$0142 Manufacturer code, only present in newer cartridges
+const MANUFACTURER_CODE: RangeInclusive<usize> = 0x013F..=0x0142;
+/// $0143 CGB flag
+const CGB_FLAG: usize = 0x0143;
+/// $0144-$0145 New licensee code, used when the old licensee code is 0x33
+const NEW_LICENSEE_CODE: RangeInclusive<usize> = 0x0144..=0x0145;
+/// $0146 SGB flag
+const SGB_FLAG: usize = 0x0146;
+/// $0147 Cartridge type
+const CARTRIDGE_TYPE: usize = 0x0147;
+/// $0148 ROM size
+const ROM_SIZE: usize = 0x0148;
+/// $0149 RAM size
+const RAM_SIZE: usize = 0x0149;
+/// $014A Destination code
+const DESTINATION_CODE: usize = 0x014A;
+/// $014B Old licensee code
+const OLD_LICENSEE_CODE: usize = 0x014B;
+/// $014C Mask ROM version number
+const VERSION: usize = 0x014C;
+/// $014D Header checksum, over $0134-$014C
+const HEADER_CHECKSUM: usize = 0x014D;
+/// $014E-$014F Global checksum, big-endian
+const GLOBAL_CHECKSUM: RangeInclusive<usize> = 0x014E..=0x014F;
+
+/// The size of the cartridge header, including the entry point and logo before it.
+pub const HEADER_SIZE: usize = 0x0150;
+
+/// The cartridge header at $0100-$014F.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Header {
+    pub title: String,
+    /// The 4 character manufacturer code of newer cartridges
+    pub manufacturer_code: Option<String>,
+    pub cgb_support: CgbSupport,
+    /// `true` if the cartridge supports Super Game Boy functions
+    pub sgb_support: bool,
+    pub cartridge_type: CartridgeType,
+    /// The size of the ROM in bytes
+    pub rom_size: usize,
+    /// The size of the external RAM in bytes. This does not include RAM that is built into the
+    /// mapper, like the RAM of MBC2.
+    pub ram_size: usize,
+    /// `true` if the cartridge is meant to be sold in Japan
+    pub japanese: bool,
+    pub licensee: Licensee,
+    pub version: u8,
+    pub header_checksum: u8,
+    pub global_checksum: u16,
+}
+
+impl Header {
+    /// Parses the header of a ROM image. This validates the header checksum, just like the boot
+    /// ROM does.
+    pub fn parse(rom: &[u8]) -> Result<Header, HeaderError> {
+        if rom.len() < HEADER_SIZE {
+            return Err(HeaderError::TooSmall(rom.len()));
+        }
+
+        let header_checksum = rom[HEADER_CHECKSUM];
+        let calculated = header_checksum_of(rom);
+        if header_checksum != calculated {
+            return Err(HeaderError::HeaderChecksum {
+                expected: header_checksum,
+                calculated,
+            });
+        }
+
+        let cgb_support = match rom[CGB_FLAG] {
+            0xC0 => CgbSupport::Required,
+            flag if flag & 0x80 > 0 => CgbSupport::Supported,
+            _ => CgbSupport::None,
+        };
+
+        // Newer cartridges use the last 5 bytes of the title for the manufacturer code and the
+        // CGB flag. Older cartridges use the entire area for the title.
+        let manufacturer_code = &rom[MANUFACTURER_CODE];
+        let (title, manufacturer_code) = if cgb_support != CgbSupport::None
+            && manufacturer_code.iter().all(u8::is_ascii_uppercase)
+        {
+            let code = String::from_utf8_lossy(manufacturer_code).into_owned();
+            (&rom[*TITLE.start()..*MANUFACTURER_CODE.start()], Some(code))
+        } else if cgb_support != CgbSupport::None {
+            (&rom[*TITLE.start()..CGB_FLAG], None)
+        } else {
+            (&rom[TITLE], None)
+        };
+        let title = title
+            .iter()
+            .take_while(|&&byte| byte != 0)
+            .map(|&byte| {
+                if byte.is_ascii_graphic() {
+                    byte as char
+                } else {
+                    ' '
+                }
+            })
+            .collect::<String>()
+            .trim_end()
+            .to_owned();
+
+        let cartridge_type = CartridgeType::from_code(rom[CARTRIDGE_TYPE])
+            .ok_or(HeaderError::UnknownCartridgeType(rom[CARTRIDGE_TYPE]))?;
+        let rom_size = match rom[ROM_SIZE] {
+            code @ 0x00..=0x08 => 0x8000 << code,
+            0x52 => 72 * 0x4000,
+            0x53 => 80 * 0x4000,
+            0x54 => 96 * 0x4000,
+            code => return Err(HeaderError::UnknownRomSize(code)),
+        };
+        let ram_size = match rom[RAM_SIZE] {
+            0x00 => 0,
+            0x01 => 0x800,
+            0x02 => 0x2000,
+            0x03 => 0x8000,
+            0x04 => 0x2_0000,
+            0x05 => 0x1_0000,
+            code => return Err(HeaderError::UnknownRamSize(code)),
+        };
+
+        let licensee = match rom[OLD_LICENSEE_CODE] {
+            0x33 => {
+                let code = &rom[NEW_LICENSEE_CODE];
+                Licensee::New([code[0], code[1]])
+            }
+            code => Licensee::Old(code),
+        };
+
+        Ok(Header {
+            title,
+            manufacturer_code,
+            cgb_support,
+            sgb_support: rom[SGB_FLAG] == 0x03,
+            cartridge_type,
+            rom_size,
+            ram_size,
+            japanese: rom[DESTINATION_CODE] == 0x00,
+            licensee,
+            version: rom[VERSION],
+            header_checksum,
+            global_checksum: (rom[*GLOBAL_CHECKSUM.start()] as u16) << 8
+                | rom[*GLOBAL_CHECKSUM.end()] as u16,
+        })
+    }
+
+    /// The number of 16 KiB ROM banks.
+    pub fn rom_banks(&self) -> usize {
+        self.rom_size / 0x4000
+    }
+}
+
+/// Calculates the header checksum over $0134-$014C, the way the boot ROM does.
+pub fn header_checksum_of(rom: &[u8]) -> u8 {
+    rom[*TITLE.start()..HEADER_CHECKSUM]
+        .iter()
+        .fold(0u8, |checksum, &byte| {
+            checksum.wrapping_sub(byte).wrapping_sub(1)
+        })
+}
+
+/// Calculates the global checksum, the sum of all bytes except the checksum itself. This is not
+/// verified by the hardware, and many cartridges get it wrong.
+pub fn global_checksum_of(rom: &[u8]) -> u16 {
+    rom.iter()
+        .enumerate()
+        .filter(|(index, _)| !GLOBAL_CHECKSUM.contains(index))
+        .fold(0u16, |checksum, (_, &byte)| {
+            checksum.wrapping_add(byte as u16)
+        })
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum CgbSupport {
+    /// A Game Boy cartridge
+    None,
+    /// The cartridge uses CGB functions, but also works on a Game Boy
+    Supported,
+    /// The cartridge only works on a CGB
+    Required,
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Licensee {
+    Old(u8),
+    /// Two ASCII characters, used when the old licensee code is 0x33
+    New([u8; 2]),
+}
+
+/// The memory bank controller, or other hardware, that the cartridge is built around.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum MapperKind {
+    RomOnly,
+    Mbc1,
+    Mbc2,
+    Mmm01,
+    Mbc3,
+    Mbc5,
+    Mbc6,
+    Mbc7,
+    PocketCamera,
+    Tama5,
+    HuC3,
+    HuC1,
+}
+
+/// The cartridge type byte at $0147, which describes the mapper and the extra hardware on the
+/// cartridge.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct CartridgeType {
+    pub code: u8,
+    pub mapper: MapperKind,
+    pub ram: bool,
+    /// Battery backed RAM (or RTC) that should be saved
+    pub battery: bool,
+    /// A real time clock
+    pub timer: bool,
+    pub rumble: bool,
+    /// The accelerometer of MBC7
+    pub sensor: bool,
+}
+
+impl CartridgeType {
+    pub fn from_code(code: u8) -> Option<CartridgeType> {
+        use self::MapperKind::*;
+
+        const RAM: u8 = 1 << 0;
+        const BATTERY: u8 = 1 << 1;
+        const TIMER: u8 = 1 << 2;
+        const RUMBLE: u8 = 1 << 3;
+        const SENSOR: u8 = 1 << 4;
+
+        let (mapper, features) = match code {
+            0x00 => (RomOnly, 0),
+            0x01 => (Mbc1, 0),
+            0x02 => (Mbc1, RAM),
+            0x03 => (Mbc1, RAM | BATTERY),
+            0x05 => (Mbc2, 0),
+            0x06 => (Mbc2, BATTERY),
+            0x08 => (RomOnly, RAM),
+            0x09 => (RomOnly, RAM | BATTERY),
+            0x0B => (Mmm01, 0),
+            0x0C => (Mmm01, RAM),
+            0x0D => (Mmm01, RAM | BATTERY),
+            0x0F => (Mbc3, TIMER | BATTERY),
+            0x10 => (Mbc3, TIMER | RAM | BATTERY),
+            0x11 => (Mbc3, 0),
+            0x12 => (Mbc3, RAM),
+            0x13 => (Mbc3, RAM | BATTERY),
+            0x19 => (Mbc5, 0),
+            0x1A => (Mbc5, RAM),
+            0x1B => (Mbc5, RAM | BATTERY),
+            0x1C => (Mbc5, RUMBLE),
+            0x1D => (Mbc5, RUMBLE | RAM),
+            0x1E => (Mbc5, RUMBLE | RAM | BATTERY),
+            0x20 => (Mbc6, 0),
+            0x22 => (Mbc7, SENSOR | RUMBLE | RAM | BATTERY),
+            0xFC => (PocketCamera, RAM | BATTERY),
+            0xFD => (Tama5, 0),
+            0xFE => (HuC3, TIMER | RAM | BATTERY),
+            0xFF => (HuC1, RAM | BATTERY),
+            _ => return None,
+        };
+
+        Some(CartridgeType {
+            code,
+            mapper,
+            ram: features & RAM > 0,
+            battery: features & BATTERY > 0,
+            timer: features & TIMER > 0,
+            rumble: features & RUMBLE > 0,
+            sensor: features & SENSOR > 0,
+        })
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum HeaderError {
+    /// The ROM is too small to contain a header
+    TooSmall(usize),
+    HeaderChecksum {
+        expected: u8,
+        calculated: u8,
+    },
+    UnknownCartridgeType(u8),
+    UnknownRomSize(u8),
+    UnknownRamSize(u8),
+    /// The mapper is not emulated yet
+    UnsupportedMapper(MapperKind),
+}
+
+impl fmt::Display for HeaderError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HeaderError::TooSmall(len) => write!(
+                fmt,
+                "the rom is 0x{:X} bytes, which is too small to contain a header",
+                len
+            ),
+            HeaderError::HeaderChecksum {
+                expected,
+                calculated,
+            } => write!(
+                fmt,
+                "the header checksum is 0x{:02X}, but the header adds up to 0x{:02X}",
+                expected, calculated
+            ),
+            HeaderError::UnknownCartridgeType(code) => {
+                write!(fmt, "unknown cartridge type 0x{:02X}", code)
+            }
+            HeaderError::UnknownRomSize(code) => write!(fmt, "unknown rom size 0x{:02X}", code),
+            HeaderError::UnknownRamSize(code) => write!(fmt, "unknown ram size 0x{:02X}", code),
+            HeaderError::UnsupportedMapper(mapper) => {
+                write!(fmt, "the {:?} mapper is not supported", mapper)
+            }
+        }
+    }
+}
+
+impl std::error::Error for HeaderError {}
@@ -0,0 +1,107 @@
+use super::{ram_offset, read_rom_bank, Mapper};
+
+/// HuC1, Hudson's mapper with up to 1 MiB of ROM, 32 KiB of RAM and an infrared port.
+///
+/// Writing 0x0E to 0x0000-0x1FFF maps the infrared port at 0xA000-0xBFFF instead of the RAM.
+pub struct HuC1 {
+    ram: Vec<u8>,
+    infrared_selected: bool,
+    rom_bank: u8,
+    ram_bank: u8,
+    /// Whether the LED is on
+    infrared_led: bool,
+    /// Whether the sensor receives light
+    infrared_light: bool,
+}
+
+impl HuC1 {
+    pub fn new(ram_size: usize) -> HuC1 {
+        HuC1 {
+            ram: vec![0; ram_size],
+            infrared_selected: false,
+            rom_bank: 1,
+            ram_bank: 0,
+            infrared_led: false,
+            infrared_light: false,
+        }
+    }
+}
+
+impl Mapper for HuC1 {
+    fn read_rom(&self, rom: &[u8], address: u16) -> u8 {
+        let bank = if address < 0x4000 {
+            0
+        } else {
+            self.rom_bank as usize
+        };
+        read_rom_bank(rom, bank, address)
+    }
+
+    fn write_rom(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000..=0x1FFF => self.infrared_selected = value & 0x0F == 0x0E,
+            0x2000..=0x3FFF => {
+                self.rom_bank = match value & 0x3F {
+                    0 => 1,
+                    bank => bank,
+                }
+            }
+            0x4000..=0x5FFF => self.ram_bank = value & 0b11,
+            _ => {}
+        }
+    }
+
+    fn read_ram(&self, address: u16) -> u8 {
+        if self.infrared_selected {
+            // 0xC1 when light is seen, 0xC0 otherwise
+            return 0xC0 | self.infrared_light as u8;
+        }
+        if self.ram.is_empty() {
+            return 0xFF;
+        }
+        self.ram[ram_offset(self.ram.len(), self.ram_bank as usize, address)]
+    }
+
+    fn write_ram(&mut self, address: u16, value: u8) {
+        if self.infrared_selected {
+            self.infrared_led = value & 1 > 0;
+        } else if !self.ram.is_empty() {
+            let offset = ram_offset(self.ram.len(), self.ram_bank as usize, address);
+            self.ram[offset] = value;
+        }
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn ram_mut(&mut self) -> &mut [u8] {
+        &mut self.ram
+    }
+
+    fn infrared_led(&self) -> bool {
+        self.infrared_led
+    }
+
+    fn set_infrared_light(&mut self, light: bool) {
+        self.infrared_light = light;
+    }
+}
+
+#[test]
+fn huc1_infrared() {
+    let mut mbc = HuC1::new(0x8000);
+    mbc.write_rom(0x4000, 0x02);
+    mbc.write_ram(0xA000, 0x12);
+    assert_eq!(mbc.ram()[2 * super::RAM_BANK_SIZE], 0x12);
+
+    mbc.write_rom(0x0000, 0x0E);
+    mbc.write_ram(0xA000, 0x01);
+    assert!(mbc.infrared_led());
+    assert_eq!(mbc.read_ram(0xA000), 0xC0);
+    mbc.set_infrared_light(true);
+    assert_eq!(mbc.read_ram(0xA000), 0xC1);
+
+    mbc.write_rom(0x0000, 0x0A);
+    assert_eq!(mbc.read_ram(0xA000), 0x12);
+}
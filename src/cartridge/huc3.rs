@@ -0,0 +1,268 @@
+use super::{
+    ram_offset, read_rom_bank,
+    rtc::{RtcClock, Timer},
+    Mapper,
+};
+use std::convert::TryInto;
+
+const MINUTES_PER_DAY: u16 = 24 * 60;
+
+/// The size of the clock state that is appended to the save RAM: the minutes and days as 16-bit
+/// values, followed by a 64-bit UNIX timestamp, all little-endian.
+const CLOCK_SAVE_SIZE: usize = 12;
+
+/// The value written to 0x0000-0x1FFF, which selects what is mapped at 0xA000-0xBFFF
+const MODE_RAM_READ: u8 = 0x0;
+const MODE_RAM: u8 = 0xA;
+/// Send a command to the clock
+const MODE_COMMAND: u8 = 0xB;
+/// Read the response to the last command
+const MODE_RESPONSE: u8 = 0xC;
+/// Read whether the clock is ready for the next command
+const MODE_SEMAPHORE: u8 = 0xD;
+const MODE_INFRARED: u8 = 0xE;
+
+/// Reads a nibble and increments the address
+const COMMAND_READ: u8 = 0x1;
+/// Writes a nibble and increments the address
+const COMMAND_WRITE: u8 = 0x3;
+const COMMAND_ADDRESS_LOW: u8 = 0x4;
+const COMMAND_ADDRESS_HIGH: u8 = 0x5;
+/// Runs the extended command in the argument
+const COMMAND_EXTENDED: u8 = 0x6;
+
+/// Copies the clock to the nibbles at 0x00-0x05 of the clock memory
+const EXTENDED_LATCH: u8 = 0x0;
+/// Sets the clock from the nibbles at 0x00-0x05 of the clock memory
+const EXTENDED_SET: u8 = 0x1;
+/// Returns 1 in the response
+const EXTENDED_STATUS: u8 = 0x2;
+
+/// HuC3, Hudson's mapper with up to 2 MiB of ROM, 32 KiB of RAM, a real time clock and an
+/// infrared port.
+///
+/// The clock is a separate chip that is controlled with 4-bit commands. It counts the minute of
+/// the day and a 12-bit day counter, and has 256 nibbles of memory where the time is latched.
+pub struct HuC3 {
+    ram: Vec<u8>,
+    mode: u8,
+    rom_bank: u8,
+    ram_bank: u8,
+
+    seconds: u8,
+    minutes: u16,
+    days: u16,
+    timer: Timer,
+    clock_memory: [u8; 0x100],
+    clock_address: u8,
+    /// The last command and its result, as read in `MODE_RESPONSE`
+    response: u8,
+
+    infrared_led: bool,
+    infrared_light: bool,
+}
+
+impl HuC3 {
+    pub fn new(ram_size: usize, clock: RtcClock) -> HuC3 {
+        HuC3 {
+            ram: vec![0; ram_size],
+            mode: MODE_RAM_READ,
+            rom_bank: 1,
+            ram_bank: 0,
+            seconds: 0,
+            minutes: 0,
+            days: 0,
+            timer: Timer::new(clock),
+            clock_memory: [0; 0x100],
+            clock_address: 0,
+            response: 0,
+            infrared_led: false,
+            infrared_light: false,
+        }
+    }
+
+    /// The minute of the day and the day counter of the clock.
+    pub fn time(&self) -> (u16, u16) {
+        (self.minutes, self.days)
+    }
+
+    fn advance(&mut self, seconds: u64) {
+        let seconds = self.seconds as u64 + seconds;
+        let minutes = self.minutes as u64 + seconds / 60;
+        self.seconds = (seconds % 60) as u8;
+        self.minutes = (minutes % MINUTES_PER_DAY as u64) as u16;
+        self.days = ((self.days as u64 + minutes / MINUTES_PER_DAY as u64) & 0xFFF) as u16;
+    }
+
+    fn command(&mut self, value: u8) {
+        let command = value >> 4 & 0b111;
+        let argument = value & 0x0F;
+        let mut result = 0;
+        match command {
+            COMMAND_READ => {
+                result = self.clock_memory[self.clock_address as usize];
+                self.clock_address = self.clock_address.wrapping_add(1);
+            }
+            COMMAND_WRITE => {
+                self.clock_memory[self.clock_address as usize] = argument;
+                self.clock_address = self.clock_address.wrapping_add(1);
+            }
+            COMMAND_ADDRESS_LOW => self.clock_address = self.clock_address & 0xF0 | argument,
+            COMMAND_ADDRESS_HIGH => self.clock_address = self.clock_address & 0x0F | argument << 4,
+            COMMAND_EXTENDED => match argument {
+                EXTENDED_LATCH => {
+                    let seconds = self.timer.elapsed();
+                    self.advance(seconds);
+                    for nibble in 0..3 {
+                        self.clock_memory[nibble] = (self.minutes >> (nibble * 4)) as u8 & 0x0F;
+                        self.clock_memory[nibble + 3] = (self.days >> (nibble * 4)) as u8 & 0x0F;
+                    }
+                }
+                EXTENDED_SET => {
+                    let memory = &self.clock_memory;
+                    let nibbles = |start: usize| {
+                        (0..3).fold(0, |value, nibble| {
+                            value | (memory[start + nibble] as u16) << (nibble * 4)
+                        })
+                    };
+                    let (minutes, days) = (nibbles(0), nibbles(3));
+                    self.minutes = minutes % MINUTES_PER_DAY;
+                    self.days = days;
+                    self.seconds = 0;
+                    self.timer.reset();
+                }
+                EXTENDED_STATUS => result = 1,
+                _ => {}
+            },
+            _ => {}
+        }
+        self.response = command << 4 | result;
+    }
+}
+
+impl Mapper for HuC3 {
+    fn read_rom(&self, rom: &[u8], address: u16) -> u8 {
+        let bank = if address < 0x4000 {
+            0
+        } else {
+            self.rom_bank as usize
+        };
+        read_rom_bank(rom, bank, address)
+    }
+
+    fn write_rom(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000..=0x1FFF => self.mode = value & 0x0F,
+            0x2000..=0x3FFF => {
+                self.rom_bank = match value & 0x7F {
+                    0 => 1,
+                    bank => bank,
+                }
+            }
+            0x4000..=0x5FFF => self.ram_bank = value & 0b11,
+            _ => {}
+        }
+    }
+
+    fn read_ram(&self, address: u16) -> u8 {
+        match self.mode {
+            MODE_RAM_READ | MODE_RAM if !self.ram.is_empty() => {
+                self.ram[ram_offset(self.ram.len(), self.ram_bank as usize, address)]
+            }
+            MODE_RESPONSE => 0x80 | self.response,
+            // The clock executes commands immediately, so it is always ready
+            MODE_SEMAPHORE => 0xFF,
+            MODE_INFRARED => 0xC0 | self.infrared_light as u8,
+            _ => 0xFF,
+        }
+    }
+
+    fn write_ram(&mut self, address: u16, value: u8) {
+        match self.mode {
+            MODE_RAM if !self.ram.is_empty() => {
+                let offset = ram_offset(self.ram.len(), self.ram_bank as usize, address);
+                self.ram[offset] = value;
+            }
+            MODE_COMMAND => self.command(value),
+            MODE_INFRARED => self.infrared_led = value & 1 > 0,
+            _ => {}
+        }
+    }
+
+    fn tick(&mut self, cycles: u16) {
+        let seconds = self.timer.tick(cycles);
+        self.advance(seconds);
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn ram_mut(&mut self) -> &mut [u8] {
+        &mut self.ram
+    }
+
+    /// The RAM, followed by the state of the clock.
+    fn save_data(&self) -> Vec<u8> {
+        let mut data = self.ram.clone();
+        data.extend_from_slice(&self.minutes.to_le_bytes());
+        data.extend_from_slice(&self.days.to_le_bytes());
+        data.extend_from_slice(&self.timer.timestamp().to_le_bytes());
+        data
+    }
+
+    fn load_save_data(&mut self, data: &[u8]) {
+        let len = self.ram.len().min(data.len());
+        self.ram[..len].copy_from_slice(&data[..len]);
+
+        let clock = &data[len..];
+        if clock.len() >= CLOCK_SAVE_SIZE {
+            self.minutes = u16::from_le_bytes([clock[0], clock[1]]) % MINUTES_PER_DAY;
+            self.days = u16::from_le_bytes([clock[2], clock[3]]) & 0xFFF;
+            self.seconds = 0;
+            self.timer
+                .set_timestamp(u64::from_le_bytes(clock[4..12].try_into().unwrap()));
+            let seconds = self.timer.elapsed();
+            self.advance(seconds);
+        }
+    }
+
+    fn infrared_led(&self) -> bool {
+        self.infrared_led
+    }
+
+    fn set_infrared_light(&mut self, light: bool) {
+        self.infrared_light = light;
+    }
+}
+
+#[test]
+fn huc3_clock_commands() {
+    let mut mbc = HuC3::new(0x8000, RtcClock::EmulatedCycles);
+    mbc.write_rom(0x0000, MODE_COMMAND);
+    // Write 1439 minutes (0x59F) and 2 days to the clock memory, and set the clock
+    for value in [0x40, 0x50, 0x3F, 0x39, 0x35, 0x32, 0x30, 0x30, 0x61] {
+        mbc.write_ram(0xA000, value);
+    }
+    assert_eq!(mbc.time(), (1439, 2));
+
+    for _ in 0..60 * 4_194_304 / 16 {
+        mbc.tick(16);
+    }
+    assert_eq!(mbc.time(), (0, 3));
+
+    // Latch the time and read the day counter back
+    for value in [0x60, 0x43, 0x50, 0x10] {
+        mbc.write_ram(0xA000, value);
+    }
+    mbc.write_rom(0x0000, MODE_RESPONSE);
+    assert_eq!(mbc.read_ram(0xA000), 0x93);
+    mbc.write_rom(0x0000, MODE_SEMAPHORE);
+    assert_eq!(mbc.read_ram(0xA000), 0xFF);
+
+    let data = mbc.save_data();
+    assert_eq!(data.len(), 0x8000 + CLOCK_SAVE_SIZE);
+    let mut loaded = HuC3::new(0x8000, RtcClock::EmulatedCycles);
+    loaded.load_save_data(&data);
+    assert_eq!(loaded.time(), (0, 3));
+}
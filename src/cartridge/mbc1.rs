@@ -0,0 +1,203 @@
+use super::{ram_offset, read_rom_bank, Mapper, ROM_BANK_SIZE};
+
+/// $0104-$0133 The Nintendo logo, which MBC1M multicarts repeat in the header of every game
+const LOGO: core::ops::Range<usize> = 0x0104..0x0134;
+
+/// MBC1, which supports up to 2 MiB of ROM and 32 KiB of RAM.
+///
+/// The 5-bit BANK1 register selects the ROM bank at 0x4000-0x7FFF, and the 2-bit BANK2 register
+/// holds either the upper bits of the ROM bank or the RAM bank. In the advanced banking mode
+/// BANK2 is also applied to 0x0000-0x3FFF and to the RAM.
+pub struct Mbc1 {
+    ram: Vec<u8>,
+    ram_enabled: bool,
+    /// BANK1, the lower 5 bits of the ROM bank. Writing 0 selects bank 1 instead.
+    rom_bank: u8,
+    /// BANK2, the upper 2 bits of the ROM bank, or the RAM bank
+    upper_bank: u8,
+    /// MODE, set to apply BANK2 to 0x0000-0x3FFF and the RAM
+    advanced_banking: bool,
+    /// MBC1M multicarts only connect the lower 4 bits of BANK1, so BANK2 selects one of the
+    /// 256 KiB games
+    multicart: bool,
+}
+
+impl Mbc1 {
+    pub fn new(rom: &[u8], ram_size: usize) -> Mbc1 {
+        Mbc1 {
+            ram: vec![0; ram_size],
+            ram_enabled: false,
+            rom_bank: 1,
+            upper_bank: 0,
+            advanced_banking: false,
+            multicart: is_multicart(rom),
+        }
+    }
+
+    fn upper_bank_shift(&self) -> u8 {
+        if self.multicart {
+            4
+        } else {
+            5
+        }
+    }
+
+    fn ram_bank(&self) -> usize {
+        if self.advanced_banking {
+            self.upper_bank as usize
+        } else {
+            0
+        }
+    }
+}
+
+/// MBC1M multicarts are 1 MiB, and contain a game with its own header every 256 KiB.
+fn is_multicart(rom: &[u8]) -> bool {
+    let second_game = 0x10 * ROM_BANK_SIZE;
+    rom.len() == 0x40 * ROM_BANK_SIZE
+        && rom[LOGO] == rom[second_game + LOGO.start..second_game + LOGO.end]
+}
+
+impl Mapper for Mbc1 {
+    fn read_rom(&self, rom: &[u8], address: u16) -> u8 {
+        let upper_bank = (self.upper_bank as usize) << self.upper_bank_shift();
+        let bank = if address < 0x4000 {
+            if self.advanced_banking {
+                upper_bank
+            } else {
+                0
+            }
+        } else if self.multicart {
+            upper_bank | (self.rom_bank & 0x0F) as usize
+        } else {
+            upper_bank | self.rom_bank as usize
+        };
+        read_rom_bank(rom, bank, address)
+    }
+
+    fn write_rom(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000..=0x1FFF => self.ram_enabled = value & 0x0F == 0x0A,
+            0x2000..=0x3FFF => {
+                // The check for bank 0 happens on all 5 bits, so the upper bits of the ROM bank
+                // can never select banks 0x20, 0x40 or 0x60.
+                self.rom_bank = match value & 0x1F {
+                    0 => 1,
+                    bank => bank,
+                }
+            }
+            0x4000..=0x5FFF => self.upper_bank = value & 0b11,
+            _ => self.advanced_banking = value & 1 > 0,
+        }
+    }
+
+    fn read_ram(&self, address: u16) -> u8 {
+        if !self.ram_enabled || self.ram.is_empty() {
+            return 0xFF;
+        }
+        self.ram[ram_offset(self.ram.len(), self.ram_bank(), address)]
+    }
+
+    fn write_ram(&mut self, address: u16, value: u8) {
+        if self.ram_enabled && !self.ram.is_empty() {
+            let offset = ram_offset(self.ram.len(), self.ram_bank(), address);
+            self.ram[offset] = value;
+        }
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn ram_mut(&mut self) -> &mut [u8] {
+        &mut self.ram
+    }
+}
+
+/// Creates a ROM where the first byte of every bank is the bank number.
+#[cfg(test)]
+fn numbered_rom(banks: usize) -> Vec<u8> {
+    let mut rom = vec![0; banks * ROM_BANK_SIZE];
+    for bank in 0..banks {
+        rom[bank * ROM_BANK_SIZE] = bank as u8;
+    }
+    rom
+}
+
+#[test]
+fn mbc1_rom_banking() {
+    let rom = numbered_rom(128);
+    let mut mbc = Mbc1::new(&rom, 0);
+    assert_eq!(mbc.read_rom(&rom, 0x0000), 0);
+    assert_eq!(mbc.read_rom(&rom, 0x4000), 1);
+
+    mbc.write_rom(0x2000, 0x00);
+    assert_eq!(mbc.read_rom(&rom, 0x4000), 1, "bank 0 selects bank 1");
+    mbc.write_rom(0x2000, 0xE5);
+    assert_eq!(mbc.read_rom(&rom, 0x4000), 0x05);
+
+    mbc.write_rom(0x4000, 0x02);
+    assert_eq!(mbc.read_rom(&rom, 0x4000), 0x45);
+    mbc.write_rom(0x2000, 0x20);
+    assert_eq!(
+        mbc.read_rom(&rom, 0x4000),
+        0x41,
+        "bank 0x40 is not reachable"
+    );
+    assert_eq!(mbc.read_rom(&rom, 0x0000), 0x00);
+
+    mbc.write_rom(0x6000, 0x01);
+    assert_eq!(mbc.read_rom(&rom, 0x0000), 0x40);
+
+    // A 256 KiB ROM ignores the upper bits
+    let rom = numbered_rom(16);
+    let mut mbc = Mbc1::new(&rom, 0);
+    mbc.write_rom(0x4000, 0x03);
+    mbc.write_rom(0x2000, 0x13);
+    assert_eq!(mbc.read_rom(&rom, 0x4000), 0x03);
+}
+
+#[test]
+fn mbc1_ram_banking() {
+    let rom = numbered_rom(4);
+    let mut mbc = Mbc1::new(&rom, 0x8000);
+    mbc.write_ram(0xA000, 0x12);
+    assert_eq!(mbc.read_ram(0xA000), 0xFF, "RAM is disabled by default");
+
+    mbc.write_rom(0x0000, 0x0A);
+    mbc.write_ram(0xA000, 0x12);
+    mbc.write_rom(0x4000, 0x01);
+    assert_eq!(mbc.read_ram(0xA000), 0x12, "RAM banking requires mode 1");
+
+    mbc.write_rom(0x6000, 0x01);
+    mbc.write_ram(0xA000, 0x34);
+    assert_eq!(mbc.read_ram(0xA000), 0x34);
+    mbc.write_rom(0x4000, 0x00);
+    assert_eq!(mbc.read_ram(0xA000), 0x12);
+
+    mbc.write_rom(0x0000, 0x00);
+    assert_eq!(mbc.read_ram(0xA000), 0xFF);
+}
+
+#[test]
+fn mbc1_multicart() {
+    let mut rom = numbered_rom(64);
+    for game in 0..4 {
+        let start = game * 0x10 * ROM_BANK_SIZE;
+        rom[start + LOGO.start..start + LOGO.end].copy_from_slice(&[0xCE; 0x30]);
+    }
+    let mut mbc = Mbc1::new(&rom, 0);
+    assert!(mbc.multicart);
+
+    mbc.write_rom(0x4000, 0x01);
+    mbc.write_rom(0x2000, 0x12);
+    assert_eq!(mbc.read_rom(&rom, 0x4000), 0x12);
+    mbc.write_rom(0x2000, 0x10);
+    assert_eq!(
+        mbc.read_rom(&rom, 0x4000),
+        0x10,
+        "bank 0 of the second game"
+    );
+    mbc.write_rom(0x6000, 0x01);
+    assert_eq!(mbc.read_rom(&rom, 0x0000), 0x10);
+}
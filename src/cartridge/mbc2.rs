@@ -0,0 +1,102 @@
+use super::{read_rom_bank, Mapper};
+
+/// The size of the built-in RAM, 512 half-bytes
+const RAM_SIZE: usize = 0x200;
+
+/// MBC2, which supports up to 256 KiB of ROM and has 512×4 bits of RAM built in.
+///
+/// Both registers live at 0x0000-0x3FFF, bit 8 of the address selects between them.
+pub struct Mbc2 {
+    /// Only the lower nibble of every byte is used
+    ram: Vec<u8>,
+    ram_enabled: bool,
+    rom_bank: u8,
+}
+
+impl Mbc2 {
+    pub fn new() -> Mbc2 {
+        Mbc2 {
+            ram: vec![0; RAM_SIZE],
+            ram_enabled: false,
+            rom_bank: 1,
+        }
+    }
+}
+
+impl Default for Mbc2 {
+    fn default() -> Mbc2 {
+        Mbc2::new()
+    }
+}
+
+impl Mapper for Mbc2 {
+    fn read_rom(&self, rom: &[u8], address: u16) -> u8 {
+        let bank = if address < 0x4000 {
+            0
+        } else {
+            self.rom_bank as usize
+        };
+        read_rom_bank(rom, bank, address)
+    }
+
+    fn write_rom(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000..=0x3FFF if address & 0x0100 == 0 => {
+                self.ram_enabled = value & 0x0F == 0x0A;
+            }
+            0x0000..=0x3FFF => {
+                self.rom_bank = match value & 0x0F {
+                    0 => 1,
+                    bank => bank,
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn read_ram(&self, address: u16) -> u8 {
+        if !self.ram_enabled {
+            return 0xFF;
+        }
+        // The upper nibble is not connected and reads as 1s. The RAM is echoed across the
+        // entire 0xA000-0xBFFF range.
+        0xF0 | self.ram[address as usize % RAM_SIZE]
+    }
+
+    fn write_ram(&mut self, address: u16, value: u8) {
+        if self.ram_enabled {
+            self.ram[address as usize % RAM_SIZE] = value & 0x0F;
+        }
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn ram_mut(&mut self) -> &mut [u8] {
+        &mut self.ram
+    }
+}
+
+#[test]
+fn mbc2_registers_and_ram() {
+    let mut rom = vec![0; 16 * super::ROM_BANK_SIZE];
+    for bank in 0..16 {
+        rom[bank * super::ROM_BANK_SIZE] = bank as u8;
+    }
+    let mut mbc = Mbc2::new();
+
+    // Bit 8 set selects the ROM bank register
+    mbc.write_rom(0x2100, 0x05);
+    assert_eq!(mbc.read_rom(&rom, 0x4000), 5);
+    mbc.write_rom(0x0100, 0x00);
+    assert_eq!(mbc.read_rom(&rom, 0x4000), 1, "bank 0 selects bank 1");
+
+    assert_eq!(mbc.read_ram(0xA000), 0xFF);
+    mbc.write_rom(0x3E00, 0x0A);
+    mbc.write_ram(0xA001, 0xAB);
+    assert_eq!(mbc.read_ram(0xA001), 0xFB);
+    assert_eq!(mbc.read_ram(0xA201), 0xFB);
+    assert_eq!(mbc.read_ram(0xBE01), 0xFB);
+    assert_eq!(mbc.ram()[1], 0x0B);
+}
@@ -0,0 +1,168 @@
+use super::{
+    ram_offset, read_rom_bank,
+    rtc::{Rtc, RtcClock, RTC_DAYS_HIGH, RTC_SAVE_SIZE, RTC_SECONDS},
+    Mapper,
+};
+
+/// MBC3, which supports up to 2 MiB of ROM, 32 KiB of RAM and an optional real time clock.
+///
+/// The RAM bank register at 0x4000-0x5FFF selects either a RAM bank (0x00-0x03) or one of the
+/// RTC registers (0x08-0x0C) at 0xA000-0xBFFF.
+pub struct Mbc3 {
+    ram: Vec<u8>,
+    /// Enables both the RAM and the RTC registers
+    ram_enabled: bool,
+    rom_bank: u8,
+    /// The RAM bank or RTC register
+    ram_bank: u8,
+    rtc: Option<Rtc>,
+}
+
+impl Mbc3 {
+    pub fn new(ram_size: usize, rtc: Option<RtcClock>) -> Mbc3 {
+        Mbc3 {
+            ram: vec![0; ram_size],
+            ram_enabled: false,
+            rom_bank: 1,
+            ram_bank: 0,
+            rtc: rtc.map(Rtc::new),
+        }
+    }
+
+    pub fn rtc(&self) -> Option<&Rtc> {
+        self.rtc.as_ref()
+    }
+
+    fn rtc_selected(&self) -> bool {
+        (RTC_SECONDS..=RTC_DAYS_HIGH).contains(&self.ram_bank)
+    }
+}
+
+impl Mapper for Mbc3 {
+    fn read_rom(&self, rom: &[u8], address: u16) -> u8 {
+        let bank = if address < 0x4000 {
+            0
+        } else {
+            self.rom_bank as usize
+        };
+        read_rom_bank(rom, bank, address)
+    }
+
+    fn write_rom(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000..=0x1FFF => self.ram_enabled = value & 0x0F == 0x0A,
+            0x2000..=0x3FFF => {
+                self.rom_bank = match value & 0x7F {
+                    0 => 1,
+                    bank => bank,
+                }
+            }
+            0x4000..=0x5FFF => self.ram_bank = value & 0x0F,
+            _ => {
+                if let Some(rtc) = &mut self.rtc {
+                    rtc.write_latch(value);
+                }
+            }
+        }
+    }
+
+    fn read_ram(&self, address: u16) -> u8 {
+        if !self.ram_enabled {
+            return 0xFF;
+        }
+        if self.rtc_selected() {
+            return self
+                .rtc
+                .as_ref()
+                .map_or(0xFF, |rtc| rtc.read(self.ram_bank));
+        }
+        if self.ram.is_empty() {
+            return 0xFF;
+        }
+        let bank = (self.ram_bank & 0b111) as usize;
+        self.ram[ram_offset(self.ram.len(), bank, address)]
+    }
+
+    fn write_ram(&mut self, address: u16, value: u8) {
+        if !self.ram_enabled {
+            return;
+        }
+        if self.rtc_selected() {
+            if let Some(rtc) = &mut self.rtc {
+                rtc.write(self.ram_bank, value);
+            }
+        } else if !self.ram.is_empty() {
+            let bank = (self.ram_bank & 0b111) as usize;
+            let offset = ram_offset(self.ram.len(), bank, address);
+            self.ram[offset] = value;
+        }
+    }
+
+    fn tick(&mut self, cycles: u16) {
+        if let Some(rtc) = &mut self.rtc {
+            rtc.tick(cycles);
+        }
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn ram_mut(&mut self) -> &mut [u8] {
+        &mut self.ram
+    }
+
+    /// The RAM, followed by the state of the RTC.
+    fn save_data(&self) -> Vec<u8> {
+        let mut data = self.ram.clone();
+        if let Some(rtc) = &self.rtc {
+            data.extend(rtc.save());
+        }
+        data
+    }
+
+    fn load_save_data(&mut self, data: &[u8]) {
+        let len = self.ram.len().min(data.len());
+        self.ram[..len].copy_from_slice(&data[..len]);
+        if let Some(rtc) = &mut self.rtc {
+            let rtc_data = &data[len..];
+            if rtc_data.len() >= RTC_SAVE_SIZE - 4 {
+                rtc.load(rtc_data);
+            }
+        }
+    }
+}
+
+#[test]
+fn mbc3_banking_and_rtc() {
+    let mut rom = vec![0; 128 * super::ROM_BANK_SIZE];
+    rom[0x7F * super::ROM_BANK_SIZE] = 0x7F;
+    let mut mbc = Mbc3::new(0x8000, Some(RtcClock::EmulatedCycles));
+
+    mbc.write_rom(0x2000, 0xFF);
+    assert_eq!(mbc.read_rom(&rom, 0x4000), 0x7F);
+
+    mbc.write_rom(0x0000, 0x0A);
+    mbc.write_rom(0x4000, 0x03);
+    mbc.write_ram(0xA123, 0x42);
+    mbc.write_rom(0x4000, 0x00);
+    assert_eq!(mbc.read_ram(0xA123), 0x00);
+    mbc.write_rom(0x4000, 0x03);
+    assert_eq!(mbc.read_ram(0xA123), 0x42);
+
+    mbc.write_rom(0x4000, 0x09);
+    mbc.write_ram(0xA000, 30);
+    for _ in 0..60 * 4_194_304 / 16 {
+        mbc.tick(16);
+    }
+    mbc.write_rom(0x6000, 0x00);
+    mbc.write_rom(0x6000, 0x01);
+    assert_eq!(mbc.read_ram(0xA000), 31);
+
+    let data = mbc.save_data();
+    assert_eq!(data.len(), 0x8000 + RTC_SAVE_SIZE);
+    let mut loaded = Mbc3::new(0x8000, Some(RtcClock::EmulatedCycles));
+    loaded.load_save_data(&data);
+    assert_eq!(loaded.ram()[3 * 0x2000 + 0x123], 0x42);
+    assert_eq!(loaded.rtc().unwrap().latched().minutes, 31);
+}
@@ -0,0 +1,107 @@
+use super::{ram_offset, read_rom_bank, Mapper};
+
+/// MBC5, which supports up to 8 MiB of ROM and 128 KiB of RAM.
+///
+/// The ROM bank is 9 bits, split over 0x2000-0x2FFF and 0x3000-0x3FFF. Unlike the older mappers,
+/// bank 0 can be mapped at 0x4000-0x7FFF as well. On rumble cartridges bit 3 of the RAM bank
+/// register drives the motor instead of selecting a RAM bank.
+pub struct Mbc5 {
+    ram: Vec<u8>,
+    ram_enabled: bool,
+    rom_bank: u16,
+    ram_bank: u8,
+    has_rumble: bool,
+    rumble: bool,
+}
+
+impl Mbc5 {
+    pub fn new(ram_size: usize, has_rumble: bool) -> Mbc5 {
+        Mbc5 {
+            ram: vec![0; ram_size],
+            ram_enabled: false,
+            rom_bank: 1,
+            ram_bank: 0,
+            has_rumble,
+            rumble: false,
+        }
+    }
+}
+
+impl Mapper for Mbc5 {
+    fn read_rom(&self, rom: &[u8], address: u16) -> u8 {
+        let bank = if address < 0x4000 {
+            0
+        } else {
+            self.rom_bank as usize
+        };
+        read_rom_bank(rom, bank, address)
+    }
+
+    fn write_rom(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000..=0x1FFF => self.ram_enabled = value & 0x0F == 0x0A,
+            0x2000..=0x2FFF => self.rom_bank = self.rom_bank & 0x100 | value as u16,
+            0x3000..=0x3FFF => self.rom_bank = self.rom_bank & 0xFF | (value as u16 & 1) << 8,
+            0x4000..=0x5FFF if self.has_rumble => {
+                self.ram_bank = value & 0b111;
+                self.rumble = value & 0b1000 > 0;
+            }
+            0x4000..=0x5FFF => self.ram_bank = value & 0x0F,
+            _ => {}
+        }
+    }
+
+    fn read_ram(&self, address: u16) -> u8 {
+        if !self.ram_enabled || self.ram.is_empty() {
+            return 0xFF;
+        }
+        self.ram[ram_offset(self.ram.len(), self.ram_bank as usize, address)]
+    }
+
+    fn write_ram(&mut self, address: u16, value: u8) {
+        if self.ram_enabled && !self.ram.is_empty() {
+            let offset = ram_offset(self.ram.len(), self.ram_bank as usize, address);
+            self.ram[offset] = value;
+        }
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn ram_mut(&mut self) -> &mut [u8] {
+        &mut self.ram
+    }
+
+    fn rumble(&self) -> bool {
+        self.rumble
+    }
+}
+
+#[test]
+fn mbc5_banking_and_rumble() {
+    let mut rom = vec![0; 512 * super::ROM_BANK_SIZE];
+    rom[0x1FF * super::ROM_BANK_SIZE] = 0xFF;
+    rom[0x4000] = 1;
+    let mut mbc = Mbc5::new(0x20000, true);
+
+    assert_eq!(mbc.read_rom(&rom, 0x4000), 1);
+    mbc.write_rom(0x2000, 0x00);
+    assert_eq!(mbc.read_rom(&rom, 0x4000), 0, "bank 0 can be mapped");
+    mbc.write_rom(0x2000, 0xFF);
+    mbc.write_rom(0x3000, 0x01);
+    assert_eq!(mbc.read_rom(&rom, 0x4000), 0xFF);
+
+    mbc.write_rom(0x0000, 0x0A);
+    mbc.write_rom(0x4000, 0x0F);
+    assert!(mbc.rumble());
+    mbc.write_ram(0xA000, 0x12);
+    mbc.write_rom(0x4000, 0x07);
+    assert!(!mbc.rumble());
+    assert_eq!(
+        mbc.read_ram(0xA000),
+        0x12,
+        "bit 3 does not select a RAM bank"
+    );
+    assert_eq!(mbc.ram()[7 * super::RAM_BANK_SIZE], 0x12);
+}
@@ -0,0 +1,352 @@
+use super::{read_rom_bank, Mapper};
+use crate::TiltState;
+
+/// The size of the 93LC56 EEPROM, 128 words of 16 bits
+const EEPROM_SIZE: usize = 0x100;
+
+/// The accelerometer value when the Game Boy is held flat
+const ACCELEROMETER_CENTER: f32 = 0x81D0 as f32;
+/// The change in the accelerometer value for a tilt of 1g
+const ACCELEROMETER_SCALE: f32 = 0x70 as f32;
+
+/// Bits of the EEPROM register at 0xAx8x
+const EEPROM_CS: u8 = 0x80;
+const EEPROM_CLK: u8 = 0x40;
+const EEPROM_DI: u8 = 0x02;
+const EEPROM_DO: u8 = 0x01;
+
+/// MBC7, which has a 2-axis accelerometer and a 93LC56 serial EEPROM instead of RAM.
+///
+/// Both 0x0000-0x1FFF (0x0A) and 0x4000-0x5FFF (0x40) have to be written to enable the registers
+/// at 0xA000-0xAFFF, where bits 4-7 of the address select the register.
+pub struct Mbc7 {
+    ram_enabled: bool,
+    registers_enabled: bool,
+    rom_bank: u8,
+    tilt: TiltState,
+    /// Set by writing 0x55 to 0xAx0x, which allows the next write to 0xAx1x to latch
+    latch_erased: bool,
+    x: u16,
+    y: u16,
+    eeprom: Eeprom,
+}
+
+impl Mbc7 {
+    pub fn new() -> Mbc7 {
+        Mbc7 {
+            ram_enabled: false,
+            registers_enabled: false,
+            rom_bank: 1,
+            tilt: TiltState::default(),
+            latch_erased: false,
+            x: 0x8000,
+            y: 0x8000,
+            eeprom: Eeprom::new(),
+        }
+    }
+
+    fn enabled(&self) -> bool {
+        self.ram_enabled && self.registers_enabled
+    }
+}
+
+impl Default for Mbc7 {
+    fn default() -> Mbc7 {
+        Mbc7::new()
+    }
+}
+
+/// Converts a tilt in g to the value of the accelerometer.
+fn accelerometer_value(g: f32) -> u16 {
+    (ACCELEROMETER_CENTER + g.clamp(-1.0, 1.0) * ACCELEROMETER_SCALE) as u16
+}
+
+impl Mapper for Mbc7 {
+    fn read_rom(&self, rom: &[u8], address: u16) -> u8 {
+        let bank = if address < 0x4000 {
+            0
+        } else {
+            self.rom_bank as usize
+        };
+        read_rom_bank(rom, bank, address)
+    }
+
+    fn write_rom(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000..=0x1FFF => self.ram_enabled = value == 0x0A,
+            0x2000..=0x3FFF => self.rom_bank = value & 0x7F,
+            0x4000..=0x5FFF => self.registers_enabled = value == 0x40,
+            _ => {}
+        }
+    }
+
+    fn read_ram(&self, address: u16) -> u8 {
+        if !self.enabled() || address >= 0xB000 {
+            return 0xFF;
+        }
+        match address >> 4 & 0x0F {
+            0x2 => self.x as u8,
+            0x3 => (self.x >> 8) as u8,
+            0x4 => self.y as u8,
+            0x5 => (self.y >> 8) as u8,
+            0x6 => 0x00,
+            0x8 => self.eeprom.read(),
+            _ => 0xFF,
+        }
+    }
+
+    fn write_ram(&mut self, address: u16, value: u8) {
+        if !self.enabled() || address >= 0xB000 {
+            return;
+        }
+        match address >> 4 & 0x0F {
+            0x0 if value == 0x55 => {
+                self.x = 0x8000;
+                self.y = 0x8000;
+                self.latch_erased = true;
+            }
+            0x1 if value == 0xAA && self.latch_erased => {
+                self.x = accelerometer_value(self.tilt.x);
+                self.y = accelerometer_value(self.tilt.y);
+                self.latch_erased = false;
+            }
+            0x8 => self.eeprom.write(value),
+            _ => {}
+        }
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.eeprom.data
+    }
+
+    fn ram_mut(&mut self) -> &mut [u8] {
+        &mut self.eeprom.data
+    }
+
+    fn set_tilt(&mut self, tilt: TiltState) {
+        self.tilt = tilt;
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum EepromState {
+    /// Waiting for a start bit
+    Idle,
+    /// Shifting in the 2-bit opcode and 8-bit address
+    Command,
+    /// Shifting in the data of WRITE, or of WRAL if there is no address
+    Data(Option<u8>),
+    /// Shifting out the word at the address
+    Read(u8),
+    /// Done with the command until the chip is deselected
+    Done,
+}
+
+/// A 93LC56 serial EEPROM in 16-bit mode, which is bit-banged through 0xAx8x.
+///
+/// While CS is high, DI is shifted in on every rising edge of CLK. A command is a start bit,
+/// a 2-bit opcode and an 8-bit address, where the upper bit of the address is unused.
+struct Eeprom {
+    /// The words are stored little-endian
+    data: Vec<u8>,
+    /// The last value written to the register
+    pins: u8,
+    data_out: bool,
+    write_enabled: bool,
+    state: EepromState,
+    /// The bits that have been shifted in or are left to shift out
+    buffer: u16,
+    bits: u8,
+}
+
+impl Eeprom {
+    fn new() -> Eeprom {
+        Eeprom {
+            data: vec![0xFF; EEPROM_SIZE],
+            pins: 0,
+            data_out: true,
+            write_enabled: false,
+            state: EepromState::Idle,
+            buffer: 0,
+            bits: 0,
+        }
+    }
+
+    fn word(&self, address: u8) -> u16 {
+        let offset = address as usize * 2;
+        u16::from_le_bytes([self.data[offset], self.data[offset + 1]])
+    }
+
+    fn set_word(&mut self, address: u8, value: u16) {
+        let offset = address as usize * 2;
+        self.data[offset..offset + 2].copy_from_slice(&value.to_le_bytes());
+    }
+
+    fn read(&self) -> u8 {
+        let data_out = if self.data_out { EEPROM_DO } else { 0 };
+        self.pins & (EEPROM_CS | EEPROM_CLK | EEPROM_DI) | data_out
+    }
+
+    fn write(&mut self, value: u8) {
+        let rising_edge = self.pins & EEPROM_CLK == 0 && value & EEPROM_CLK > 0;
+        self.pins = value;
+        if value & EEPROM_CS == 0 {
+            self.state = EepromState::Idle;
+        } else if rising_edge {
+            self.clock(value & EEPROM_DI > 0);
+        }
+    }
+
+    fn clock(&mut self, bit: bool) {
+        match self.state {
+            EepromState::Idle => {
+                if bit {
+                    self.state = EepromState::Command;
+                    self.buffer = 0;
+                    self.bits = 0;
+                }
+            }
+            EepromState::Command => {
+                self.buffer = self.buffer << 1 | bit as u16;
+                self.bits += 1;
+                if self.bits == 10 {
+                    self.command((self.buffer >> 8) as u8, self.buffer as u8);
+                }
+            }
+            EepromState::Data(address) => {
+                self.buffer = self.buffer << 1 | bit as u16;
+                self.bits += 1;
+                if self.bits == 16 {
+                    if self.write_enabled {
+                        match address {
+                            Some(address) => self.set_word(address, self.buffer),
+                            None => {
+                                (0..0x80).for_each(|address| self.set_word(address, self.buffer))
+                            }
+                        }
+                    }
+                    self.finish();
+                }
+            }
+            EepromState::Read(address) => {
+                self.data_out = self.buffer & 0x8000 > 0;
+                self.buffer <<= 1;
+                self.bits -= 1;
+                if self.bits == 0 {
+                    // Reads continue with the next word
+                    let address = (address + 1) & 0x7F;
+                    self.state = EepromState::Read(address);
+                    self.buffer = self.word(address);
+                    self.bits = 16;
+                }
+            }
+            EepromState::Done => {}
+        }
+    }
+
+    /// Runs a command. The 8 address bits hold a 7-bit word address, or select one of the
+    /// commands without an address with their upper 2 bits.
+    fn command(&mut self, opcode: u8, address_bits: u8) {
+        let address = address_bits & 0x7F;
+        self.buffer = 0;
+        self.bits = 0;
+        match opcode {
+            // READ, which starts with a dummy 0 bit
+            0b10 => {
+                self.data_out = false;
+                self.state = EepromState::Read(address);
+                self.buffer = self.word(address);
+                self.bits = 16;
+            }
+            // WRITE
+            0b01 => self.state = EepromState::Data(Some(address)),
+            // ERASE
+            0b11 => {
+                if self.write_enabled {
+                    self.set_word(address, 0xFFFF);
+                }
+                self.finish();
+            }
+            _ => match address_bits >> 6 {
+                // EWDS
+                0b00 => {
+                    self.write_enabled = false;
+                    self.finish();
+                }
+                // WRAL
+                0b01 => self.state = EepromState::Data(None),
+                // ERAL
+                0b10 => {
+                    if self.write_enabled {
+                        self.data.iter_mut().for_each(|byte| *byte = 0xFF);
+                    }
+                    self.finish();
+                }
+                // EWEN
+                _ => {
+                    self.write_enabled = true;
+                    self.finish();
+                }
+            },
+        }
+    }
+
+    /// Writes complete immediately, so DO signals ready right away.
+    fn finish(&mut self) {
+        self.data_out = true;
+        self.state = EepromState::Done;
+    }
+}
+
+/// Clocks `bits` into the EEPROM, most significant bit first, and returns the bits of DO.
+#[cfg(test)]
+fn shift_eeprom(mbc: &mut Mbc7, value: u32, bits: u8) -> u32 {
+    let mut out = 0;
+    for bit in (0..bits).rev() {
+        let di = if value >> bit & 1 > 0 { EEPROM_DI } else { 0 };
+        mbc.write_ram(0xA080, EEPROM_CS | di);
+        mbc.write_ram(0xA080, EEPROM_CS | EEPROM_CLK | di);
+        out = out << 1 | (mbc.read_ram(0xA080) & EEPROM_DO) as u32;
+    }
+    out
+}
+
+#[test]
+fn mbc7_accelerometer_and_eeprom() {
+    let mut mbc = Mbc7::new();
+    mbc.write_rom(0x0000, 0x0A);
+    mbc.write_rom(0x4000, 0x40);
+
+    mbc.set_tilt(TiltState { x: 1.0, y: -0.5 });
+    mbc.write_ram(0xA010, 0xAA);
+    assert_eq!(
+        mbc.read_ram(0xA020),
+        0x00,
+        "latching requires an erase first"
+    );
+    mbc.write_ram(0xA000, 0x55);
+    mbc.write_ram(0xA010, 0xAA);
+    assert_eq!(
+        mbc.read_ram(0xA020) as u16 | (mbc.read_ram(0xA030) as u16) << 8,
+        0x8240
+    );
+    assert_eq!(
+        mbc.read_ram(0xA040) as u16 | (mbc.read_ram(0xA050) as u16) << 8,
+        0x8198
+    );
+
+    // A start bit and opcode, followed by the address: EWEN, then WRITE 0x1234 to word 5
+    shift_eeprom(&mut mbc, 0b100, 3);
+    shift_eeprom(&mut mbc, 0xC0, 8);
+    mbc.write_ram(0xA080, 0x00);
+    shift_eeprom(&mut mbc, 0b101, 3);
+    shift_eeprom(&mut mbc, 0x05, 8);
+    shift_eeprom(&mut mbc, 0x1234, 16);
+    mbc.write_ram(0xA080, 0x00);
+    assert_eq!(&mbc.ram()[10..12], &[0x34, 0x12]);
+
+    // READ word 5, after the dummy bit
+    shift_eeprom(&mut mbc, 0b110, 3);
+    shift_eeprom(&mut mbc, 0x05, 8);
+    assert_eq!(shift_eeprom(&mut mbc, 0, 16), 0x1234);
+}
@@ -0,0 +1,188 @@
+use super::{ram_offset, read_rom_bank, Mapper};
+
+/// MMM01, a mapper for multicarts that is built on top of MBC1.
+///
+/// The cartridge starts unmapped, with the last 32 KiB of the ROM mapped at 0x0000-0x7FFF, which
+/// is where the menu lives. The menu selects a game by writing the outer bank bits and masks,
+/// and then sets bit 6 of 0x0000-0x1FFF. From then on the registers behave like MBC1 within the
+/// selected game, and the outer bank bits can not be changed until the next reset.
+///
+/// Like on MBC1, the lower RAM bank bits only apply in the advanced banking mode. The ROM at
+/// 0x0000-0x3FFF always stays at the first bank of the game.
+pub struct Mmm01 {
+    ram: Vec<u8>,
+    ram_enabled: bool,
+    /// Set by the menu, after which the outer bank bits are locked
+    mapped: bool,
+    /// The lower 5 bits of the ROM bank, like BANK1 of MBC1
+    rom_bank_low: u8,
+    /// Bits 5 and 6 of the ROM bank
+    rom_bank_mid: u8,
+    /// Bits 7 and 8 of the ROM bank
+    rom_bank_high: u8,
+    /// Bits 1-4 of `rom_bank_low` that are locked once mapped, which sizes the game
+    rom_bank_mask: u8,
+    /// The lower 2 bits of the RAM bank, like BANK2 of MBC1
+    ram_bank_low: u8,
+    /// Bits 2 and 3 of the RAM bank
+    ram_bank_high: u8,
+    /// Bits of `ram_bank_low` that are locked once mapped
+    ram_bank_mask: u8,
+    advanced_banking: bool,
+    /// Prevents the game from changing the banking mode
+    mode_locked: bool,
+}
+
+impl Mmm01 {
+    pub fn new(ram_size: usize) -> Mmm01 {
+        Mmm01 {
+            ram: vec![0; ram_size],
+            ram_enabled: false,
+            mapped: false,
+            rom_bank_low: 0,
+            rom_bank_mid: 0,
+            rom_bank_high: 0,
+            rom_bank_mask: 0,
+            ram_bank_low: 0,
+            ram_bank_high: 0,
+            ram_bank_mask: 0,
+            advanced_banking: false,
+            mode_locked: false,
+        }
+    }
+
+    /// The first ROM bank of the selected game.
+    fn outer_rom_bank(&self) -> usize {
+        (self.rom_bank_high as usize) << 7
+            | (self.rom_bank_mid as usize) << 5
+            | (self.rom_bank_low & self.rom_bank_mask) as usize
+    }
+
+    fn ram_bank(&self) -> usize {
+        let low = if self.advanced_banking {
+            self.ram_bank_low
+        } else {
+            0
+        };
+        (self.ram_bank_high << 2 | low) as usize
+    }
+
+    /// Only changes the bits of `register` that are not in `mask` once the cartridge is mapped.
+    fn masked(&self, register: u8, value: u8, mask: u8) -> u8 {
+        if self.mapped {
+            register & mask | value & !mask
+        } else {
+            value
+        }
+    }
+}
+
+impl Mapper for Mmm01 {
+    fn read_rom(&self, rom: &[u8], address: u16) -> u8 {
+        if !self.mapped {
+            let banks = rom.len() / super::ROM_BANK_SIZE;
+            let bank = banks - 2 + (address as usize >> 14);
+            return read_rom_bank(rom, bank, address);
+        }
+        let bank = if address < 0x4000 {
+            self.outer_rom_bank()
+        } else {
+            let low = match self.rom_bank_low & !self.rom_bank_mask & 0x1F {
+                0 => 1,
+                bank => bank,
+            };
+            self.outer_rom_bank() | low as usize
+        };
+        read_rom_bank(rom, bank, address)
+    }
+
+    fn write_rom(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000..=0x1FFF => {
+                self.ram_enabled = value & 0x0F == 0x0A;
+                if !self.mapped {
+                    self.ram_bank_mask = value >> 4 & 0b11;
+                    self.mapped = value & 0x40 > 0;
+                }
+            }
+            0x2000..=0x3FFF => {
+                self.rom_bank_low =
+                    self.masked(self.rom_bank_low, value & 0x1F, self.rom_bank_mask);
+                if !self.mapped {
+                    self.rom_bank_mid = value >> 5 & 0b11;
+                }
+            }
+            0x4000..=0x5FFF => {
+                self.ram_bank_low =
+                    self.masked(self.ram_bank_low, value & 0b11, self.ram_bank_mask);
+                if !self.mapped {
+                    self.ram_bank_high = value >> 2 & 0b11;
+                    self.rom_bank_high = value >> 4 & 0b11;
+                    self.mode_locked = value & 0x40 > 0;
+                }
+            }
+            _ => {
+                if !self.mode_locked {
+                    self.advanced_banking = value & 1 > 0;
+                }
+                if !self.mapped {
+                    self.rom_bank_mask = (value >> 2 & 0x0F) << 1;
+                }
+            }
+        }
+    }
+
+    fn read_ram(&self, address: u16) -> u8 {
+        if !self.ram_enabled || self.ram.is_empty() {
+            return 0xFF;
+        }
+        self.ram[ram_offset(self.ram.len(), self.ram_bank(), address)]
+    }
+
+    fn write_ram(&mut self, address: u16, value: u8) {
+        if self.ram_enabled && !self.ram.is_empty() {
+            let offset = ram_offset(self.ram.len(), self.ram_bank(), address);
+            self.ram[offset] = value;
+        }
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn ram_mut(&mut self) -> &mut [u8] {
+        &mut self.ram
+    }
+}
+
+#[test]
+fn mmm01_menu_and_game() {
+    let mut rom = vec![0; 64 * super::ROM_BANK_SIZE];
+    for bank in 0..64 {
+        rom[bank * super::ROM_BANK_SIZE] = bank as u8;
+    }
+    let mut mbc = Mmm01::new(0);
+    assert_eq!(
+        mbc.read_rom(&rom, 0x0000),
+        62,
+        "the menu is in the last 32 KiB"
+    );
+    assert_eq!(mbc.read_rom(&rom, 0x4000), 63);
+
+    // Select the 128 KiB game at bank 0x28, which locks bits 3 and 4 of the bank number
+    mbc.write_rom(0x6000, 0b1100 << 2);
+    mbc.write_rom(0x2000, 0x28);
+    mbc.write_rom(0x0000, 0x40);
+    assert_eq!(mbc.read_rom(&rom, 0x0000), 0x28);
+    assert_eq!(mbc.read_rom(&rom, 0x4000), 0x29);
+
+    mbc.write_rom(0x2000, 0x1F);
+    assert_eq!(mbc.read_rom(&rom, 0x4000), 0x2F);
+    mbc.write_rom(0x4000, 0x30);
+    mbc.write_rom(0x2000, 0x00);
+    assert_eq!(
+        mbc.read_rom(&rom, 0x4000),
+        0x29,
+        "the outer bank is locked after mapping"
+    );
+}
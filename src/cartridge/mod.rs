@@ -0,0 +1,261 @@
+pub mod camera;
+mod header;
+mod huc1;
+mod huc3;
+mod mbc1;
+mod mbc2;
+mod mbc3;
+mod mbc5;
+mod mbc7;
+mod mmm01;
+mod rom_only;
+mod rtc;
+
+pub use self::{
+    camera::{ImageFile, ImageSource, PocketCamera},
+    header::{
+        global_checksum_of, header_checksum_of, CartridgeType, CgbSupport, Header, HeaderError,
+        Licensee, MapperKind, HEADER_SIZE,
+    },
+    huc1::HuC1,
+    huc3::HuC3,
+    mbc1::Mbc1,
+    mbc2::Mbc2,
+    mbc3::Mbc3,
+    mbc5::Mbc5,
+    mbc7::Mbc7,
+    mmm01::Mmm01,
+    rom_only::RomOnly,
+    rtc::{Rtc, RtcClock, RtcRegisters},
+};
+
+use crate::TiltState;
+use std::sync::Arc;
+
+pub const ROM_BANK_SIZE: usize = 0x4000;
+pub const RAM_BANK_SIZE: usize = 0x2000;
+
+/// The hardware on the cartridge that maps the ROM and RAM into the address space, usually a
+/// memory bank controller (MBC).
+pub trait Mapper {
+    /// Reads from the ROM area at 0x0000-0x7FFF.
+    fn read_rom(&self, rom: &[u8], address: u16) -> u8;
+
+    /// Writes to the ROM area at 0x0000-0x7FFF, which sets the registers of the mapper.
+    fn write_rom(&mut self, address: u16, value: u8);
+
+    /// Reads from the external RAM area at 0xA000-0xBFFF.
+    fn read_ram(&self, address: u16) -> u8;
+
+    fn write_ram(&mut self, address: u16, value: u8);
+
+    /// The contents of the RAM, which is saved for battery backed cartridges.
+    fn ram(&self) -> &[u8];
+
+    fn ram_mut(&mut self) -> &mut [u8];
+
+    /// Whether the rumble motor of the cartridge is running.
+    fn rumble(&self) -> bool {
+        false
+    }
+
+    /// Whether the infrared LED of the cartridge is on.
+    fn infrared_led(&self) -> bool {
+        false
+    }
+
+    /// Sets whether the infrared sensor of the cartridge receives light.
+    fn set_infrared_light(&mut self, _light: bool) {}
+
+    /// Sets the tilt of the Game Boy, for cartridges with an accelerometer.
+    fn set_tilt(&mut self, _tilt: TiltState) {}
+
+    /// Sets what the image sensor sees, for the Pocket Camera.
+    fn set_image_source(&mut self, _source: Box<dyn ImageSource>) {}
+
+    /// Advances the hardware on the cartridge that runs on the CPU clock, like a real time clock.
+    fn tick(&mut self, _cycles: u16) {}
+
+    /// The battery backed state in the `.sav` layout used by other emulators: the RAM, followed
+    /// by any extra state like a real time clock.
+    fn save_data(&self) -> Vec<u8> {
+        self.ram().to_vec()
+    }
+
+    /// Restores the state returned by `save_data`.
+    fn load_save_data(&mut self, data: &[u8]) {
+        let ram = self.ram_mut();
+        let len = ram.len().min(data.len());
+        ram[..len].copy_from_slice(&data[..len]);
+    }
+}
+
+/// Reads `address` from a 16 KiB ROM bank. Bank numbers wrap around the size of the ROM, as the
+/// unused upper bits of the bank number are not connected.
+fn read_rom_bank(rom: &[u8], bank: usize, address: u16) -> u8 {
+    let banks = rom.len() / ROM_BANK_SIZE;
+    rom[(bank % banks) * ROM_BANK_SIZE + (address as usize & (ROM_BANK_SIZE - 1))]
+}
+
+/// Returns the offset of `address` in a banked RAM of `len` bytes. Like the ROM, the bank number
+/// wraps around the size of the RAM.
+fn ram_offset(len: usize, bank: usize, address: u16) -> usize {
+    (bank * RAM_BANK_SIZE + (address as usize & (RAM_BANK_SIZE - 1))) % len
+}
+
+/// A ROM image together with its parsed header. The ROM is shared between clones.
+#[derive(Clone)]
+pub struct Cartridge {
+    header: Header,
+    rom: Arc<[u8]>,
+    rtc_clock: RtcClock,
+}
+
+impl Cartridge {
+    /// Parses the header of a ROM image. The ROM is padded with 0xFF up to the size in the
+    /// header, or up to the next bank if it is not a multiple of the bank size.
+    ///
+    /// MMM01 multicarts start with the menu in the last 32 KiB of the ROM, so their header is
+    /// read from there.
+    pub fn new(mut rom: Vec<u8>) -> Result<Cartridge, HeaderError> {
+        let header = match mmm01_header(&rom) {
+            Some(header) => header,
+            None => Header::parse(&rom)?,
+        };
+        match header.cartridge_type.mapper {
+            MapperKind::RomOnly
+            | MapperKind::Mbc1
+            | MapperKind::Mbc2
+            | MapperKind::Mbc3
+            | MapperKind::Mbc5
+            | MapperKind::Mbc7
+            | MapperKind::Mmm01
+            | MapperKind::PocketCamera
+            | MapperKind::HuC1
+            | MapperKind::HuC3 => {}
+            mapper => return Err(HeaderError::UnsupportedMapper(mapper)),
+        }
+
+        let banks = rom.len().div_ceil(ROM_BANK_SIZE).max(header.rom_banks());
+        rom.resize(banks * ROM_BANK_SIZE, 0xFF);
+
+        Ok(Cartridge {
+            header,
+            rom: rom.into(),
+            rtc_clock: RtcClock::default(),
+        })
+    }
+
+    /// Selects what drives the real time clock of mappers created after this call. The host
+    /// time is used by default.
+    pub fn set_rtc_clock(&mut self, clock: RtcClock) {
+        self.rtc_clock = clock;
+    }
+
+    /// Creates the mapper of this cartridge, selected by the cartridge type in the header.
+    pub fn create_mapper(&self) -> Box<dyn Mapper> {
+        let ram_size = self.header.ram_size;
+        let cartridge_type = &self.header.cartridge_type;
+        match cartridge_type.mapper {
+            MapperKind::RomOnly => Box::new(RomOnly::new(ram_size)),
+            MapperKind::Mbc1 => Box::new(Mbc1::new(&self.rom, ram_size)),
+            MapperKind::Mbc2 => Box::new(Mbc2::new()),
+            MapperKind::Mbc3 => {
+                let rtc = Some(self.rtc_clock).filter(|_| cartridge_type.timer);
+                Box::new(Mbc3::new(ram_size, rtc))
+            }
+            MapperKind::Mbc5 => Box::new(Mbc5::new(ram_size, cartridge_type.rumble)),
+            MapperKind::Mbc7 => Box::new(Mbc7::new()),
+            MapperKind::Mmm01 => Box::new(Mmm01::new(ram_size)),
+            MapperKind::PocketCamera => Box::new(PocketCamera::new()),
+            MapperKind::HuC1 => Box::new(HuC1::new(ram_size)),
+            MapperKind::HuC3 => Box::new(HuC3::new(ram_size, self.rtc_clock)),
+            mapper => unreachable!("{:?} is rejected by Cartridge::new", mapper),
+        }
+    }
+
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    pub fn rom(&self) -> &[u8] {
+        &self.rom
+    }
+
+    /// The number of 16 KiB ROM banks.
+    pub fn rom_banks(&self) -> usize {
+        self.rom.len() / ROM_BANK_SIZE
+    }
+
+    /// Returns a 16 KiB bank of the ROM. The bank number wraps around like on hardware, where the
+    /// unused upper bits of the bank number are not connected.
+    pub fn rom_bank(&self, bank: usize) -> &[u8] {
+        let start = (bank % self.rom_banks()) * ROM_BANK_SIZE;
+        &self.rom[start..start + ROM_BANK_SIZE]
+    }
+}
+
+/// Parses the header in the last 32 KiB of the ROM, if that is the header of an MMM01 multicart.
+fn mmm01_header(rom: &[u8]) -> Option<Header> {
+    let menu = rom
+        .len()
+        .checked_sub(2 * ROM_BANK_SIZE)
+        .filter(|&menu| menu > 0)?;
+    Header::parse(&rom[menu..])
+        .ok()
+        .filter(|header| header.cartridge_type.mapper == MapperKind::Mmm01)
+}
+
+/// Creates a 32 KiB ROM with a valid header, with `program` placed at 0x0100.
+#[cfg(test)]
+pub(crate) fn test_rom(program: &[u8]) -> Vec<u8> {
+    let mut rom = vec![0; 0x8000];
+    rom[0x100..0x100 + program.len()].copy_from_slice(program);
+    rom[0x134..0x138].copy_from_slice(b"TEST");
+    rom[0x14D] = header_checksum_of(&rom);
+    rom
+}
+
+#[test]
+fn parse_header() {
+    let mut rom = test_rom(&[]);
+    rom[0x134..0x143].copy_from_slice(b"POKEMON_SLVAAXE");
+    rom[0x143] = 0x80;
+    rom[0x14B] = 0x01;
+    rom[0x147] = 0x03;
+    rom[0x148] = 0x06;
+    rom[0x149] = 0x03;
+    rom[0x14D] = header_checksum_of(&rom);
+
+    let cartridge = Cartridge::new(rom.clone()).unwrap();
+    let header = cartridge.header();
+    assert_eq!(header.title, "POKEMON_SLV");
+    assert_eq!(header.manufacturer_code.as_deref(), Some("AAXE"));
+    assert_eq!(header.cgb_support, CgbSupport::Supported);
+    assert_eq!(header.cartridge_type.mapper, MapperKind::Mbc1);
+    assert!(header.cartridge_type.ram && header.cartridge_type.battery);
+    assert_eq!(header.rom_size, 2 * 1024 * 1024);
+    assert_eq!(header.ram_size, 32 * 1024);
+    assert_eq!(header.licensee, Licensee::Old(0x01));
+    // The image is padded to the size in the header
+    assert_eq!(cartridge.rom_banks(), 128);
+    assert_eq!(cartridge.rom_bank(129)[0x100], cartridge.rom()[0x4100]);
+
+    // MMM01 multicarts are recognized by the header of the menu at the end of the ROM
+    let mut multicart = vec![0; 0x20000];
+    multicart[0x18000..].copy_from_slice(&test_rom(&[]));
+    multicart[0x18147] = 0x0B;
+    multicart[0x1814D] = header_checksum_of(&multicart[0x18000..]);
+    let cartridge = Cartridge::new(multicart).unwrap();
+    assert_eq!(cartridge.header().cartridge_type.mapper, MapperKind::Mmm01);
+
+    rom[0x14D] ^= 1;
+    assert!(matches!(
+        Cartridge::new(rom),
+        Err(HeaderError::HeaderChecksum { .. })
+    ));
+    assert_eq!(
+        Cartridge::new(vec![0; 0x100]).err(),
+        Some(HeaderError::TooSmall(0x100))
+    );
+}
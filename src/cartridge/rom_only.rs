@@ -0,0 +1,45 @@
+use super::{read_rom_bank, Mapper};
+
+/// A cartridge without a mapper. The 32 KiB of ROM are mapped directly, optionally with up to
+/// 8 KiB of RAM.
+pub struct RomOnly {
+    ram: Vec<u8>,
+}
+
+impl RomOnly {
+    pub fn new(ram_size: usize) -> RomOnly {
+        RomOnly {
+            ram: vec![0; ram_size],
+        }
+    }
+}
+
+impl Mapper for RomOnly {
+    fn read_rom(&self, rom: &[u8], address: u16) -> u8 {
+        read_rom_bank(rom, address as usize / super::ROM_BANK_SIZE, address)
+    }
+
+    fn write_rom(&mut self, _address: u16, _value: u8) {}
+
+    fn read_ram(&self, address: u16) -> u8 {
+        if self.ram.is_empty() {
+            return 0xFF;
+        }
+        self.ram[super::ram_offset(self.ram.len(), 0, address)]
+    }
+
+    fn write_ram(&mut self, address: u16, value: u8) {
+        if !self.ram.is_empty() {
+            let offset = super::ram_offset(self.ram.len(), 0, address);
+            self.ram[offset] = value;
+        }
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn ram_mut(&mut self) -> &mut [u8] {
+        &mut self.ram
+    }
+}
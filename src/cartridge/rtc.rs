@@ -0,0 +1,368 @@
+use std::{
+    convert::TryInto,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// The clock speed of the CPU
+const CYCLES_PER_SECOND: u32 = 4_194_304;
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// $08 RTC S, seconds
+pub const RTC_SECONDS: u8 = 0x08;
+/// $09 RTC M, minutes
+pub const RTC_MINUTES: u8 = 0x09;
+/// $0A RTC H, hours
+pub const RTC_HOURS: u8 = 0x0A;
+/// $0B RTC DL, the lower 8 bits of the day counter
+pub const RTC_DAYS_LOW: u8 = 0x0B;
+/// $0C RTC DH, bit 0 is bit 8 of the day counter, bit 6 halts the clock, bit 7 is the day carry
+pub const RTC_DAYS_HIGH: u8 = 0x0C;
+
+/// The size of the RTC state that is appended to the save RAM
+pub const RTC_SAVE_SIZE: usize = 48;
+
+/// What drives a cartridge real time clock.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default)]
+pub enum RtcClock {
+    /// The clock follows the time of the host, and keeps running while the emulator is closed.
+    #[default]
+    WallTime,
+    /// The clock follows the emulated cycles, which makes it deterministic.
+    EmulatedCycles,
+}
+
+/// The time registers of the MBC3 real time clock.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default)]
+pub struct RtcRegisters {
+    pub seconds: u8,
+    pub minutes: u8,
+    pub hours: u8,
+    /// The 9-bit day counter
+    pub days: u16,
+    pub halted: bool,
+    /// Set when the day counter overflows, until it is cleared by the game
+    pub day_carry: bool,
+}
+
+impl RtcRegisters {
+    pub fn read(&self, register: u8) -> u8 {
+        match register {
+            RTC_SECONDS => self.seconds,
+            RTC_MINUTES => self.minutes,
+            RTC_HOURS => self.hours,
+            RTC_DAYS_LOW => self.days as u8,
+            RTC_DAYS_HIGH => {
+                (self.days >> 8) as u8 | (self.halted as u8) << 6 | (self.day_carry as u8) << 7
+            }
+            _ => 0xFF,
+        }
+    }
+
+    /// Writes a register. Only the bits that exist on hardware are stored, but values that are out
+    /// of range (like 61 seconds) are allowed.
+    pub fn write(&mut self, register: u8, value: u8) {
+        match register {
+            RTC_SECONDS => self.seconds = value & 0x3F,
+            RTC_MINUTES => self.minutes = value & 0x3F,
+            RTC_HOURS => self.hours = value & 0x1F,
+            RTC_DAYS_LOW => self.days = self.days & 0x100 | value as u16,
+            RTC_DAYS_HIGH => {
+                self.days = self.days & 0xFF | (value as u16 & 1) << 8;
+                self.halted = value & 0x40 > 0;
+                self.day_carry = value & 0x80 > 0;
+            }
+            _ => {}
+        }
+    }
+
+    fn is_valid(&self) -> bool {
+        self.seconds < 60 && self.minutes < 60 && self.hours < 24
+    }
+
+    /// Advances the clock by a single second. Out of range values keep counting until their bits
+    /// overflow, without carrying into the next register.
+    fn tick_second(&mut self) {
+        self.seconds = (self.seconds + 1) & 0x3F;
+        if self.seconds != 60 {
+            return;
+        }
+        self.seconds = 0;
+        self.minutes = (self.minutes + 1) & 0x3F;
+        if self.minutes != 60 {
+            return;
+        }
+        self.minutes = 0;
+        self.hours = (self.hours + 1) & 0x1F;
+        if self.hours != 24 {
+            return;
+        }
+        self.hours = 0;
+        self.add_days(1);
+    }
+
+    fn add_days(&mut self, days: u64) {
+        let days = self.days as u64 + days;
+        if days >= 512 {
+            self.day_carry = true;
+        }
+        self.days = (days % 512) as u16;
+    }
+
+    /// Advances the clock, unless it is halted.
+    pub fn advance(&mut self, mut seconds: u64) {
+        if self.halted {
+            return;
+        }
+        while seconds > 0 && !self.is_valid() {
+            self.tick_second();
+            seconds -= 1;
+        }
+        if seconds == 0 {
+            return;
+        }
+
+        let time_of_day =
+            (self.hours as u64 * 60 + self.minutes as u64) * 60 + self.seconds as u64 + seconds;
+        self.seconds = (time_of_day % 60) as u8;
+        self.minutes = (time_of_day / 60 % 60) as u8;
+        self.hours = (time_of_day / 3600 % 24) as u8;
+        self.add_days(time_of_day / SECONDS_PER_DAY);
+    }
+}
+
+/// Counts the seconds of a real time clock, either from the host time or from the emulated
+/// cycles.
+pub(super) struct Timer {
+    clock: RtcClock,
+    /// The host time up to which the seconds are counted, for `RtcClock::WallTime`
+    last_update: SystemTime,
+    /// The cycles since the last second, for `RtcClock::EmulatedCycles`
+    cycles: u32,
+}
+
+impl Timer {
+    pub(super) fn new(clock: RtcClock) -> Timer {
+        Timer {
+            clock,
+            last_update: SystemTime::now(),
+            cycles: 0,
+        }
+    }
+
+    /// Counts emulated cycles, and returns the number of seconds that passed.
+    pub(super) fn tick(&mut self, cycles: u16) -> u64 {
+        if self.clock != RtcClock::EmulatedCycles {
+            return 0;
+        }
+        self.cycles += cycles as u32;
+        if self.cycles >= CYCLES_PER_SECOND {
+            self.cycles -= CYCLES_PER_SECOND;
+            1
+        } else {
+            0
+        }
+    }
+
+    /// Returns the number of seconds of host time that passed since the last call.
+    pub(super) fn elapsed(&mut self) -> u64 {
+        if self.clock != RtcClock::WallTime {
+            return 0;
+        }
+        let elapsed = SystemTime::now()
+            .duration_since(self.last_update)
+            .map_or(0, |elapsed| elapsed.as_secs());
+        self.last_update += Duration::from_secs(elapsed);
+        elapsed
+    }
+
+    /// Restarts the current second.
+    pub(super) fn reset(&mut self) {
+        self.last_update = SystemTime::now();
+        self.cycles = 0;
+    }
+
+    /// The UNIX timestamp up to which the seconds are counted, which is stored in saves.
+    pub(super) fn timestamp(&self) -> u64 {
+        self.last_update
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_secs())
+    }
+
+    /// Continues counting from the time a save was made, so `elapsed` includes the time the
+    /// emulator was closed.
+    pub(super) fn set_timestamp(&mut self, timestamp: u64) {
+        self.last_update = UNIX_EPOCH + Duration::from_secs(timestamp);
+        self.cycles = 0;
+    }
+}
+
+/// The real time clock of MBC3 cartridges. The game reads a latched copy of the registers, which
+/// is updated by writing 0x00 followed by 0x01 to 0x6000-0x7FFF.
+pub struct Rtc {
+    registers: RtcRegisters,
+    latched: RtcRegisters,
+    timer: Timer,
+    /// The last value written to the latch register
+    latch: u8,
+}
+
+impl Rtc {
+    pub fn new(clock: RtcClock) -> Rtc {
+        Rtc {
+            registers: RtcRegisters::default(),
+            latched: RtcRegisters::default(),
+            timer: Timer::new(clock),
+            latch: 0xFF,
+        }
+    }
+
+    pub fn registers(&self) -> &RtcRegisters {
+        &self.registers
+    }
+
+    pub fn latched(&self) -> &RtcRegisters {
+        &self.latched
+    }
+
+    pub fn tick(&mut self, cycles: u16) {
+        let seconds = self.timer.tick(cycles);
+        self.registers.advance(seconds);
+    }
+
+    /// Catches up with the host time.
+    fn update(&mut self) {
+        let seconds = self.timer.elapsed();
+        self.registers.advance(seconds);
+    }
+
+    /// Reads one of the latched registers.
+    pub fn read(&self, register: u8) -> u8 {
+        self.latched.read(register)
+    }
+
+    pub fn write(&mut self, register: u8, value: u8) {
+        self.update();
+        if register == RTC_SECONDS {
+            // Writing the seconds resets the counter that divides the clock into seconds
+            self.timer.reset();
+        }
+        self.registers.write(register, value);
+    }
+
+    /// Handles a write to the latch register at 0x6000-0x7FFF.
+    pub fn write_latch(&mut self, value: u8) {
+        if self.latch == 0x00 && value == 0x01 {
+            self.update();
+            self.latched = self.registers;
+        }
+        self.latch = value;
+    }
+
+    /// Serializes the clock in the format used by other emulators: the current and the latched
+    /// registers as 32-bit little-endian values, followed by a 64-bit UNIX timestamp.
+    pub fn save(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(RTC_SAVE_SIZE);
+        for registers in [&self.registers, &self.latched] {
+            for register in RTC_SECONDS..=RTC_DAYS_HIGH {
+                data.extend_from_slice(&(registers.read(register) as u32).to_le_bytes());
+            }
+        }
+        data.extend_from_slice(&self.timer.timestamp().to_le_bytes());
+        data
+    }
+
+    /// Restores the clock from `save`. With `RtcClock::WallTime` the clock is advanced by the
+    /// time that has passed since it was saved. Older saves with a 32-bit timestamp are
+    /// supported as well.
+    pub fn load(&mut self, data: &[u8]) {
+        if data.len() < RTC_SAVE_SIZE - 4 {
+            return;
+        }
+        let word =
+            |index: usize| u32::from_le_bytes(data[index * 4..index * 4 + 4].try_into().unwrap());
+        for (index, register) in (RTC_SECONDS..=RTC_DAYS_HIGH).enumerate() {
+            self.registers.write(register, word(index) as u8);
+            self.latched.write(register, word(index + 5) as u8);
+        }
+
+        let timestamp = if data.len() >= RTC_SAVE_SIZE {
+            u64::from_le_bytes(data[40..48].try_into().unwrap())
+        } else {
+            word(10) as u64
+        };
+        self.timer.set_timestamp(timestamp);
+        self.update();
+    }
+}
+
+#[test]
+fn rtc_advance() {
+    let mut registers = RtcRegisters {
+        seconds: 59,
+        minutes: 59,
+        hours: 23,
+        days: 511,
+        ..RtcRegisters::default()
+    };
+    registers.advance(1);
+    assert_eq!(registers.read(RTC_SECONDS), 0);
+    assert_eq!(registers.read(RTC_HOURS), 0);
+    assert_eq!(registers.read(RTC_DAYS_LOW), 0);
+    assert_eq!(
+        registers.read(RTC_DAYS_HIGH),
+        0x80,
+        "the day counter overflowed"
+    );
+
+    registers.advance(3 * SECONDS_PER_DAY + 3600 + 61);
+    assert_eq!(
+        (
+            registers.days,
+            registers.hours,
+            registers.minutes,
+            registers.seconds
+        ),
+        (3, 1, 1, 1)
+    );
+
+    // Out of range values count up to 63 and wrap around without a carry
+    registers.write(RTC_SECONDS, 62);
+    registers.advance(3);
+    assert_eq!((registers.minutes, registers.seconds), (1, 1));
+
+    registers.write(RTC_DAYS_HIGH, 0x40);
+    registers.advance(100);
+    assert_eq!(registers.seconds, 1, "the clock is halted");
+}
+
+#[test]
+fn rtc_latch_and_save() {
+    let mut rtc = Rtc::new(RtcClock::EmulatedCycles);
+    rtc.write(RTC_MINUTES, 10);
+    for _ in 0..(CYCLES_PER_SECOND / 4) * 5 {
+        rtc.tick(4);
+    }
+    assert_eq!(
+        rtc.read(RTC_SECONDS),
+        0,
+        "the registers are not latched yet"
+    );
+
+    rtc.write_latch(0x01);
+    assert_eq!(
+        rtc.read(RTC_SECONDS),
+        0,
+        "latching requires 0x00 followed by 0x01"
+    );
+    rtc.write_latch(0x00);
+    rtc.write_latch(0x01);
+    assert_eq!(rtc.read(RTC_SECONDS), 5);
+    assert_eq!(rtc.read(RTC_MINUTES), 10);
+
+    let data = rtc.save();
+    assert_eq!(data.len(), RTC_SAVE_SIZE);
+    let mut loaded = Rtc::new(RtcClock::EmulatedCycles);
+    loaded.load(&data);
+    assert_eq!(loaded.registers(), rtc.registers());
+    assert_eq!(loaded.latched(), rtc.latched());
+}
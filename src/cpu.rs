@@ -1,6 +1,6 @@
 #![allow(dead_code)]
 
-use crate::memory::Memory;
+use crate::Bus;
 
 pub struct Cpu {
     a: u8,
@@ -8,15 +8,31 @@ pub struct Cpu {
     c: u8,
     d: u8,
     e: u8,
-    f: u8,
     h: u8,
     l: u8,
     sp: u16,
     pc: u16,
 
     pub flags: Flags,
+    /// The interrupt master enable flag (IME)
+    ime: bool,
+    /// Set by EI, interrupts are enabled after the instruction following EI
+    ime_scheduled: bool,
+    /// Set by HALT, the CPU sleeps until an interrupt is pending
+    halted: bool,
+    /// Set by STOP, the CPU sleeps until a button is pressed
+    stopped: bool,
+    /// The P1 input lines the last time they were checked during STOP, to detect when a button
+    /// gets pressed
+    joypad_lines: u8,
+    /// Set when HALT is executed with IME disabled and an interrupt already pending. The next
+    /// opcode byte is read twice because the program counter fails to increment.
+    halt_bug: bool,
+    /// Set by conditional instructions that take their branch, see `take_branch`
+    branch_taken: bool,
     cycles: u32,
-    pub scanline_cycles: u16,
+    /// The total amount of cycles clocked since the CPU was created
+    elapsed_cycles: u64,
 }
 
 impl Default for Cpu {
@@ -27,14 +43,20 @@ impl Default for Cpu {
             c: 0,
             d: 0,
             e: 0,
-            f: 0,
             h: 0,
             l: 0,
 
             sp: 0xFFFE,
             cycles: 0,
-            scanline_cycles: 0,
+            elapsed_cycles: 0,
             flags: Flags(0),
+            ime: false,
+            ime_scheduled: false,
+            halted: false,
+            stopped: false,
+            joypad_lines: 0x0F,
+            halt_bug: false,
+            branch_taken: false,
             pc: 0x0,
         }
     }
@@ -46,16 +68,97 @@ impl Cpu {
     }
 
     pub fn increment_program_counter(&mut self) {
-        self.pc += 1;
+        if self.halt_bug {
+            self.halt_bug = false;
+        } else {
+            self.pc = self.pc.wrapping_add(1);
+        }
     }
 
     pub fn set_program_counter(&mut self, pc: u16) {
         self.pc = pc;
     }
 
+    pub fn interrupts_enabled(&self) -> bool {
+        self.ime
+    }
+
+    /// Enables interrupts immediately, used by RETI.
+    pub fn enable_interrupts(&mut self) {
+        self.ime = true;
+        self.ime_scheduled = false;
+    }
+
+    /// Enables interrupts after the next instruction has executed, used by EI.
+    pub fn schedule_enable_interrupts(&mut self) {
+        self.ime_scheduled = true;
+    }
+
+    pub fn interrupts_scheduled(&self) -> bool {
+        self.ime_scheduled
+    }
+
+    pub fn disable_interrupts(&mut self) {
+        self.ime = false;
+        self.ime_scheduled = false;
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    pub fn halt(&mut self) {
+        self.halted = true;
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stopped
+    }
+
+    /// Stops the CPU until one of the P1 `joypad_lines` goes from high to low. Buttons that are
+    /// already held down do not wake it.
+    pub fn stop(&mut self, joypad_lines: u8) {
+        self.stopped = true;
+        self.joypad_lines = joypad_lines;
+    }
+
+    /// Records the P1 input lines during STOP, and returns whether any of them went low since
+    /// they were last recorded.
+    pub fn update_joypad_lines(&mut self, joypad_lines: u8) -> bool {
+        let pressed = self.joypad_lines & !joypad_lines & 0x0F;
+        self.joypad_lines = joypad_lines;
+        pressed > 0
+    }
+
+    /// Wakes the CPU up from HALT or STOP.
+    pub fn resume(&mut self) {
+        self.halted = false;
+        self.stopped = false;
+    }
+
+    /// Makes the next program counter increment a no-op, see `halt_bug`.
+    pub fn trigger_halt_bug(&mut self) {
+        self.halt_bug = true;
+    }
+
+    /// Marks the conditional instruction that is being executed as taking its branch, so it is
+    /// clocked with its `branch_cycles` instead of its regular cycles.
+    pub fn take_branch(&mut self) {
+        self.branch_taken = true;
+    }
+
+    /// Returns whether the last instruction took its branch, and resets it for the next one.
+    pub fn branch_taken(&mut self) -> bool {
+        core::mem::replace(&mut self.branch_taken, false)
+    }
+
     pub fn clock_cycles(&mut self, cycles: u16) {
         self.cycles += cycles as u32;
-        self.scanline_cycles += cycles;
+        self.elapsed_cycles += cycles as u64;
+    }
+
+    pub fn elapsed_cycles(&self) -> u64 {
+        self.elapsed_cycles
     }
 
     pub fn a(&self) -> u8 {
@@ -79,9 +182,14 @@ impl Cpu {
     pub fn l(&self) -> u8 {
         self.l
     }
+    /// The raw value of the F register, see `Flags`.
+    pub fn f(&self) -> u8 {
+        self.flags.bits()
+    }
 
+    /// AF, with F backed by `flags`
     pub fn af(&self) -> u16 {
-        bytes_to_word(self.a, self.f)
+        bytes_to_word(self.a, self.flags.bits())
     }
     pub fn bc(&self) -> u16 {
         bytes_to_word(self.b, self.c)
@@ -124,6 +232,18 @@ impl Cpu {
         self.l = val;
     }
 
+    /// Overwrites all flags. The lower nibble of F always reads as zero.
+    pub fn set_f(&mut self, val: u8) {
+        self.flags.set_bits(val);
+    }
+
+    /// Sets AF. The lower nibble of F always reads as zero.
+    pub fn set_af(&mut self, val: u16) {
+        let (a, f) = word_to_bytes(val);
+        self.a = a;
+        self.set_f(f);
+    }
+
     pub fn set_bc(&mut self, val: u16) {
         let (b, c) = word_to_bytes(val);
         self.b = b;
@@ -158,34 +278,157 @@ impl Cpu {
     }
 
     pub fn add_a(&mut self, val: u8, with_carry: bool) {
-        let val = val + if with_carry && self.flags.c() { 1 } else { 0 };
-        let half_carry = ((self.a & 0xf) + (val & 0xf)) & 0x10 > 0;
-        let (new_a, carry) = self.a.overflowing_add(val);
+        let carry = if with_carry && self.flags.c() { 1 } else { 0 };
+        let half_carry = (self.a & 0xF) + (val & 0xF) + carry > 0xF;
+        let result = self.a as u16 + val as u16 + carry as u16;
+        let new_a = result as u8;
 
         self.flags.update_half_carry(half_carry);
-        self.flags.update_carry(carry);
+        self.flags.update_carry(result > 0xFF);
         self.flags.update_zero(new_a == 0);
         self.flags.clear_subtract();
 
         self.a = new_a;
     }
 
+    pub fn sub_a(&mut self, val: u8, with_carry: bool) {
+        self.a = self.sub(val, with_carry);
+    }
+
     pub fn cmp_a_with(&mut self, val: u8) {
-        let r = self.a.wrapping_sub(val);
-        self.flags.update_zero(r == 0);
-        self.flags.update_half_carry((self.a & 0x0F) < (val & 0x0F));
+        self.sub(val, false);
+    }
+
+    /// Subtracts `val` (and the carry flag if `with_carry` is set) from A, updating the flags but
+    /// leaving A untouched.
+    fn sub(&mut self, val: u8, with_carry: bool) -> u8 {
+        let carry = if with_carry && self.flags.c() { 1 } else { 0 };
+        let half_carry = (self.a & 0xF) < (val & 0xF) + carry;
+        let result = (self.a as u16).wrapping_sub(val as u16 + carry as u16);
+        let new_a = result as u8;
+
+        self.flags.update_zero(new_a == 0);
+        self.flags.update_half_carry(half_carry);
         self.flags.set_subtract();
-        self.flags.update_carry(self.a < val);
+        self.flags.update_carry(result > 0xFF);
+
+        new_a
     }
 
-    pub fn pop_stack(&mut self, memory: &mut Memory) -> u16 {
-        self.sp += 2;
-        memory.read_word(self.sp)
+    pub fn and_a(&mut self, val: u8) {
+        self.a &= val;
+        self.flags.update_zero(self.a == 0);
+        self.flags.clear_subtract();
+        self.flags.set_half_carry();
+        self.flags.clear_carry();
     }
 
-    pub fn push_stack(&mut self, memory: &mut Memory, value: u16) {
-        memory.write_word(self.sp, value);
-        self.sp -= 2;
+    pub fn or_a(&mut self, val: u8) {
+        self.a |= val;
+        self.flags.update_zero(self.a == 0);
+        self.flags.clear_subtract();
+        self.flags.clear_half_carry();
+        self.flags.clear_carry();
+    }
+
+    pub fn xor_a(&mut self, val: u8) {
+        self.a ^= val;
+        self.flags.update_zero(self.a == 0);
+        self.flags.clear_subtract();
+        self.flags.clear_half_carry();
+        self.flags.clear_carry();
+    }
+
+    /// Increments an 8-bit value, updating the Z, N and H flags. The carry flag is untouched.
+    pub fn inc(&mut self, val: u8) -> u8 {
+        let new_val = val.wrapping_add(1);
+        self.flags.update_zero(new_val == 0);
+        self.flags.clear_subtract();
+        self.flags.update_half_carry(val & 0xF == 0xF);
+        new_val
+    }
+
+    /// Decrements an 8-bit value, updating the Z, N and H flags. The carry flag is untouched.
+    pub fn dec(&mut self, val: u8) -> u8 {
+        let new_val = val.wrapping_sub(1);
+        self.flags.update_zero(new_val == 0);
+        self.flags.set_subtract();
+        self.flags.update_half_carry(val & 0xF == 0);
+        new_val
+    }
+
+    pub fn add_hl(&mut self, val: u16) {
+        let hl = self.hl();
+        let half_carry = (hl & 0x0FFF) + (val & 0x0FFF) > 0x0FFF;
+        let (new_hl, carry) = hl.overflowing_add(val);
+
+        self.flags.clear_subtract();
+        self.flags.update_half_carry(half_carry);
+        self.flags.update_carry(carry);
+
+        self.set_hl(new_hl);
+    }
+
+    /// Adds a signed offset to the stack pointer and returns the result, without storing it. Used
+    /// by `ADD SP, r8` and `LD HL, SP+r8`, which both set H and C based on the lower byte.
+    pub fn sp_plus_offset(&mut self, offset: i8) -> u16 {
+        let sp = self.sp;
+        let offset = offset as u16;
+
+        self.flags.clear_zero();
+        self.flags.clear_subtract();
+        self.flags
+            .update_half_carry((sp & 0x0F) + (offset & 0x0F) > 0x0F);
+        self.flags
+            .update_carry((sp & 0xFF) + (offset & 0xFF) > 0xFF);
+
+        sp.wrapping_add(offset)
+    }
+
+    /// Decimal adjusts A after a BCD addition or subtraction.
+    pub fn daa(&mut self) {
+        let mut a = self.a;
+        let mut carry = self.flags.c();
+
+        if self.flags.n() {
+            if carry {
+                a = a.wrapping_sub(0x60);
+            }
+            if self.flags.h() {
+                a = a.wrapping_sub(0x06);
+            }
+        } else {
+            if carry || a > 0x99 {
+                a = a.wrapping_add(0x60);
+                carry = true;
+            }
+            if self.flags.h() || (a & 0x0F) > 0x09 {
+                a = a.wrapping_add(0x06);
+            }
+        }
+
+        self.flags.update_zero(a == 0);
+        self.flags.clear_half_carry();
+        self.flags.update_carry(carry);
+
+        self.a = a;
+    }
+
+    pub fn pop_stack(&mut self, memory: &mut dyn Bus) -> u16 {
+        let value = memory.read_word(self.sp);
+        self.sp = self.sp.wrapping_add(2);
+        value
+    }
+
+    /// Pushes a word onto the stack. This takes an internal cycle to decrement the stack pointer
+    /// before the two writes.
+    pub fn push_stack(&mut self, memory: &mut dyn Bus, value: u16) {
+        memory.tick(4);
+        // The high byte is written first
+        self.sp = self.sp.wrapping_sub(1);
+        memory.write_byte(self.sp, (value >> 8) as u8);
+        self.sp = self.sp.wrapping_sub(1);
+        memory.write_byte(self.sp, value as u8);
     }
 }
 
@@ -220,6 +463,17 @@ impl Flags {
         (self.0 & BITMASK_CARRY) > 0
     }
 
+    /// The raw value of the F register. The lower nibble is always zero.
+    pub fn bits(&self) -> u8 {
+        self.0
+    }
+
+    /// Overwrites all flags at once. The lower nibble of F does not exist on hardware and is
+    /// discarded.
+    pub fn set_bits(&mut self, bits: u8) {
+        self.0 = bits & 0xF0;
+    }
+
     fn update(&mut self, mask: u8, set: bool) {
         self.0 = if set { self.0 | mask } else { self.0 & !mask };
     }
@@ -236,6 +490,10 @@ impl Flags {
         self.update_subtract(true);
     }
 
+    pub fn set_carry(&mut self) {
+        self.update_carry(true);
+    }
+
     pub fn clear_zero(&mut self) {
         self.update_zero(false);
     }
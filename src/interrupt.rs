@@ -0,0 +1,110 @@
+use crate::{Bus, Cpu};
+
+/// The five interrupt sources of the gameboy. The discriminant is the bit in the IF (0xFF0F) and
+/// IE (0xFFFF) registers, and also the priority: a lower bit is serviced first.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Interrupt {
+    VBlank = 0,
+    LcdStat = 1,
+    Timer = 2,
+    Serial = 3,
+    Joypad = 4,
+}
+
+impl Interrupt {
+    /// All interrupts, ordered from highest to lowest priority.
+    pub const ALL: [Interrupt; 5] = [
+        Interrupt::VBlank,
+        Interrupt::LcdStat,
+        Interrupt::Timer,
+        Interrupt::Serial,
+        Interrupt::Joypad,
+    ];
+
+    /// The bit of this interrupt in the IF and IE registers.
+    pub fn mask(self) -> u8 {
+        1 << self as u8
+    }
+
+    /// The address the CPU jumps to when this interrupt is serviced.
+    pub fn vector(self) -> u16 {
+        0x40 + 8 * self as u16
+    }
+
+    /// Returns the interrupt with the highest priority in the given IF & IE bits, if any.
+    pub fn highest_priority(pending: u8) -> Option<Interrupt> {
+        Interrupt::ALL
+            .iter()
+            .copied()
+            .find(|interrupt| pending & interrupt.mask() > 0)
+    }
+}
+
+/// Services the highest priority pending interrupt if the CPU has interrupts enabled (IME).
+///
+/// This pushes the program counter, clears IME and the interrupt's IF bit, and jumps to the
+/// interrupt vector. This takes 20 cycles. Returns `true` if an interrupt was dispatched.
+pub fn dispatch(memory: &mut dyn Bus, cpu: &mut Cpu) -> bool {
+    if !cpu.interrupts_enabled() {
+        return false;
+    }
+    let interrupt = match Interrupt::highest_priority(memory.pending_interrupts()) {
+        Some(interrupt) => interrupt,
+        None => return false,
+    };
+
+    cpu.disable_interrupts();
+    memory.acknowledge_interrupt(interrupt);
+    // Two wait cycles, followed by pushing the program counter in three cycles
+    memory.tick(8);
+    cpu.push_stack(memory, cpu.program_counter());
+    cpu.set_program_counter(interrupt.vector());
+
+    true
+}
+
+#[test]
+fn interrupt_priority() {
+    assert_eq!(Interrupt::highest_priority(0), None);
+    assert_eq!(
+        Interrupt::highest_priority(0b1_0100),
+        Some(Interrupt::Timer)
+    );
+    assert_eq!(
+        Interrupt::highest_priority(0b1_1111),
+        Some(Interrupt::VBlank)
+    );
+    assert_eq!(Interrupt::Joypad.vector(), 0x60);
+}
+
+#[test]
+fn interrupt_dispatch() {
+    let mut rom = crate::cartridge::test_rom(&[
+        0xFB, // 0x100 EI
+        0x00, // 0x101 NOP, interrupts are enabled after this instruction
+        0x00, // 0x102 NOP
+    ]);
+    rom[0x50] = 0xD9; // RETI at the timer vector
+    let mut memory = crate::opcodes::test_memory_with_rom(rom);
+    let mut cpu = crate::opcodes::test_cpu();
+    cpu.set_sp(0xFFFE);
+    memory.write_byte(crate::memory::INTERRUPT_ADDRESS, Interrupt::Timer.mask());
+    memory.request_interrupt(Interrupt::Timer);
+
+    crate::opcodes::execute(&mut memory, &mut cpu);
+    assert!(!cpu.interrupts_enabled());
+    crate::opcodes::execute(&mut memory, &mut cpu);
+    assert!(cpu.interrupts_enabled());
+    assert_eq!(cpu.program_counter(), 0x102);
+
+    let cycles = cpu.elapsed_cycles();
+    crate::opcodes::execute(&mut memory, &mut cpu);
+    assert_eq!(cpu.program_counter(), 0x50);
+    assert_eq!(cpu.elapsed_cycles() - cycles, 20);
+    assert!(!cpu.interrupts_enabled());
+    assert_eq!(memory.pending_interrupts(), 0);
+
+    crate::opcodes::execute(&mut memory, &mut cpu);
+    assert_eq!(cpu.program_counter(), 0x102);
+    assert!(cpu.interrupts_enabled());
+}
@@ -1,10 +1,15 @@
 // #![no_std]
 
+pub mod boot;
+pub mod bus;
+pub mod cartridge;
 pub mod cpu;
+pub mod interrupt;
 pub mod memory;
 pub mod opcodes;
+pub mod save;
 
-pub use self::{cpu::Cpu, memory::Memory};
+pub use self::{bus::Bus, cartridge::Cartridge, cpu::Cpu, interrupt::Interrupt, memory::Memory};
 
 pub trait Video {
     fn is_running(&self) -> bool;
@@ -12,6 +17,11 @@ pub trait Video {
     fn set_tile_pixel(&mut self, tile_index: u16, row_index: u16, pixel_index: usize, color: Color);
     fn button_state(&mut self) -> ButtonState;
     fn direction_state(&mut self) -> DirectionState;
+
+    /// The tilt of the Game Boy, for cartridges with an accelerometer. Defaults to held flat.
+    fn tilt_state(&mut self) -> TiltState {
+        TiltState::default()
+    }
 }
 
 #[derive(Debug)]
@@ -30,20 +40,24 @@ pub struct DirectionState {
     pub right: bool,
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+/// The tilt of the Game Boy in g, from -1.0 to 1.0 on both axes.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct TiltState {
+    /// Positive when tilted to the right
+    pub x: f32,
+    /// Positive when tilted towards the player
+    pub y: f32,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
 pub enum Color {
     Black = 0b00,
     LightGray = 0b01,
     DarkGray = 0b10,
+    #[default]
     White = 0b11,
 }
 
-impl Default for Color {
-    fn default() -> Self {
-        Color::White
-    }
-}
-
 impl Color {
     pub fn to_u8_rgb(self) -> u32 {
         let (r, g, b) = match self {
@@ -2,11 +2,20 @@ extern crate gameboy_emulator;
 
 mod video;
 
-use gameboy_emulator::{cpu::Cpu, memory::*, Video};
+use gameboy_emulator::{
+    boot::{self, BootRom, Model},
+    cartridge::{camera, ImageFile, RtcClock, ROM_BANK_SIZE},
+    cpu::Cpu,
+    memory::Memory,
+    save::SaveFile,
+    Cartridge, Video,
+};
 use std::time::{Duration, Instant};
 use structopt::StructOpt;
 
 const TARGET_FPS: u32 = 60;
+/// The number of frames between writes of the save file
+const SAVE_INTERVAL: u32 = 5 * TARGET_FPS;
 
 #[derive(Debug, StructOpt)]
 #[structopt(name = "Gameboy emulator", about = "Gameboy emulator written in rust")]
@@ -19,60 +28,169 @@ pub struct Opts {
     #[structopt(long = "no_output")]
     no_output: bool,
 
+    /// If present, the real time clock of the cartridge follows the emulated time instead of the
+    /// time of the host. This makes runs deterministic
+    #[structopt(long = "emulated_rtc")]
+    emulated_rtc: bool,
+
+    /// A PNG image that the Pocket Camera sees, instead of a gradient
+    #[structopt(long = "camera_image", parse(from_os_str))]
+    camera_image: Option<std::path::PathBuf>,
+
+    /// The directory of the save files of battery backed cartridges, defaults to the directory of
+    /// the rom
+    #[structopt(long = "save_dir", parse(from_os_str))]
+    save_dir: Option<std::path::PathBuf>,
+
+    /// A boot rom to run instead of the built-in DMG boot rom, for example of the MGB, SGB or CGB
+    #[structopt(long = "boot_rom", parse(from_os_str))]
+    boot_rom: Option<std::path::PathBuf>,
+
+    /// Start the game right away at 0x0100, in the state that the boot rom of `model` leaves the
+    /// Game Boy in
+    #[structopt(long = "skip_boot", conflicts_with = "boot_rom")]
+    skip_boot: bool,
+
+    /// The Game Boy model that is emulated: dmg0, dmg, mgb, sgb, sgb2 or cgb. This decides the
+    /// state after `skip_boot`
+    #[structopt(long = "model", default_value = "dmg")]
+    model: Model,
+
     /// The gameboy (.gb) rom that you want to play
     #[structopt(parse(from_os_str))]
     rom: std::path::PathBuf,
+
+    #[structopt(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, StructOpt)]
+pub enum Command {
+    /// Print a disassembly of the rom instead of running it
+    #[structopt(name = "disasm")]
+    Disasm {
+        /// The first rom bank to disassemble
+        #[structopt(long = "from", default_value = "0")]
+        from: usize,
+
+        /// The last rom bank to disassemble, defaults to the first bank
+        #[structopt(long = "to")]
+        to: Option<usize>,
+    },
+
+    /// Export the photo album of a Pocket Camera save as PNG images
+    #[structopt(name = "album")]
+    Album {
+        /// The save file, defaults to the save of the rom
+        #[structopt(long = "save", parse(from_os_str))]
+        save: Option<std::path::PathBuf>,
+
+        /// The directory to write the images to
+        #[structopt(long = "output", default_value = ".", parse(from_os_str))]
+        output: std::path::PathBuf,
+    },
 }
 
 fn main() {
     use std::io::Read;
     let opts = Opts::from_args();
 
-    let mut video: Box<dyn Video> = if opts.no_output {
-        Box::new(video::NoOutput)
-    } else if opts.terminal {
-        Box::new(video::TerminalVideo::init())
-    } else {
-        Box::new(video::MinifbVideo::init())
-    };
-
-    let mut fs = std::fs::File::open(opts.rom).expect("Could not open file");
+    let mut fs = std::fs::File::open(&opts.rom).expect("Could not open file");
     let mut rom = Vec::new();
     fs.read_to_end(&mut rom).expect("Could not read file");
 
-    let name = match std::str::from_utf8(&rom[0x134..0x142]) {
-        Ok(name) => name.trim_end_matches('\0'),
+    match &opts.command {
+        Some(Command::Disasm { from, to }) => {
+            disassemble(&rom, *from, to.unwrap_or(*from));
+            return;
+        }
+        Some(Command::Album { save, output }) => {
+            let save = save.clone().unwrap_or_else(|| {
+                SaveFile::for_rom(&opts.rom, opts.save_dir.as_deref())
+                    .path()
+                    .to_owned()
+            });
+            let ram = std::fs::read(&save).expect("Could not read save file");
+            match camera::export_album(&ram, output) {
+                Ok(count) => println!("Exported {} photos to {}", count, output.display()),
+                Err(e) => {
+                    eprintln!("Could not export the album: {}", e);
+                    std::process::exit(1);
+                }
+            }
+            return;
+        }
+        None => {}
+    }
+
+    let mut cartridge = match Cartridge::new(rom) {
+        Ok(cartridge) => cartridge,
         Err(e) => {
-            eprintln!("Could not load the rom name, you're probably loading an invalid cartridge");
-            panic!("{:?}", e);
+            eprintln!("Could not load {}: {}", opts.rom.display(), e);
+            std::process::exit(1);
         }
     };
-    println!("Found game: {} (0x{:X} bytes)", name, rom.len());
-
-    let mut fixed = [0u8; CARTRIDGE_ROM_FIXED_BANK_SIZE];
-    fixed.copy_from_slice(&rom[..CARTRIDGE_ROM_FIXED_BANK_SIZE]);
-
-    let switchable_banks_len =
-        (rom.len() - CARTRIDGE_ROM_FIXED_BANK_SIZE) / CARTRIDGE_ROM_SWITCHABLE_BANK_SIZE;
-    let mut switchable_roms = Vec::with_capacity(switchable_banks_len);
-
-    for i in 0..switchable_banks_len {
-        let mut bank = [0u8; CARTRIDGE_ROM_SWITCHABLE_BANK_SIZE];
-        let start = CARTRIDGE_ROM_FIXED_BANK_SIZE + CARTRIDGE_ROM_SWITCHABLE_BANK_SIZE * i;
-        let end = (start + CARTRIDGE_ROM_SWITCHABLE_BANK_SIZE).min(rom.len());
-        let len = end - start;
-        bank[..len].copy_from_slice(&rom[start..end]);
-
-        switchable_roms.push(bank);
+    if opts.emulated_rtc {
+        cartridge.set_rtc_clock(RtcClock::EmulatedCycles);
     }
+    let header = cartridge.header();
     println!(
-        "Loaded 1 fixed and {} switchable banks (total {} bytes)",
-        switchable_banks_len,
-        rom.len()
+        "Found game: {} ({:?}, {} rom banks, 0x{:X} bytes of ram)",
+        header.title,
+        header.cartridge_type.mapper,
+        cartridge.rom_banks(),
+        header.ram_size
     );
 
-    let mut memory = Memory::new(fixed, &switchable_roms, &mut *video);
+    let video: Box<dyn Video> = if opts.no_output {
+        Box::new(video::NoOutput)
+    } else if opts.terminal {
+        Box::new(video::TerminalVideo::init())
+    } else {
+        Box::new(video::MinifbVideo::init())
+    };
+
+    let mut memory = Memory::new(cartridge, video);
+    if let Some(path) = &opts.camera_image {
+        match ImageFile::open(path) {
+            Ok(image) => memory.mapper_mut().set_image_source(Box::new(image)),
+            Err(e) => {
+                eprintln!("Could not load {}: {}", path.display(), e);
+                std::process::exit(1);
+            }
+        }
+    }
+    let mut save = if memory.cartridge().header().cartridge_type.battery {
+        let mut save = SaveFile::for_rom(&opts.rom, opts.save_dir.as_deref());
+        match save.load(memory.mapper_mut()) {
+            Ok(true) => println!("Loaded {}", save.path().display()),
+            Ok(false) => {}
+            Err(e) => {
+                eprintln!("Could not load {}: {}", save.path().display(), e);
+                std::process::exit(1);
+            }
+        }
+        Some(save)
+    } else {
+        None
+    };
+    memory.set_model(opts.model);
     let mut cpu = Cpu::default();
+    if let Some(path) = &opts.boot_rom {
+        let boot_rom = std::fs::read(path)
+            .map_err(|e| e.to_string())
+            .and_then(|data| BootRom::new(data).map_err(|e| e.to_string()));
+        match boot_rom {
+            Ok(boot_rom) => memory.set_boot_rom(boot_rom),
+            Err(e) => {
+                eprintln!("Could not load {}: {}", path.display(), e);
+                std::process::exit(1);
+            }
+        }
+    } else if opts.skip_boot {
+        boot::skip_boot(&mut cpu, &mut memory, opts.model);
+    }
+    let mut frames = 0;
 
     let mut last_frame_start = Instant::now();
     let target_frame_time = Duration::from_millis(1000 / TARGET_FPS as u64);
@@ -82,11 +200,14 @@ fn main() {
     while memory.video.is_running() {
         gameboy_emulator::opcodes::execute(&mut memory, &mut cpu);
 
-        memory.update_scanline(&mut cpu.scanline_cycles);
-
         if cpu.frame_elapsed(TARGET_FPS) {
             memory.video.render();
 
+            frames += 1;
+            if frames % SAVE_INTERVAL == 0 {
+                write_save(&mut save, &memory);
+            }
+
             let diff = Instant::now().duration_since(last_frame_start);
             if target_frame_time > diff && !opts.no_output {
                 let sleep_time = target_frame_time - diff;
@@ -95,4 +216,53 @@ fn main() {
             last_frame_start = Instant::now();
         }
     }
+    write_save(&mut save, &memory);
+}
+
+fn write_save(save: &mut Option<SaveFile>, memory: &Memory) {
+    if let Some(save) = save {
+        if let Err(e) = save.write(memory.mapper()) {
+            eprintln!("Could not write {}: {}", save.path().display(), e);
+        }
+    }
+}
+
+/// Prints a linear disassembly of the rom banks `first..=last`. Bank 0 is shown at 0x0000 and the
+/// other banks at 0x4000, where they would be mapped by the cartridge.
+fn disassemble(rom: &[u8], first: usize, last: usize) {
+    let bank_count = rom.len().div_ceil(ROM_BANK_SIZE);
+    if last >= bank_count {
+        eprintln!("The rom only has {} banks", bank_count);
+        std::process::exit(1);
+    }
+
+    for bank_index in first..=last {
+        let start = bank_index * ROM_BANK_SIZE;
+        let end = (start + ROM_BANK_SIZE).min(rom.len());
+        let bank = &rom[start..end];
+        let base: u16 = if bank_index == 0 { 0x0000 } else { 0x4000 };
+        let read_byte = |address: u16| {
+            let offset = address.wrapping_sub(base) as usize;
+            bank.get(offset).copied().unwrap_or(0)
+        };
+
+        println!("; ROM bank {}", bank_index);
+        let mut offset = 0;
+        while offset < bank.len() {
+            let address = base + offset as u16;
+            let (disassembly, length) =
+                gameboy_emulator::opcodes::disassemble_with(read_byte, address);
+            let bytes = disassembly
+                .bytes()
+                .iter()
+                .map(|byte| format!("{:02X}", byte))
+                .collect::<Vec<_>>()
+                .join(" ");
+            println!(
+                "{:02X}:{:04X}  {:<8}  {}",
+                bank_index, address, bytes, disassembly
+            );
+            offset += length as usize;
+        }
+    }
 }
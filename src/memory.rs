@@ -1,9 +1,16 @@
 #![allow(dead_code)]
 
-use crate::{Color, Video};
+use crate::{
+    boot::{BootRom, Model},
+    cartridge::Mapper,
+    Bus, Cartridge, Interrupt, Video,
+};
 use core::ops::RangeInclusive;
 
+/// $FFFF IE, the interrupt enable register
 pub const INTERRUPT_ADDRESS: u16 = 0xFFFF;
+/// $FF0F IF, the interrupt request register
+pub const INTERRUPT_FLAG_ADDRESS: u16 = 0xFF0F;
 pub const BIOS: [u8; 256] = [
     0x31, 0xFE, 0xFF, 0xAF, 0x21, 0xFF, 0x9F, 0x32, 0xCB, 0x7C, 0x20, 0xFB, 0x21, 0x26, 0xFF, 0x0E,
     0x11, 0x3E, 0x80, 0x32, 0xE2, 0x0C, 0x3E, 0xF3, 0xE2, 0x32, 0x3E, 0x77, 0x77, 0x3E, 0xFC, 0xE0,
@@ -26,14 +33,8 @@ pub const BIOS: [u8; 256] = [
 struct MemMap([u8; 0x10_000]);
 
 impl MemMap {
-    pub fn new(
-        switched_bank: &[u8; CARTRIDGE_ROM_SWITCHABLE_BANK_SIZE],
-        fixed_bank: &[u8; CARTRIDGE_ROM_FIXED_BANK_SIZE],
-    ) -> Self {
-        let mut mem = MemMap([0u8; 0x10_000]);
-        mem.0[CARTRIDGE_ROM_SWITCHABLE].copy_from_slice(switched_bank);
-        mem.0[..CARTRIDGE_ROM_FIXED_BANK_SIZE].copy_from_slice(fixed_bank);
-        mem
+    pub fn new() -> Self {
+        MemMap([0u8; 0x10_000])
     }
 }
 
@@ -69,21 +70,126 @@ const CARTRIDGE_ROM_FIXED: RangeInclusive<usize> = 0x0150..=0x3FFF;
 const CARTRIDGE_HEADER_AREA: RangeInclusive<usize> = 0x0100..=0x014F;
 /// $0000-$00FF Restart and Interrupt Vectors
 const RESTART_AND_INTERRUPT_VECTORS: RangeInclusive<usize> = 0x0000..=0x00FF;
+/// $0000-$7FFF Cartridge ROM, mapped by the cartridge
+const CARTRIDGE_ROM: RangeInclusive<usize> = 0x0000..=0x7FFF;
 /// $0000-$00FF BIOS_AREA
 const BIOS_AREA: RangeInclusive<usize> = 0x0000..=0x00FF;
 
 pub const CARTRIDGE_ROM_FIXED_BANK_SIZE: usize = 0x4000;
 pub const CARTRIDGE_ROM_SWITCHABLE_BANK_SIZE: usize = 0x4000;
 
-const REGISTER_CHANNEL_ONE_SOUND_LENGTH_WAVE_PATTERN: u16 = 0xFF11;
-const REGISTER_CHANNEL_ONE_VOLUME_ENVELOPE: u16 = 0xFF12;
-const REGISTER_CHANNEL_CONTROL: u16 = 0xFF24;
-const REGISTER_SOUND_SELECTION: u16 = 0xFF25;
-const REGISTER_SOUND_ENABLE: u16 = 0xFF26;
+/// $FF00 P1, selects the buttons or directions in bits 4-5 and reads them in bits 0-3
+const REGISTER_JOYPAD: u16 = 0xFF00;
+const REGISTER_DIVIDER: u16 = 0xFF04;
+/// $FF05 TIMA, incremented at the frequency selected by TAC
+const REGISTER_TIMER_COUNTER: u16 = 0xFF05;
+/// $FF06 TMA, loaded into TIMA when it overflows
+const REGISTER_TIMER_MODULO: u16 = 0xFF06;
+/// $FF07 TAC, bit 2 enables the timer and bits 0-1 select its frequency
+const REGISTER_TIMER_CONTROL: u16 = 0xFF07;
+const REGISTER_INTERRUPT_FLAG: u16 = INTERRUPT_FLAG_ADDRESS;
 const REGISTER_LCD_CONTROL: u16 = 0xFF40;
+/// $FF41 STAT, bits 0-2 hold the PPU mode and whether LY equals LYC
+const REGISTER_LCD_STATUS: u16 = 0xFF41;
 const REGISTER_SCROLL_POSITION_Y: u16 = 0xFF42;
 const REGISTER_SCANLINE_Y: u16 = 0xFF44;
+const REGISTER_SCANLINE_Y_COMPARE: u16 = 0xFF45;
+/// $FF46 DMA, starts an OAM DMA transfer from `value << 8`
+const REGISTER_DMA: u16 = 0xFF46;
 const REGISTER_BACKGROUND_PALETTE: u16 = 0xFF47;
+/// $FF50 BOOT, writing a value with bit 0 set unmaps the boot ROM until the next reset
+const REGISTER_DISABLE_BIOS: u16 = 0xFF50;
+
+/// How a hardware register at 0xFF00-0xFF7F behaves.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+struct IoRegister {
+    /// Bits that are unused or write-only, which always read as 1
+    read_mask: u8,
+    /// Bits that can be written, the other bits keep their value
+    write_mask: u8,
+}
+
+impl IoRegister {
+    const fn new(read_mask: u8, write_mask: u8) -> IoRegister {
+        IoRegister {
+            read_mask,
+            write_mask,
+        }
+    }
+}
+
+const READ_WRITE: IoRegister = IoRegister::new(0x00, 0xFF);
+const READ_ONLY: IoRegister = IoRegister::new(0x00, 0x00);
+const WRITE_ONLY: IoRegister = IoRegister::new(0xFF, 0xFF);
+
+/// Returns how the register at `address` behaves. Addresses without a register return `None`;
+/// they read as 0xFF and ignore writes, like an open bus.
+fn io_register(address: u16) -> Option<IoRegister> {
+    Some(match address {
+        REGISTER_JOYPAD => IoRegister::new(0xC0, 0x30),
+        // SB
+        0xFF01 => READ_WRITE,
+        // SC, only the transfer start and clock select bits exist
+        0xFF02 => IoRegister::new(0x7E, 0x81),
+        REGISTER_DIVIDER => READ_WRITE,
+        REGISTER_TIMER_COUNTER | REGISTER_TIMER_MODULO => READ_WRITE,
+        REGISTER_TIMER_CONTROL => IoRegister::new(0xF8, 0x07),
+        REGISTER_INTERRUPT_FLAG => IoRegister::new(0xE0, 0x1F),
+        // NR10
+        0xFF10 => IoRegister::new(0x80, 0x7F),
+        // NR11 and NR21, the sound length is write-only
+        0xFF11 | 0xFF16 => IoRegister::new(0x3F, 0xFF),
+        // NR12, NR22, NR42, NR43, NR50 and NR51
+        0xFF12 | 0xFF17 | 0xFF21 | 0xFF22 | 0xFF24 | 0xFF25 => READ_WRITE,
+        // NR13, NR23, NR31 and NR33, the frequencies and lengths
+        0xFF13 | 0xFF18 | 0xFF1B | 0xFF1D => WRITE_ONLY,
+        // NR14, NR24 and NR34, only the length enable can be read
+        0xFF14 | 0xFF19 | 0xFF1E => IoRegister::new(0xBF, 0xC7),
+        // NR30
+        0xFF1A => IoRegister::new(0x7F, 0x80),
+        // NR32
+        0xFF1C => IoRegister::new(0x9F, 0x60),
+        // NR41
+        0xFF20 => IoRegister::new(0xFF, 0x3F),
+        // NR44
+        0xFF23 => IoRegister::new(0xBF, 0xC0),
+        // NR52, the status of the channels in bits 0-3 is read-only
+        0xFF26 => IoRegister::new(0x70, 0x80),
+        // Wave pattern RAM
+        0xFF30..=0xFF3F => READ_WRITE,
+        REGISTER_LCD_CONTROL => READ_WRITE,
+        REGISTER_LCD_STATUS => IoRegister::new(0x80, 0x78),
+        // SCY, SCX
+        0xFF42 | 0xFF43 => READ_WRITE,
+        REGISTER_SCANLINE_Y => READ_ONLY,
+        // LYC, DMA, BGP, OBP0, OBP1, WY and WX
+        0xFF45..=0xFF4B => READ_WRITE,
+        // Bit 0 reflects whether the boot ROM is unmapped, it is not stored
+        REGISTER_DISABLE_BIOS => IoRegister::new(0xFE, 0x00),
+        _ => return None,
+    })
+}
+
+/// Maps echo RAM at 0xE000-0xFDFF to the internal RAM at 0xC000-0xDDFF that it mirrors.
+fn mirror_echo_ram(address: u16) -> u16 {
+    if ECHO_RAM.contains(&(address as usize)) {
+        address - 0x2000
+    } else {
+        address
+    }
+}
+
+/// The number of bytes, and M-cycles, of an OAM DMA transfer
+const DMA_LENGTH: u16 = 0xA0;
+
+/// An OAM DMA transfer in progress.
+#[derive(Debug, Clone, Copy)]
+struct Dma {
+    source: u16,
+    /// The cycles since the transfer started. The first M-cycle is spent setting up the transfer,
+    /// after which one byte is copied every M-cycle.
+    cycles: u16,
+}
 
 #[test]
 fn mem_size_sanity_check() {
@@ -94,12 +200,23 @@ fn mem_size_sanity_check() {
     );
 }
 
-pub struct Memory<'a> {
+pub struct Memory {
     map: MemMap,
-    switchable_banks: &'a [[u8; CARTRIDGE_ROM_SWITCHABLE_BANK_SIZE]],
-    bios_loaded: bool,
-    pub video: &'a mut dyn Video,
+    cartridge: Cartridge,
+    /// Handles all reads and writes to the cartridge ROM and RAM
+    mapper: Box<dyn Mapper>,
+    /// Mapped over the cartridge until 0xFF50 is written
+    boot_rom: Option<BootRom>,
+    model: Model,
+    pub video: Box<dyn Video>,
     scanline: ScanLine,
+    /// The cycles spent in the current `scanline` mode
+    scanline_cycles: u16,
+    /// The internal counter of the DIV register
+    divider: u16,
+    dma: Option<Dma>,
+    /// The pressed directions in bits 0-3 and buttons in bits 4-7, in the order of P1
+    joypad: u8,
 }
 
 #[derive(PartialEq, Eq, Hash, Debug, Copy, Clone)]
@@ -109,50 +226,216 @@ pub enum ScanLine {
     HorizontalBlank,
 }
 
-impl<'a> Memory<'a> {
-    pub fn new(
-        fixed_bank: [u8; CARTRIDGE_ROM_FIXED_BANK_SIZE],
-        switchable_banks: &'a [[u8; CARTRIDGE_ROM_SWITCHABLE_BANK_SIZE]],
-        video: &'a mut dyn Video,
-    ) -> Self {
-        let switched_bank = if switchable_banks.is_empty() {
-            &[0u8; CARTRIDGE_ROM_SWITCHABLE_BANK_SIZE]
-        } else {
-            &switchable_banks[0]
-        };
+impl Memory {
+    pub fn new(cartridge: Cartridge, video: Box<dyn Video>) -> Self {
         Memory {
-            map: MemMap::new(switched_bank, &fixed_bank),
-            bios_loaded: true,
+            map: MemMap::new(),
+            mapper: cartridge.create_mapper(),
+            boot_rom: Some(BootRom::default()),
+            model: Model::default(),
             video,
-            switchable_banks,
+            cartridge,
             scanline: ScanLine::Oam,
+            scanline_cycles: 0,
+            divider: 0,
+            dma: None,
+            joypad: 0,
         }
     }
 
-    pub fn update_scanline(&mut self, scanline_counter: &mut u16) {
+    fn update_scanline(&mut self) {
+        // The PPU is stopped while the LCD is off, so it does not request VBlank either
+        if !self.lcd_enabled() {
+            return;
+        }
         match self.scanline {
             ScanLine::Oam => {
-                if *scanline_counter >= 80 {
+                if self.scanline_cycles >= 80 {
                     self.scanline = ScanLine::Vram;
-                    *scanline_counter -= 80;
+                    self.scanline_cycles -= 80;
                 }
             }
             ScanLine::Vram => {
-                if *scanline_counter >= 172 {
+                if self.scanline_cycles >= 172 {
                     self.scanline = ScanLine::HorizontalBlank;
-                    *scanline_counter -= 172;
+                    self.scanline_cycles -= 172;
                 }
             }
             ScanLine::HorizontalBlank => {
-                if *scanline_counter >= 204 {
+                if self.scanline_cycles >= 204 {
                     self.scanline = ScanLine::Oam;
                     self.increment_scanline_y();
-                    *scanline_counter -= 204;
+                    if self.map.0[REGISTER_SCANLINE_Y as usize] == 144 {
+                        self.request_interrupt(Interrupt::VBlank);
+                        // The input is sampled once per frame, like games usually do
+                        self.sample_joypad();
+                        let tilt = self.video.tilt_state();
+                        self.mapper.set_tilt(tilt);
+                    }
+                    self.scanline_cycles -= 204;
                 }
             }
         }
     }
 
+    fn start_dma(&mut self, value: u8) {
+        // Sources above 0xDFFF read from the echo of the internal RAM
+        let source = match (value as u16) << 8 {
+            source @ 0xE000..=0xFFFF => source - 0x2000,
+            source => source,
+        };
+        self.dma = Some(Dma { source, cycles: 0 });
+    }
+
+    fn tick_dma(&mut self, cycles: u16) {
+        let mut dma = match self.dma {
+            Some(dma) => dma,
+            None => return,
+        };
+        let copied = |cycles: u16| (cycles / 4).saturating_sub(1).min(DMA_LENGTH);
+        let start = copied(dma.cycles);
+        dma.cycles += cycles;
+        let end = copied(dma.cycles);
+        for index in start..end {
+            let value = Memory::read_byte(self, dma.source + index);
+            self.map.0[*OBJECT_ATTRIBUTE_MEMORY.start() + index as usize] = value;
+        }
+        self.dma = if end < DMA_LENGTH { Some(dma) } else { None };
+    }
+
+    /// Whether the CPU can access `address`. During an OAM DMA transfer the CPU can only access
+    /// the I/O registers and HRAM, as the DMA uses the bus to everything else. The PPU blocks OAM
+    /// while it searches and draws a line, and VRAM while it draws.
+    fn cpu_can_access(&self, address: u16) -> bool {
+        let address = mirror_echo_ram(address) as usize;
+        if self.dma.is_some() && address < *HARDWARE_IO_REGISTERS.start() {
+            false
+        } else if OBJECT_ATTRIBUTE_MEMORY.contains(&address) {
+            !self.oam_blocked()
+        } else if VIDEO_RAM.contains(&address) {
+            self.ppu_mode() != 3
+        } else {
+            true
+        }
+    }
+
+    fn lcd_enabled(&self) -> bool {
+        self.map.0[REGISTER_LCD_CONTROL as usize] & 0x80 > 0
+    }
+
+    /// The mode in bits 0-1 of STAT: 0 in horizontal blank or while the LCD is off, 1 in vertical
+    /// blank, 2 while searching OAM and 3 while drawing.
+    fn ppu_mode(&self) -> u8 {
+        match self.scanline {
+            _ if !self.lcd_enabled() => 0,
+            _ if self.map.0[REGISTER_SCANLINE_Y as usize] >= 144 => 1,
+            ScanLine::Oam => 2,
+            ScanLine::Vram => 3,
+            ScanLine::HorizontalBlank => 0,
+        }
+    }
+
+    fn oam_blocked(&self) -> bool {
+        matches!(self.ppu_mode(), 2 | 3)
+    }
+
+    /// Resets the DIV register, as done by writing to it or by executing STOP.
+    fn reset_divider(&mut self) {
+        self.set_divider(0);
+    }
+
+    /// Advances the internal divider by `cycles`, one M-cycle at a time so the timer sees every
+    /// change of the divider.
+    fn tick_divider(&mut self, cycles: u16) {
+        for _ in 0..cycles / 4 {
+            self.set_divider(self.divider.wrapping_add(4));
+        }
+    }
+
+    /// Sets the internal divider. TIMA is incremented on the falling edge of the divider bit that
+    /// TAC selects, which also happens when resetting the divider clears that bit.
+    fn set_divider(&mut self, divider: u16) {
+        let before = self.timer_input();
+        self.divider = divider;
+        if before && !self.timer_input() {
+            self.increment_timer();
+        }
+    }
+
+    /// The divider bit that clocks TIMA, or `false` while the timer is disabled.
+    fn timer_input(&self) -> bool {
+        let control = self.map.0[REGISTER_TIMER_CONTROL as usize];
+        let bit = match control & 0b11 {
+            0b00 => 9,
+            0b01 => 3,
+            0b10 => 5,
+            _ => 7,
+        };
+        control & 0b100 > 0 && self.divider >> bit & 1 > 0
+    }
+
+    fn increment_timer(&mut self) {
+        let counter = self.map.0[REGISTER_TIMER_COUNTER as usize];
+        self.map.0[REGISTER_TIMER_COUNTER as usize] = match counter.checked_add(1) {
+            Some(counter) => counter,
+            None => {
+                self.request_interrupt(Interrupt::Timer);
+                self.map.0[REGISTER_TIMER_MODULO as usize]
+            }
+        };
+    }
+
+    /// Replaces the built-in DMG boot ROM, see `boot::BootRom`.
+    pub fn set_boot_rom(&mut self, boot_rom: BootRom) {
+        self.boot_rom = Some(boot_rom);
+    }
+
+    /// Sets the model, which decides how the unusable memory at 0xFEA0-0xFEFF reads.
+    pub fn set_model(&mut self, model: Model) {
+        self.model = model;
+    }
+
+    /// Whether the boot ROM overlays the start of the cartridge. Writes to the ROM region always
+    /// go to the mapper.
+    pub fn boot_rom_mapped(&self) -> bool {
+        self.boot_rom.is_some()
+    }
+
+    pub fn unmap_boot_rom(&mut self) {
+        self.boot_rom = None;
+    }
+
+    /// Sets the divider and the I/O `registers` to the state after the boot ROM, and clears the
+    /// other I/O registers.
+    pub fn reset_io_registers(&mut self, divider: u16, registers: &[(u16, u8)]) {
+        self.map.0[HARDWARE_IO_REGISTERS]
+            .iter_mut()
+            .for_each(|value| *value = 0);
+        for (address, value) in registers {
+            self.map.0[*address as usize] = *value;
+        }
+        self.divider = divider;
+    }
+
+    pub fn cartridge(&self) -> &Cartridge {
+        &self.cartridge
+    }
+
+    /// The mapper of the cartridge, which holds the cartridge RAM and any extra hardware.
+    pub fn mapper(&self) -> &dyn Mapper {
+        &*self.mapper
+    }
+
+    pub fn mapper_mut(&mut self) -> &mut dyn Mapper {
+        &mut *self.mapper
+    }
+
+    /// Whether the rumble motor of the cartridge is running. Frontends can poll this after every
+    /// frame.
+    pub fn rumble(&self) -> bool {
+        self.mapper.rumble()
+    }
+
     fn increment_scanline_y(&mut self) {
         let y = &mut self.map.0[0xff44];
         *y += 1;
@@ -162,58 +445,128 @@ impl<'a> Memory<'a> {
     }
 
     pub fn read_byte(&self, address: u16) -> u8 {
-        if self.bios_loaded && address < 0x0100 {
-            BIOS[address as usize]
+        let address = mirror_echo_ram(address);
+        if let Some(value) = self.boot_rom.as_ref().and_then(|rom| rom.read(address)) {
+            value
+        } else if CARTRIDGE_ROM.contains(&(address as usize)) {
+            self.mapper.read_rom(self.cartridge.rom(), address)
+        } else if CARTRIDGE_RAM.contains(&(address as usize)) {
+            self.mapper.read_ram(address)
+        } else if HARDWARE_IO_REGISTERS.contains(&(address as usize)) {
+            self.read_io_register(address)
+        } else if UNUSABLE_MEMORY.contains(&(address as usize)) {
+            self.read_unusable_memory(address)
         } else {
-            let val = self.map.0[address as usize];
-
-            if HARDWARE_IO_REGISTERS.contains(&(address as usize)) {
-                match address {
-                    REGISTER_SCROLL_POSITION_Y => {} // Read scroll Y
-                    REGISTER_SCANLINE_Y => {}        // Read vertical scanline
-                    _ => todo!(
-                        "Reading from hardware register 0x{:04x} (value 0x{:02X})",
-                        address,
-                        val
-                    ),
-                }
+            self.map.0[address as usize]
+        }
+    }
+
+    /// What the unusable memory at 0xFEA0-0xFEFF reads as, which differs between models. While
+    /// the PPU is using OAM it reads as 0xFF.
+    fn read_unusable_memory(&self, address: u16) -> u8 {
+        if self.oam_blocked() {
+            return 0xFF;
+        }
+        match self.model {
+            // The later CGB revisions repeat the upper nibble of the lower address byte
+            Model::Cgb => address as u8 & 0xF0 | (address as u8) >> 4,
+            _ => 0x00,
+        }
+    }
+
+    fn read_io_register(&self, address: u16) -> u8 {
+        let register = match io_register(address) {
+            Some(register) => register,
+            None => return 0xFF,
+        };
+        let value = match address {
+            REGISTER_JOYPAD => self.read_joypad(),
+            // DIV is the upper byte of the internal divider, which counts clock cycles
+            REGISTER_DIVIDER => (self.divider >> 8) as u8,
+            REGISTER_DISABLE_BIOS => !self.boot_rom_mapped() as u8,
+            REGISTER_LCD_STATUS => {
+                let y = self.map.0[REGISTER_SCANLINE_Y as usize];
+                let coincidence = y == self.map.0[REGISTER_SCANLINE_Y_COMPARE as usize];
+                self.map.0[address as usize] & 0x78 | (coincidence as u8) << 2 | self.ppu_mode()
             }
+            _ => self.map.0[address as usize],
+        };
+        value | register.read_mask
+    }
 
-            val
+    fn write_io_register(&mut self, address: u16, value: u8) {
+        let register = match io_register(address) {
+            Some(register) => register,
+            None => return,
+        };
+        let old = self.map.0[address as usize];
+        self.map.0[address as usize] = old & !register.write_mask | value & register.write_mask;
+
+        match address {
+            // Any write to DIV resets it
+            REGISTER_DIVIDER => self.reset_divider(),
+            REGISTER_DMA => self.start_dma(value),
+            // Turning the LCD off stops the PPU at the start of the first line
+            REGISTER_LCD_CONTROL if old & 0x80 > 0 && value & 0x80 == 0 => {
+                self.map.0[REGISTER_SCANLINE_Y as usize] = 0;
+                self.scanline = ScanLine::Oam;
+                self.scanline_cycles = 0;
+            }
+            // Once unmapped, the boot ROM can not be mapped again
+            REGISTER_DISABLE_BIOS if value & 0x01 > 0 => self.unmap_boot_rom(),
+            _ => {}
         }
     }
 
-    pub fn write_byte(&mut self, address: u16, value: u8) {
-        if self.bios_loaded && address < 0x0100 {
-            unimplemented!()
-        } else {
-            self.map.0[address as usize] = value;
+    fn sample_joypad(&mut self) {
+        let buttons = self.video.button_state();
+        let directions = self.video.direction_state();
+        self.joypad = [
+            directions.right,
+            directions.left,
+            directions.up,
+            directions.down,
+            buttons.a,
+            buttons.b,
+            buttons.select,
+            buttons.start,
+        ]
+        .iter()
+        .enumerate()
+        .fold(0, |joypad, (bit, pressed)| joypad | (*pressed as u8) << bit);
+    }
+
+    /// Clearing bit 4 of P1 selects the directions and clearing bit 5 the buttons. Pressed keys
+    /// read as 0.
+    fn read_joypad(&self) -> u8 {
+        let select = self.map.0[REGISTER_JOYPAD as usize] & 0x30;
+        let mut pressed = 0;
+        if select & 0x10 == 0 {
+            pressed |= self.joypad & 0x0F;
+        }
+        if select & 0x20 == 0 {
+            pressed |= self.joypad >> 4;
         }
+        select | !pressed & 0x0F
+    }
 
+    pub fn write_byte(&mut self, address: u16, value: u8) {
+        let address = mirror_echo_ram(address);
+        if UNUSABLE_MEMORY.contains(&(address as usize)) {
+            return;
+        }
+        if CARTRIDGE_ROM.contains(&(address as usize)) {
+            self.mapper.write_rom(address, value);
+            return;
+        } else if CARTRIDGE_RAM.contains(&(address as usize)) {
+            self.mapper.write_ram(address, value);
+            return;
+        }
         if HARDWARE_IO_REGISTERS.contains(&(address as usize)) {
-            match address {
-                REGISTER_CHANNEL_ONE_SOUND_LENGTH_WAVE_PATTERN => {
-                    println!("Channel 1 {:?}", ChannelOneSoundLengthWavePattern(value))
-                }
-                REGISTER_CHANNEL_ONE_VOLUME_ENVELOPE => {
-                    println!("Channel 1 {:?}", ChannelOneVolumeEnvelope(value))
-                }
-                REGISTER_CHANNEL_CONTROL => println!("Channel control {:?}", ChannelControl(value)),
-                REGISTER_SOUND_SELECTION => println!("Sound selection {:?}", SoundSelection(value)),
-                REGISTER_SOUND_ENABLE => println!("Sound {:?}", SoundEnable(value)),
-                REGISTER_LCD_CONTROL => println!("{:?}", LcdControl(value)),
-                REGISTER_SCROLL_POSITION_Y => {} // Writing scroll position y
-                REGISTER_BACKGROUND_PALETTE => {
-                    println!("Background palette {:?}", BackgroundPalette(value))
-                }
-                REGISTER_DISABLE_BIOS => { self.bios_loaded = false; }
-                _ => todo!(
-                    "Writing to hardware register 0x{:04X} (value 0x{:02X})",
-                    address,
-                    value
-                ),
-            }
+            self.write_io_register(address, value);
+            return;
         }
+        self.map.0[address as usize] = value;
 
         if VIDEO_RAM.contains(&(address as usize)) {
             // More info: https://blog.ryanlevick.com/DMG-01/public/book/graphics/tile_ram.html
@@ -254,160 +607,234 @@ impl<'a> Memory<'a> {
             }
         }
     }
+}
 
-    pub fn read_word(&self, address: u16) -> u16 {
-        let high = self.read_byte(address);
-        let low = self.read_byte(address + 1);
-        bytes_to_word(high, low)
+impl Bus for Memory {
+    fn read_byte(&mut self, address: u16) -> u8 {
+        if !self.cpu_can_access(address) {
+            return 0xFF;
+        }
+        Memory::read_byte(self, address)
     }
 
-    pub fn write_word(&mut self, address: u16, value: u16) {
-        let (high, low) = word_to_bytes(value);
-        self.write_byte(address, high);
-        self.write_byte(address + 1, low);
+    fn write_byte(&mut self, address: u16, value: u8) {
+        if self.cpu_can_access(address) {
+            Memory::write_byte(self, address, value)
+        }
     }
-}
 
-struct ChannelOneSoundLengthWavePattern(u8);
-
-impl core::fmt::Debug for ChannelOneSoundLengthWavePattern {
-    fn fmt(&self, fmt: &mut core::fmt::Formatter) -> core::fmt::Result {
-        let duty = (self.0 & 0b1100_0000) >> 6;
-        let length_wave = self.0 & 0b0011_1111;
-        write!(
-            fmt,
-            "Wave duty: {}, sound length: {} seconds",
-            match duty {
-                0b00 => "12.5%",
-                0b01 => "25%",
-                0b10 => "50%",
-                0b11 => "75%",
-                _ => unreachable!(),
-            },
-            1.0 / ((64.0 - length_wave as f32) * 256.0),
-        )
+    fn tick(&mut self, cycles: u16) {
+        self.tick_dma(cycles);
+        self.tick_divider(cycles);
+        if self.lcd_enabled() {
+            self.scanline_cycles += cycles;
+        }
+        self.update_scanline();
+        self.mapper.tick(cycles);
     }
-}
 
-struct ChannelOneVolumeEnvelope(u8);
-
-impl core::fmt::Debug for ChannelOneVolumeEnvelope {
-    fn fmt(&self, fmt: &mut core::fmt::Formatter) -> core::fmt::Result {
-        let initial_volume = (self.0 & 0b0111_0000) >> 4;
-        let envelope_direction = (self.0 & 0b0000_1000) >> 3;
-        let numer_of_envelope_sweeps = self.0 & 0b0000_0111;
-
-        write!(
-            fmt,
-            "Initial volume: {}, direction: {} ({}), number of sweeps: {}",
-            initial_volume,
-            envelope_direction,
-            if envelope_direction > 0 {
-                "increase"
-            } else {
-                "decrease"
-            },
-            numer_of_envelope_sweeps
-        )
+    fn joypad_lines(&mut self) -> u8 {
+        // STOP waits for the buttons, so they can not wait for the next frame to be sampled
+        self.sample_joypad();
+        self.read_joypad() & 0x0F
     }
 }
 
-struct SoundEnable(u8);
-impl core::fmt::Debug for SoundEnable {
-    fn fmt(&self, fmt: &mut core::fmt::Formatter) -> core::fmt::Result {
-        let all_sound_off = (self.0 & 0b1000_0000) > 0;
-        let sound_4_on = (self.0 & 0b0000_1000) > 0;
-        let sound_3_on = (self.0 & 0b0000_0100) > 0;
-        let sound_2_on = (self.0 & 0b0000_0010) > 0;
-        let sound_1_on = (self.0 & 0b0000_0001) > 0;
-
-        write!(
-            fmt,
-            "Sound enable: {} (4: {}, 3: {}, 2: {}, 1: {})",
-            !all_sound_off, sound_4_on, sound_3_on, sound_2_on, sound_1_on
-        )
-    }
-}
+#[test]
+fn timer() {
+    let mut memory = crate::opcodes::test_memory(&[]);
+    memory.write_byte(REGISTER_TIMER_MODULO, 0xF0);
+    memory.write_byte(REGISTER_TIMER_COUNTER, 0xFE);
+    memory.write_byte(REGISTER_TIMER_CONTROL, 0b101);
+
+    // 16 cycles per increment
+    memory.tick(16);
+    assert_eq!(memory.read_byte(REGISTER_TIMER_COUNTER), 0xFF);
+    assert_eq!(
+        memory.read_byte(REGISTER_INTERRUPT_FLAG) & Interrupt::Timer.mask(),
+        0
+    );
+    memory.tick(16);
+    assert_eq!(memory.read_byte(REGISTER_TIMER_COUNTER), 0xF0);
+    assert_ne!(
+        memory.read_byte(REGISTER_INTERRUPT_FLAG) & Interrupt::Timer.mask(),
+        0
+    );
 
-struct SoundSelection(u8);
-impl core::fmt::Debug for SoundSelection {
-    fn fmt(&self, fmt: &mut core::fmt::Formatter) -> core::fmt::Result {
-        let masks = &[
-            (0b1000_0000, "channel 4 to SO2"),
-            (0b0100_0000, "channel 3 to SO2"),
-            (0b0010_0000, "channel 2 to SO2"),
-            (0b0001_0000, "channel 1 to SO2"),
-            (0b0000_1000, "channel 4 to SO1"),
-            (0b0000_0100, "channel 3 to SO1"),
-            (0b0000_0010, "channel 2 to SO1"),
-            (0b0000_0001, "channel 1 to SO1"),
-        ];
-        let mut first = true;
-        for (mask, text) in masks {
-            if self.0 & mask > 0 {
-                if first {
-                    first = false;
-                } else {
-                    write!(fmt, ", ")?;
-                }
-                write!(fmt, "{}", text)?;
-            }
-        }
-        Ok(())
-    }
+    // Resetting DIV while the selected bit is set is a falling edge as well
+    memory.tick(8);
+    memory.write_byte(REGISTER_DIVIDER, 0);
+    assert_eq!(memory.read_byte(REGISTER_TIMER_COUNTER), 0xF1);
+
+    memory.write_byte(REGISTER_TIMER_CONTROL, 0b001);
+    memory.tick(64);
+    assert_eq!(
+        memory.read_byte(REGISTER_TIMER_COUNTER),
+        0xF1,
+        "the timer is disabled"
+    );
 }
 
-struct ChannelControl(u8);
-impl core::fmt::Debug for ChannelControl {
-    fn fmt(&self, fmt: &mut core::fmt::Formatter) -> core::fmt::Result {
-        let so2_enable = (self.0 & 0b1000_0000) > 0;
-        let so2_volume = (self.0 & 0b0111_0000) >> 4;
-        let so1_enable = (self.0 & 0b0000_1000) > 0;
-        let so1_volume = self.0 & 0b0000_0111;
+#[test]
+fn no_vblank_while_lcd_off() {
+    let mut memory = crate::opcodes::test_memory(&[]);
+    memory.write_byte(REGISTER_LCD_CONTROL, 0x11);
 
-        if so2_enable {
-            write!(fmt, "so2: {}", so2_volume)?;
-        } else {
-            write!(fmt, "so2: disabled")?;
-        }
-        write!(fmt, ", ")?;
-        if so1_enable {
-            write!(fmt, "so1: {}", so1_volume)
-        } else {
-            write!(fmt, "so1: disabled")
-        }
+    for _ in 0..70224 * 2 / 4 {
+        memory.tick(4);
     }
-}
-
-struct BackgroundPalette(u8);
-impl core::fmt::Debug for BackgroundPalette {
-    fn fmt(&self, fmt: &mut core::fmt::Formatter) -> core::fmt::Result {
-        let shade_3: Color = ((self.0 & 0b1100_0000) >> 6).into();
-        let shade_2: Color = ((self.0 & 0b0011_0000) >> 4).into();
-        let shade_1: Color = ((self.0 & 0b0000_1100) >> 2).into();
-        let shade_0: Color = (self.0 & 0b0000_0011).into();
+    assert_eq!(
+        memory.read_byte(REGISTER_INTERRUPT_FLAG) & Interrupt::VBlank.mask(),
+        0
+    );
 
-        write!(
-            fmt,
-            "shade 3: {:?}, shade 2: {:?}, shade 1: {:?}, shade 0: {:?}",
-            shade_3, shade_2, shade_1, shade_0
-        )
+    memory.write_byte(REGISTER_LCD_CONTROL, 0x91);
+    for _ in 0..70224 / 4 {
+        memory.tick(4);
     }
+    assert_ne!(
+        memory.read_byte(REGISTER_INTERRUPT_FLAG) & Interrupt::VBlank.mask(),
+        0
+    );
 }
 
-struct LcdControl(u8);
-impl core::fmt::Debug for LcdControl {
-    fn fmt(&self, fmt: &mut core::fmt::Formatter) -> core::fmt::Result {
-        write!(fmt, "LCD control {:08b}", self.0)
+#[test]
+fn io_register_masks() {
+    let mut memory = crate::opcodes::test_memory(&[]);
+
+    memory.write_byte(REGISTER_INTERRUPT_FLAG, 0xFF);
+    assert_eq!(memory.read_byte(REGISTER_INTERRUPT_FLAG), 0xFF);
+    memory.write_byte(REGISTER_INTERRUPT_FLAG, 0x01);
+    assert_eq!(memory.read_byte(REGISTER_INTERRUPT_FLAG), 0xE1);
+
+    // Unmapped addresses are open bus
+    memory.write_byte(0xFF03, 0x12);
+    assert_eq!(memory.read_byte(0xFF03), 0xFF);
+    assert_eq!(memory.read_byte(0xFF7F), 0xFF);
+
+    // Write-only and read-only registers
+    memory.write_byte(0xFF13, 0x12);
+    assert_eq!(memory.read_byte(0xFF13), 0xFF);
+    memory.write_byte(REGISTER_SCANLINE_Y, 0x12);
+    assert_eq!(memory.read_byte(REGISTER_SCANLINE_Y), 0x00);
+
+    // STAT reads mode 0 while the LCD is off, and the mode bits can not be written
+    memory.write_byte(REGISTER_LCD_STATUS, 0xFF);
+    assert_eq!(memory.read_byte(REGISTER_LCD_STATUS), 0xFC);
+    memory.write_byte(REGISTER_LCD_CONTROL, 0x80);
+    assert_eq!(memory.read_byte(REGISTER_LCD_STATUS), 0xFE);
+
+    // Turning the LCD off resets LY, which stays 0 until the LCD is turned on again
+    for _ in 0..1000 {
+        memory.tick(4);
     }
+    assert_ne!(memory.read_byte(REGISTER_SCANLINE_Y), 0);
+    memory.write_byte(REGISTER_LCD_CONTROL, 0x00);
+    assert_eq!(memory.read_byte(REGISTER_SCANLINE_Y), 0);
+    for _ in 0..1000 {
+        memory.tick(4);
+    }
+    assert_eq!(memory.read_byte(REGISTER_SCANLINE_Y), 0);
+
+    memory.joypad = 0b0001_0010;
+    memory.write_byte(REGISTER_JOYPAD, 0x20);
+    assert_eq!(memory.read_byte(REGISTER_JOYPAD), 0xED, "left is pressed");
+    memory.write_byte(REGISTER_JOYPAD, 0x10);
+    assert_eq!(memory.read_byte(REGISTER_JOYPAD), 0xDE, "A is pressed");
+    memory.write_byte(REGISTER_JOYPAD, 0x30);
+    assert_eq!(memory.read_byte(REGISTER_JOYPAD), 0xFF);
 }
 
-const fn bytes_to_word(high: u8, low: u8) -> u16 {
-    (low as u16) << 8 | (high as u16)
+#[test]
+fn boot_rom_register() {
+    let mut rom = crate::cartridge::test_rom(&[]);
+    rom.resize(0x10000, 0);
+    rom[0x147] = 0x01;
+    rom[0x148] = 0x01;
+    rom[0x14D] = crate::cartridge::header_checksum_of(&rom);
+    rom[0x8000] = 0xAB;
+    let mut memory = Memory::new(
+        Cartridge::new(rom).unwrap(),
+        Box::new(crate::opcodes::TestVideo),
+    );
+
+    assert_eq!(memory.read_byte(0x0000), BIOS[0]);
+    assert_eq!(memory.read_byte(REGISTER_DISABLE_BIOS), 0xFE);
+    // The mapper sees writes while the boot ROM is mapped
+    memory.write_byte(0x2000, 0x02);
+    assert_eq!(memory.read_byte(0x4000), 0xAB);
+
+    memory.write_byte(REGISTER_DISABLE_BIOS, 0x00);
+    assert!(memory.boot_rom_mapped());
+    memory.write_byte(REGISTER_DISABLE_BIOS, 0x01);
+    assert!(!memory.boot_rom_mapped());
+    assert_eq!(memory.read_byte(0x0000), 0x00);
+    assert_eq!(memory.read_byte(REGISTER_DISABLE_BIOS), 0xFF);
+    memory.write_byte(REGISTER_DISABLE_BIOS, 0x00);
+    assert_eq!(memory.read_byte(REGISTER_DISABLE_BIOS), 0xFF);
 }
 
-const fn word_to_bytes(word: u16) -> (u8, u8) {
-    let high = (word >> 8) as u8;
-    let low = word as u8;
-    (low, high)
+#[test]
+fn restricted_regions() {
+    let mut memory = crate::opcodes::test_memory(&[]);
+
+    memory.write_byte(0xE123, 0x45);
+    assert_eq!(memory.read_byte(0xC123), 0x45);
+    memory.write_byte(0xDDFF, 0x67);
+    assert_eq!(memory.read_byte(0xFDFF), 0x67);
+
+    memory.write_byte(0xFEB0, 0x12);
+    assert_eq!(memory.read_byte(0xFEB0), 0x00);
+    memory.set_model(Model::Cgb);
+    assert_eq!(memory.read_byte(0xFEB0), 0xBB);
+
+    Bus::write_byte(&mut memory, 0xFE00, 0x12);
+    Bus::write_byte(&mut memory, 0x8000, 0x34);
+    memory.write_byte(REGISTER_LCD_CONTROL, 0x91);
+    // Mode 2, OAM is blocked
+    assert_eq!(Bus::read_byte(&mut memory, 0xFE00), 0xFF);
+    assert_eq!(Bus::read_byte(&mut memory, 0x8000), 0x34);
+    assert_eq!(Bus::read_byte(&mut memory, 0xFEB0), 0xFF);
+    // Mode 3, VRAM is blocked as well
+    memory.tick(80);
+    Bus::write_byte(&mut memory, 0x8000, 0x56);
+    assert_eq!(Bus::read_byte(&mut memory, 0x8000), 0xFF);
+    // Horizontal blank
+    memory.tick(172);
+    assert_eq!(Bus::read_byte(&mut memory, 0xFE00), 0x12);
+    assert_eq!(Bus::read_byte(&mut memory, 0x8000), 0x34);
+}
+
+#[test]
+fn oam_dma() {
+    let mut memory = crate::opcodes::test_memory(&[]);
+    for index in 0..DMA_LENGTH {
+        Memory::write_byte(&mut memory, 0xC000 + index, index as u8);
+    }
+    Bus::write_byte(&mut memory, 0xFF80, 0x12);
+
+    // 0xE0 reads from the echo RAM, so from 0xC000
+    Bus::write_byte(&mut memory, REGISTER_DMA, 0xE0);
+    memory.tick(4);
+    assert_eq!(
+        Bus::read_byte(&mut memory, 0xC000),
+        0xFF,
+        "the bus is in use"
+    );
+    assert_eq!(
+        Bus::read_byte(&mut memory, 0xFF80),
+        0x12,
+        "HRAM can be accessed"
+    );
+    memory.tick(4 * (DMA_LENGTH - 1));
+    assert_eq!(memory.read_byte(0xFE9E), 0x9E);
+    assert_eq!(
+        memory.read_byte(0xFE9F),
+        0x00,
+        "the last byte is not copied yet"
+    );
+
+    memory.tick(4);
+    assert_eq!(memory.read_byte(0xFE9F), 0x9F);
+    assert_eq!(Bus::read_byte(&mut memory, 0xC001), 0x01);
 }
@@ -1,115 +1,186 @@
-use crate::{Cpu, Memory};
+use crate::{Bus, Cpu};
+
+macro_rules! impl_inc_r {
+    ($($code:tt $name:ident $field:ident $set_field:ident),* $(,)?) => {
+        $(
+            pub fn $name(_: &mut dyn Bus, cpu: &mut Cpu) {
+                cpu.increment_program_counter();
+
+                let val = cpu.inc(cpu.$field());
+                cpu.$set_field(val);
+            }
+        )*
+    }
+}
 
-pub fn add_a_e(_: &mut Memory, cpu: &mut Cpu) {
-    // ADD A,E 1 4 Z 0 H C
-    cpu.clock_cycles(4);
-    cpu.increment_program_counter();
+impl_inc_r! {
+    0x04 inc_b b set_b,
+    0x0C inc_c c set_c,
+    0x14 inc_d d set_d,
+    0x1C inc_e e set_e,
+    0x24 inc_h h set_h,
+    0x2C inc_l l set_l,
+    0x3C inc_a a set_a,
+}
 
-    let val = cpu.e();
+macro_rules! impl_inc_rr {
+    ($($code:tt $name:ident $field:ident $set_field:ident),* $(,)?) => {
+        $(
+            pub fn $name(_: &mut dyn Bus, cpu: &mut Cpu) {
+                cpu.increment_program_counter();
 
-    cpu.add_a(val, false);
+                let val = cpu.$field().wrapping_add(1);
+                cpu.$set_field(val);
+            }
+        )*
+    }
 }
 
-pub fn adc_a_d8(memory: &mut Memory, cpu: &mut Cpu) {
-    // ADC A,d8 2 8 Z 0 H C
-    cpu.clock_cycles(8);
-    cpu.increment_program_counter();
+impl_inc_rr! {
+    0x03 inc_bc bc set_bc,
+    0x13 inc_de de set_de,
+    0x23 inc_hl hl set_hl,
+    0x33 inc_sp stack_pointer set_sp,
+}
 
-    let val = memory.read_byte(cpu.program_counter());
-    cpu.increment_program_counter();
+macro_rules! impl_add_a_r {
+    ($($code:tt $name:ident $field:ident $with_carry:expr),* $(,)?) => {
+        $(
+            pub fn $name(_: &mut dyn Bus, cpu: &mut Cpu) {
+                cpu.increment_program_counter();
 
-    cpu.add_a(val, true);
+                let val = cpu.$field();
+                cpu.add_a(val, $with_carry);
+            }
+        )*
+    }
 }
 
-pub fn inc_bc(_: &mut Memory, cpu: &mut Cpu) {
-    // INC BC 1 8 - - - -
-    cpu.clock_cycles(8);
-    cpu.increment_program_counter();
-
-    cpu.set_bc(cpu.bc().wrapping_add(1));
+impl_add_a_r! {
+    0x80 add_a_b b false,
+    0x81 add_a_c c false,
+    0x82 add_a_d d false,
+    0x83 add_a_e e false,
+    0x84 add_a_h h false,
+    0x85 add_a_l l false,
+    0x87 add_a_a a false,
+    0x88 adc_a_b b true,
+    0x89 adc_a_c c true,
+    0x8A adc_a_d d true,
+    0x8B adc_a_e e true,
+    0x8C adc_a_h h true,
+    0x8D adc_a_l l true,
+    0x8F adc_a_a a true,
 }
 
-pub fn inc_c(_: &mut Memory, cpu: &mut Cpu) {
-    // INC C 1 4 Z 0 H -
-    cpu.clock_cycles(4);
-    cpu.increment_program_counter();
+macro_rules! impl_add_hl_rr {
+    ($($code:tt $name:ident $field:ident),* $(,)?) => {
+        $(
+            pub fn $name(_: &mut dyn Bus, cpu: &mut Cpu) {
+                cpu.increment_program_counter();
 
-    let val = cpu.c();
-    cpu.flags.clear_subtract();
-    if val == core::u8::MAX {
-        cpu.flags.set_zero();
-        cpu.flags.clear_half_carry();
-        cpu.set_c(0);
-    } else {
-        cpu.flags.update_half_carry(val == 0b1111);
-        cpu.set_c(val + 1);
+                let val = cpu.$field();
+                cpu.add_hl(val);
+            }
+        )*
     }
 }
 
-pub fn adc_a_b(_: &mut Memory, cpu: &mut Cpu) {
-    // ADC A, B 1 4 Z 0 H C
-    cpu.clock_cycles(4);
-    cpu.increment_program_counter();
-
-    let val = cpu.b();
-    cpu.add_a(val, true);
+impl_add_hl_rr! {
+    0x09 add_hl_bc bc,
+    0x19 add_hl_de de,
+    0x29 add_hl_hl hl,
+    0x39 add_hl_sp stack_pointer,
 }
 
-pub fn adc_a_c(_: &mut Memory, cpu: &mut Cpu) {
-    // ADC A, C 1 4 Z 0 H C
-    cpu.clock_cycles(4);
+pub fn inc_ptr_hl(memory: &mut dyn Bus, cpu: &mut Cpu) {
     cpu.increment_program_counter();
 
-    let val = cpu.c();
-    cpu.add_a(val, true);
+    let address = cpu.hl();
+    let val = cpu.inc(memory.read_byte(address));
+    memory.write_byte(address, val);
 }
 
-pub fn add_a_ptr_hl(memory: &mut Memory, cpu: &mut Cpu) {
-    // 0x86 ADD A, (HL) 1 8 Z 0 H C
-    cpu.clock_cycles(4);
+pub fn add_a_ptr_hl(memory: &mut dyn Bus, cpu: &mut Cpu) {
     cpu.increment_program_counter();
 
-    let addr = cpu.hl();
-    let val = memory.read_byte(addr);
+    let val = memory.read_byte(cpu.hl());
     cpu.add_a(val, false);
 }
 
-pub fn inc_hl(_: &mut Memory, cpu: &mut Cpu) {
-    // 0x23 INC HL 1 8 - - - -
+pub fn adc_a_ptr_hl(memory: &mut dyn Bus, cpu: &mut Cpu) {
     cpu.increment_program_counter();
-    cpu.clock_cycles(8);
-    cpu.set_hl(cpu.hl().wrapping_add(1));
+
+    let val = memory.read_byte(cpu.hl());
+    cpu.add_a(val, true);
 }
 
-pub fn inc_de(_: &mut Memory, cpu: &mut Cpu) {
-    // 0x13 DE HL 1 8 - - - -
+pub fn add_a_d8(memory: &mut dyn Bus, cpu: &mut Cpu) {
+    cpu.increment_program_counter();
+
+    let val = memory.read_byte(cpu.program_counter());
     cpu.increment_program_counter();
-    cpu.clock_cycles(8);
-    cpu.set_de(cpu.de().wrapping_add(1));
+
+    cpu.add_a(val, false);
 }
 
-pub fn inc_b(_: &mut Memory, cpu: &mut Cpu) {
-    // 0x04 INC B 1 4 Z 0 H -
+pub fn adc_a_d8(memory: &mut dyn Bus, cpu: &mut Cpu) {
     cpu.increment_program_counter();
-    cpu.clock_cycles(4);
 
-    let b = cpu.b();
-    cpu.flags.update_zero(b == 0xff);
-    cpu.flags.clear_subtract();
-    cpu.flags.update_half_carry(b == 0x80);
+    let val = memory.read_byte(cpu.program_counter());
+    cpu.increment_program_counter();
 
-    cpu.set_b(b.wrapping_add(1));
+    cpu.add_a(val, true);
 }
 
-pub fn inc_h(_: &mut Memory, cpu: &mut Cpu) {
-    // 0x24 INC H 1 4 Z 0 H -
+pub fn add_sp_r8(memory: &mut dyn Bus, cpu: &mut Cpu) {
+    cpu.increment_program_counter();
+
+    let offset = memory.read_byte(cpu.program_counter()) as i8;
     cpu.increment_program_counter();
-    cpu.clock_cycles(4);
 
-    let h = cpu.h();
-    cpu.flags.update_zero(h == 0xff);
-    cpu.flags.clear_subtract();
-    cpu.flags.update_half_carry(h == 0x80);
+    let val = cpu.sp_plus_offset(offset);
+    cpu.set_sp(val);
+}
 
-    cpu.set_h(h.wrapping_add(1));
+#[test]
+fn add_family() {
+    let mut memory = super::test_memory(&[0x80, 0x8F, 0x3C, 0x34, 0x09, 0xE8, 0x02]);
+    let mut cpu = super::test_cpu();
+
+    // 0x3A + 0xC6 = 0x100
+    cpu.set_a(0x3A);
+    cpu.set_b(0xC6);
+    super::execute(&mut memory, &mut cpu);
+    assert_eq!(cpu.a(), 0);
+    assert!(cpu.flags.z() && cpu.flags.h() && cpu.flags.c() && !cpu.flags.n());
+
+    // ADC A, A with the carry from the previous addition
+    super::execute(&mut memory, &mut cpu);
+    assert_eq!(cpu.a(), 1);
+    assert!(!cpu.flags.z() && !cpu.flags.h() && !cpu.flags.c());
+
+    cpu.set_a(0x0F);
+    cpu.flags.set_carry();
+    super::execute(&mut memory, &mut cpu);
+    assert_eq!(cpu.a(), 0x10);
+    assert!(cpu.flags.h() && cpu.flags.c());
+
+    cpu.set_hl(0xC000);
+    memory.write_byte(0xC000, 0xFF);
+    super::execute(&mut memory, &mut cpu);
+    assert_eq!(memory.read_byte(0xC000), 0);
+    assert!(cpu.flags.z() && cpu.flags.h());
+
+    cpu.set_bc(0x0800);
+    cpu.set_hl(0x0800);
+    super::execute(&mut memory, &mut cpu);
+    assert_eq!(cpu.hl(), 0x1000);
+    assert!(cpu.flags.z() && cpu.flags.h() && !cpu.flags.c());
+
+    cpu.set_sp(0xFFFE);
+    super::execute(&mut memory, &mut cpu);
+    assert_eq!(cpu.stack_pointer(), 0x0000);
+    assert!(!cpu.flags.z() && cpu.flags.h() && cpu.flags.c());
+    assert_eq!(cpu.elapsed_cycles(), 4 + 4 + 4 + 12 + 8 + 16);
 }
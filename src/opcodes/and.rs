@@ -0,0 +1,58 @@
+use crate::{Bus, Cpu};
+
+macro_rules! impl_and_r {
+    ($($code:tt $name:ident $field:ident),* $(,)?) => {
+        $(
+            pub fn $name(_: &mut dyn Bus, cpu: &mut Cpu) {
+                cpu.increment_program_counter();
+
+                let val = cpu.$field();
+                cpu.and_a(val);
+            }
+        )*
+    }
+}
+
+impl_and_r! {
+    0xA0 and_b b,
+    0xA1 and_c c,
+    0xA2 and_d d,
+    0xA3 and_e e,
+    0xA4 and_h h,
+    0xA5 and_l l,
+    0xA7 and_a a,
+}
+
+pub fn and_ptr_hl(memory: &mut dyn Bus, cpu: &mut Cpu) {
+    cpu.increment_program_counter();
+
+    let val = memory.read_byte(cpu.hl());
+    cpu.and_a(val);
+}
+
+pub fn and_d8(memory: &mut dyn Bus, cpu: &mut Cpu) {
+    cpu.increment_program_counter();
+
+    let val = memory.read_byte(cpu.program_counter());
+    cpu.increment_program_counter();
+
+    cpu.and_a(val);
+}
+
+#[test]
+fn and_family() {
+    let mut memory = super::test_memory(&[0xA0, 0xE6, 0x00]);
+    let mut cpu = super::test_cpu();
+
+    cpu.set_a(0b1100_1010);
+    cpu.set_b(0b1010_0110);
+    cpu.flags.set_carry();
+    super::execute(&mut memory, &mut cpu);
+    assert_eq!(cpu.a(), 0b1000_0010);
+    assert!(!cpu.flags.z() && !cpu.flags.n() && cpu.flags.h() && !cpu.flags.c());
+
+    super::execute(&mut memory, &mut cpu);
+    assert_eq!(cpu.a(), 0);
+    assert!(cpu.flags.z() && cpu.flags.h());
+    assert_eq!(cpu.elapsed_cycles(), 4 + 8);
+}
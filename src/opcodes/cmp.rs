@@ -1,8 +1,29 @@
-use crate::{Cpu, Memory};
+use crate::{Bus, Cpu};
 
-pub fn cp_d8(memory: &mut Memory, cpu: &mut Cpu) {
-    // CP d8 2 8 Z 1 H C
-    cpu.clock_cycles(8);
+macro_rules! impl_cp_r {
+    ($($code:tt $name:ident $field:ident),* $(,)?) => {
+        $(
+            pub fn $name(_: &mut dyn Bus, cpu: &mut Cpu) {
+                cpu.increment_program_counter();
+
+                let val = cpu.$field();
+                cpu.cmp_a_with(val);
+            }
+        )*
+    }
+}
+
+impl_cp_r! {
+    0xB8 cp_b b,
+    0xB9 cp_c c,
+    0xBA cp_d d,
+    0xBB cp_e e,
+    0xBC cp_h h,
+    0xBD cp_l l,
+    0xBF cp_a a,
+}
+
+pub fn cp_d8(memory: &mut dyn Bus, cpu: &mut Cpu) {
     cpu.increment_program_counter();
     let cmp_val = memory.read_byte(cpu.program_counter());
     cpu.increment_program_counter();
@@ -10,9 +31,7 @@ pub fn cp_d8(memory: &mut Memory, cpu: &mut Cpu) {
     cpu.cmp_a_with(cmp_val);
 }
 
-pub fn cp_ptr_hl(memory: &mut Memory, cpu: &mut Cpu) {
-    // 0xBE CP (HL) 1 8 Z 1 H C
-    cpu.clock_cycles(8);
+pub fn cp_ptr_hl(memory: &mut dyn Bus, cpu: &mut Cpu) {
     cpu.increment_program_counter();
 
     let val = memory.read_byte(cpu.hl());
@@ -20,3 +39,21 @@ pub fn cp_ptr_hl(memory: &mut Memory, cpu: &mut Cpu) {
     cpu.cmp_a_with(val);
 }
 
+#[test]
+fn cp_family() {
+    let mut memory = super::test_memory(&[0xBF, 0xB8, 0xFE, 0x3C]);
+    let mut cpu = super::test_cpu();
+
+    cpu.set_a(0x3C);
+    super::execute(&mut memory, &mut cpu);
+    assert!(cpu.flags.z() && cpu.flags.n() && !cpu.flags.h() && !cpu.flags.c());
+
+    cpu.set_b(0x40);
+    super::execute(&mut memory, &mut cpu);
+    assert_eq!(cpu.a(), 0x3C, "CP does not modify A");
+    assert!(!cpu.flags.z() && !cpu.flags.h() && cpu.flags.c());
+
+    super::execute(&mut memory, &mut cpu);
+    assert!(cpu.flags.z() && !cpu.flags.c());
+    assert_eq!(cpu.elapsed_cycles(), 4 + 4 + 8);
+}
@@ -0,0 +1,130 @@
+use super::{Instruction, Operand, CB_INSTRUCTIONS, INSTRUCTIONS};
+use crate::Memory;
+use core::fmt;
+
+/// A decoded instruction together with the bytes it was decoded from. The `Display`
+/// implementation prints the instruction with its immediate operands resolved, e.g.
+/// `JR NZ, $0213` or `LDH ($FF44), A`.
+#[derive(Debug, Clone, Copy)]
+pub struct Disassembly {
+    pub address: u16,
+    pub instruction: &'static Instruction,
+    bytes: [u8; 3],
+}
+
+impl Disassembly {
+    /// The raw bytes of the instruction, including the 0xCB prefix.
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes[..self.instruction.length as usize]
+    }
+
+    /// The value of the 8 or 16-bit immediate operand, if the instruction has one.
+    pub fn immediate(&self) -> Option<u16> {
+        let start = if self.instruction.prefixed { 2 } else { 1 };
+        let immediate = &self.bytes[start..self.instruction.length as usize];
+        match *immediate {
+            [low] => Some(low as u16),
+            [low, high] => Some((high as u16) << 8 | low as u16),
+            _ => None,
+        }
+    }
+
+    fn fmt_operand(&self, fmt: &mut fmt::Formatter, operand: Operand) -> fmt::Result {
+        let immediate = self.immediate().unwrap_or(0);
+        match operand {
+            Operand::Data8 => write!(fmt, "${:02X}", immediate),
+            Operand::Data16 | Operand::Address16 => write!(fmt, "${:04X}", immediate),
+            Operand::PtrAddress8 => write!(fmt, "(${:04X})", 0xFF00 | immediate),
+            Operand::PtrAddress16 => write!(fmt, "(${:04X})", immediate),
+            Operand::PtrC => write!(fmt, "($FF00+C)"),
+            Operand::Relative8 => {
+                let next = self.address.wrapping_add(self.instruction.length as u16);
+                let target = next.wrapping_add(immediate as u8 as i8 as u16);
+                write!(fmt, "${:04X}", target)
+            }
+            Operand::Signed8 => write!(fmt, "{}", immediate as u8 as i8),
+            Operand::SPPlusSigned8 => write!(fmt, "SP{:+}", immediate as u8 as i8),
+            operand => write!(fmt, "{}", operand),
+        }
+    }
+}
+
+impl fmt::Display for Disassembly {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{}", self.instruction.mnemonic)?;
+        for (index, operand) in self.instruction.operands.iter().enumerate() {
+            fmt.write_str(if index == 0 { " " } else { ", " })?;
+            self.fmt_operand(fmt, *operand)?;
+        }
+        Ok(())
+    }
+}
+
+/// Decodes the instruction at `address`, following the 0xCB prefix. Returns the instruction and
+/// its length in bytes, which is the offset of the next instruction.
+pub fn disassemble(memory: &Memory, address: u16) -> (Disassembly, u16) {
+    disassemble_with(|address| memory.read_byte(address), address)
+}
+
+/// Like `disassemble`, but reads the bytes through `read_byte`. This allows disassembling
+/// memory that is not currently mapped, like the other banks of a cartridge.
+pub fn disassemble_with(read_byte: impl Fn(u16) -> u8, address: u16) -> (Disassembly, u16) {
+    let opcode = read_byte(address);
+    let instruction = if opcode == 0xCB {
+        &CB_INSTRUCTIONS[read_byte(address.wrapping_add(1)) as usize]
+    } else {
+        &INSTRUCTIONS[opcode as usize]
+    };
+
+    let mut bytes = [0u8; 3];
+    for (offset, byte) in bytes
+        .iter_mut()
+        .enumerate()
+        .take(instruction.length as usize)
+    {
+        *byte = read_byte(address.wrapping_add(offset as u16));
+    }
+
+    let disassembly = Disassembly {
+        address,
+        instruction,
+        bytes,
+    };
+    (disassembly, instruction.length as u16)
+}
+
+#[test]
+fn disassemble_resolves_operands() {
+    let memory = super::test_memory(&[
+        0x20, 0xFB, // JR NZ, -5
+        0xE0, 0x44, // LDH ($FF44), A
+        0xCB, 0x7C, // BIT 7, H
+        0x31, 0xFE, 0xFF, // LD SP, $FFFE
+        0xF8, 0xFE, // LD HL, SP-2
+        0xE8, 0x05, // ADD SP, 5
+        0xFA, 0x34, 0x12, // LD A, ($1234)
+        0xD3, // illegal
+    ]);
+
+    let mut address = 0x100;
+    let mut lines = Vec::new();
+    while address < 0x111 {
+        let (disassembly, length) = disassemble(&memory, address);
+        lines.push(format!("{:02X?} {}", disassembly.bytes(), disassembly));
+        address += length;
+    }
+
+    assert_eq!(
+        lines,
+        [
+            "[20, FB] JR NZ, $00FD",
+            "[E0, 44] LDH ($FF44), A",
+            "[CB, 7C] BIT 7, H",
+            "[31, FE, FF] LD SP, $FFFE",
+            "[F8, FE] LD HL, SP-2",
+            "[E8, 05] ADD SP, 5",
+            "[FA, 34, 12] LD A, ($1234)",
+            "[D3] ILLEGAL",
+        ]
+    );
+}
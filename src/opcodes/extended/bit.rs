@@ -1,22 +1,35 @@
-use crate::{Cpu, Memory};
+use crate::{Bus, Cpu};
 
-pub fn bit_7_h(_memory: &mut Memory, cpu: &mut Cpu) {
-    // 0x7C BIT 7, H 1 4 z 0 1 -
-    cpu.increment_program_counter();
-    cpu.clock_cycles(4);
+macro_rules! impl_bit {
+    ($($code:tt $name:ident $bit:tt $field:tt),* $(,)?) => {
+        $(
+            pub fn $name(_: &mut dyn Bus, cpu: &mut Cpu) {
+                cpu.increment_program_counter();
 
-    cpu.flags.update_zero(bit_cleared(cpu.h(), 7));
-    cpu.flags.clear_subtract();
-    cpu.flags.set_half_carry();
+                bit(cpu, cpu.$field(), $bit);
+            }
+        )*
+    }
+}
+
+macro_rules! impl_bit_ptr_hl {
+    ($($code:tt $name:ident $bit:tt),* $(,)?) => {
+        $(
+            pub fn $name(memory: &mut dyn Bus, cpu: &mut Cpu) {
+                cpu.increment_program_counter();
+
+                let val = memory.read_byte(cpu.hl());
+                bit(cpu, val, $bit);
+            }
+        )*
+    }
 }
 
 macro_rules! impl_res {
     ($($code:tt $name:ident $bit:tt $field:tt $set_field:tt),* $(,)?) => {
         $(
-            pub fn $name(_: &mut Memory, cpu: &mut Cpu) {
-                // $code RES $bit $field 1 4 - - - -
+            pub fn $name(_: &mut dyn Bus, cpu: &mut Cpu) {
                 cpu.increment_program_counter();
-                cpu.clock_cycles(4);
 
                 let mut val = cpu.$field();
                 val &= !(1 << $bit);
@@ -26,8 +39,262 @@ macro_rules! impl_res {
     }
 }
 
+macro_rules! impl_res_ptr_hl {
+    ($($code:tt $name:ident $bit:tt),* $(,)?) => {
+        $(
+            pub fn $name(memory: &mut dyn Bus, cpu: &mut Cpu) {
+                cpu.increment_program_counter();
+
+                let address = cpu.hl();
+                let val = memory.read_byte(address) & !(1 << $bit);
+                memory.write_byte(address, val);
+            }
+        )*
+    }
+}
+
+macro_rules! impl_set {
+    ($($code:tt $name:ident $bit:tt $field:tt $set_field:tt),* $(,)?) => {
+        $(
+            pub fn $name(_: &mut dyn Bus, cpu: &mut Cpu) {
+                cpu.increment_program_counter();
+
+                let mut val = cpu.$field();
+                val |= 1 << $bit;
+                cpu.$set_field(val);
+            }
+        )*
+    }
+}
+
+macro_rules! impl_set_ptr_hl {
+    ($($code:tt $name:ident $bit:tt),* $(,)?) => {
+        $(
+            pub fn $name(memory: &mut dyn Bus, cpu: &mut Cpu) {
+                cpu.increment_program_counter();
+
+                let address = cpu.hl();
+                let val = memory.read_byte(address) | (1 << $bit);
+                memory.write_byte(address, val);
+            }
+        )*
+    }
+}
+
+impl_bit! {
+    0x40 bit_0_b 0 b,
+    0x41 bit_0_c 0 c,
+    0x42 bit_0_d 0 d,
+    0x43 bit_0_e 0 e,
+    0x44 bit_0_h 0 h,
+    0x45 bit_0_l 0 l,
+    0x47 bit_0_a 0 a,
+    0x48 bit_1_b 1 b,
+    0x49 bit_1_c 1 c,
+    0x4A bit_1_d 1 d,
+    0x4B bit_1_e 1 e,
+    0x4C bit_1_h 1 h,
+    0x4D bit_1_l 1 l,
+    0x4F bit_1_a 1 a,
+    0x50 bit_2_b 2 b,
+    0x51 bit_2_c 2 c,
+    0x52 bit_2_d 2 d,
+    0x53 bit_2_e 2 e,
+    0x54 bit_2_h 2 h,
+    0x55 bit_2_l 2 l,
+    0x57 bit_2_a 2 a,
+    0x58 bit_3_b 3 b,
+    0x59 bit_3_c 3 c,
+    0x5A bit_3_d 3 d,
+    0x5B bit_3_e 3 e,
+    0x5C bit_3_h 3 h,
+    0x5D bit_3_l 3 l,
+    0x5F bit_3_a 3 a,
+    0x60 bit_4_b 4 b,
+    0x61 bit_4_c 4 c,
+    0x62 bit_4_d 4 d,
+    0x63 bit_4_e 4 e,
+    0x64 bit_4_h 4 h,
+    0x65 bit_4_l 4 l,
+    0x67 bit_4_a 4 a,
+    0x68 bit_5_b 5 b,
+    0x69 bit_5_c 5 c,
+    0x6A bit_5_d 5 d,
+    0x6B bit_5_e 5 e,
+    0x6C bit_5_h 5 h,
+    0x6D bit_5_l 5 l,
+    0x6F bit_5_a 5 a,
+    0x70 bit_6_b 6 b,
+    0x71 bit_6_c 6 c,
+    0x72 bit_6_d 6 d,
+    0x73 bit_6_e 6 e,
+    0x74 bit_6_h 6 h,
+    0x75 bit_6_l 6 l,
+    0x77 bit_6_a 6 a,
+    0x78 bit_7_b 7 b,
+    0x79 bit_7_c 7 c,
+    0x7A bit_7_d 7 d,
+    0x7B bit_7_e 7 e,
+    0x7C bit_7_h 7 h,
+    0x7D bit_7_l 7 l,
+    0x7F bit_7_a 7 a,
+}
+
+impl_bit_ptr_hl! {
+    0x46 bit_0_ptr_hl 0,
+    0x4E bit_1_ptr_hl 1,
+    0x56 bit_2_ptr_hl 2,
+    0x5E bit_3_ptr_hl 3,
+    0x66 bit_4_ptr_hl 4,
+    0x6E bit_5_ptr_hl 5,
+    0x76 bit_6_ptr_hl 6,
+    0x7E bit_7_ptr_hl 7,
+}
+
 impl_res! {
+    0x80 res_0_b 0 b set_b,
+    0x81 res_0_c 0 c set_c,
+    0x82 res_0_d 0 d set_d,
+    0x83 res_0_e 0 e set_e,
+    0x84 res_0_h 0 h set_h,
+    0x85 res_0_l 0 l set_l,
     0x87 res_0_a 0 a set_a,
+    0x88 res_1_b 1 b set_b,
+    0x89 res_1_c 1 c set_c,
+    0x8A res_1_d 1 d set_d,
+    0x8B res_1_e 1 e set_e,
+    0x8C res_1_h 1 h set_h,
+    0x8D res_1_l 1 l set_l,
+    0x8F res_1_a 1 a set_a,
+    0x90 res_2_b 2 b set_b,
+    0x91 res_2_c 2 c set_c,
+    0x92 res_2_d 2 d set_d,
+    0x93 res_2_e 2 e set_e,
+    0x94 res_2_h 2 h set_h,
+    0x95 res_2_l 2 l set_l,
+    0x97 res_2_a 2 a set_a,
+    0x98 res_3_b 3 b set_b,
+    0x99 res_3_c 3 c set_c,
+    0x9A res_3_d 3 d set_d,
+    0x9B res_3_e 3 e set_e,
+    0x9C res_3_h 3 h set_h,
+    0x9D res_3_l 3 l set_l,
+    0x9F res_3_a 3 a set_a,
+    0xA0 res_4_b 4 b set_b,
+    0xA1 res_4_c 4 c set_c,
+    0xA2 res_4_d 4 d set_d,
+    0xA3 res_4_e 4 e set_e,
+    0xA4 res_4_h 4 h set_h,
+    0xA5 res_4_l 4 l set_l,
+    0xA7 res_4_a 4 a set_a,
+    0xA8 res_5_b 5 b set_b,
+    0xA9 res_5_c 5 c set_c,
+    0xAA res_5_d 5 d set_d,
+    0xAB res_5_e 5 e set_e,
+    0xAC res_5_h 5 h set_h,
+    0xAD res_5_l 5 l set_l,
+    0xAF res_5_a 5 a set_a,
+    0xB0 res_6_b 6 b set_b,
+    0xB1 res_6_c 6 c set_c,
+    0xB2 res_6_d 6 d set_d,
+    0xB3 res_6_e 6 e set_e,
+    0xB4 res_6_h 6 h set_h,
+    0xB5 res_6_l 6 l set_l,
+    0xB7 res_6_a 6 a set_a,
+    0xB8 res_7_b 7 b set_b,
+    0xB9 res_7_c 7 c set_c,
+    0xBA res_7_d 7 d set_d,
+    0xBB res_7_e 7 e set_e,
+    0xBC res_7_h 7 h set_h,
+    0xBD res_7_l 7 l set_l,
+    0xBF res_7_a 7 a set_a,
+}
+
+impl_res_ptr_hl! {
+    0x86 res_0_ptr_hl 0,
+    0x8E res_1_ptr_hl 1,
+    0x96 res_2_ptr_hl 2,
+    0x9E res_3_ptr_hl 3,
+    0xA6 res_4_ptr_hl 4,
+    0xAE res_5_ptr_hl 5,
+    0xB6 res_6_ptr_hl 6,
+    0xBE res_7_ptr_hl 7,
+}
+
+impl_set! {
+    0xC0 set_0_b 0 b set_b,
+    0xC1 set_0_c 0 c set_c,
+    0xC2 set_0_d 0 d set_d,
+    0xC3 set_0_e 0 e set_e,
+    0xC4 set_0_h 0 h set_h,
+    0xC5 set_0_l 0 l set_l,
+    0xC7 set_0_a 0 a set_a,
+    0xC8 set_1_b 1 b set_b,
+    0xC9 set_1_c 1 c set_c,
+    0xCA set_1_d 1 d set_d,
+    0xCB set_1_e 1 e set_e,
+    0xCC set_1_h 1 h set_h,
+    0xCD set_1_l 1 l set_l,
+    0xCF set_1_a 1 a set_a,
+    0xD0 set_2_b 2 b set_b,
+    0xD1 set_2_c 2 c set_c,
+    0xD2 set_2_d 2 d set_d,
+    0xD3 set_2_e 2 e set_e,
+    0xD4 set_2_h 2 h set_h,
+    0xD5 set_2_l 2 l set_l,
+    0xD7 set_2_a 2 a set_a,
+    0xD8 set_3_b 3 b set_b,
+    0xD9 set_3_c 3 c set_c,
+    0xDA set_3_d 3 d set_d,
+    0xDB set_3_e 3 e set_e,
+    0xDC set_3_h 3 h set_h,
+    0xDD set_3_l 3 l set_l,
+    0xDF set_3_a 3 a set_a,
+    0xE0 set_4_b 4 b set_b,
+    0xE1 set_4_c 4 c set_c,
+    0xE2 set_4_d 4 d set_d,
+    0xE3 set_4_e 4 e set_e,
+    0xE4 set_4_h 4 h set_h,
+    0xE5 set_4_l 4 l set_l,
+    0xE7 set_4_a 4 a set_a,
+    0xE8 set_5_b 5 b set_b,
+    0xE9 set_5_c 5 c set_c,
+    0xEA set_5_d 5 d set_d,
+    0xEB set_5_e 5 e set_e,
+    0xEC set_5_h 5 h set_h,
+    0xED set_5_l 5 l set_l,
+    0xEF set_5_a 5 a set_a,
+    0xF0 set_6_b 6 b set_b,
+    0xF1 set_6_c 6 c set_c,
+    0xF2 set_6_d 6 d set_d,
+    0xF3 set_6_e 6 e set_e,
+    0xF4 set_6_h 6 h set_h,
+    0xF5 set_6_l 6 l set_l,
+    0xF7 set_6_a 6 a set_a,
+    0xF8 set_7_b 7 b set_b,
+    0xF9 set_7_c 7 c set_c,
+    0xFA set_7_d 7 d set_d,
+    0xFB set_7_e 7 e set_e,
+    0xFC set_7_h 7 h set_h,
+    0xFD set_7_l 7 l set_l,
+    0xFF set_7_a 7 a set_a,
+}
+
+impl_set_ptr_hl! {
+    0xC6 set_0_ptr_hl 0,
+    0xCE set_1_ptr_hl 1,
+    0xD6 set_2_ptr_hl 2,
+    0xDE set_3_ptr_hl 3,
+    0xE6 set_4_ptr_hl 4,
+    0xEE set_5_ptr_hl 5,
+    0xF6 set_6_ptr_hl 6,
+    0xFE set_7_ptr_hl 7,
+}
+
+fn bit(cpu: &mut Cpu, value: u8, offset: i8) {
+    cpu.flags.update_zero(bit_cleared(value, offset));
+    cpu.flags.clear_subtract();
+    cpu.flags.set_half_carry();
 }
 
 fn bit_cleared(value: u8, offset: i8) -> bool {
@@ -37,3 +304,45 @@ fn bit_cleared(value: u8, offset: i8) -> bool {
 fn bit_set(value: u8, offset: i8) -> bool {
     (value >> offset) & 1 == 1
 }
+
+#[test]
+fn bit_flags() {
+    let mut cpu = Cpu::default();
+    cpu.flags.set_carry();
+
+    bit(&mut cpu, 0b1000_0000, 7);
+    assert!(!cpu.flags.z() && !cpu.flags.n() && cpu.flags.h());
+    bit(&mut cpu, 0b1000_0000, 6);
+    assert!(cpu.flags.z());
+    assert!(cpu.flags.c(), "BIT does not touch the carry flag");
+}
+
+#[test]
+fn bit_res_set_timing() {
+    let mut memory = crate::opcodes::test_memory(&[0xCB, 0x7E, 0xCB, 0x86, 0xCB, 0xFE, 0xCB, 0xD0]);
+    let mut cpu = crate::opcodes::test_cpu();
+    cpu.set_hl(0xC000);
+    memory.write_byte(0xC000, 0b0000_0001);
+
+    crate::opcodes::execute(&mut memory, &mut cpu);
+    assert!(cpu.flags.z());
+    assert_eq!(cpu.elapsed_cycles(), 12);
+
+    crate::opcodes::execute(&mut memory, &mut cpu);
+    assert_eq!(memory.read_byte(0xC000), 0);
+    assert_eq!(cpu.elapsed_cycles(), 12 + 16);
+
+    crate::opcodes::execute(&mut memory, &mut cpu);
+    assert_eq!(memory.read_byte(0xC000), 0b1000_0000);
+    assert_eq!(cpu.elapsed_cycles(), 12 + 16 + 16);
+
+    let flags = cpu.flags.bits();
+    crate::opcodes::execute(&mut memory, &mut cpu);
+    assert_eq!(cpu.b(), 0b0000_0100);
+    assert_eq!(
+        cpu.flags.bits(),
+        flags,
+        "RES and SET do not touch the flags"
+    );
+    assert_eq!(cpu.elapsed_cycles(), 12 + 16 + 16 + 8);
+}
@@ -1,297 +1,291 @@
-use super::Op;
-use crate::{Cpu, Memory};
+use super::Instruction;
+use crate::{Bus, Cpu};
 
 mod bit;
 mod shift;
 
-pub fn execute(memory: &mut Memory, cpu: &mut Cpu) {
-    // CB 1 4 - - - -
+/// The 0xCB entry of the base table. Fetching the second opcode byte clocks the second M-cycle of
+/// the CB instruction.
+pub fn execute(memory: &mut dyn Bus, cpu: &mut Cpu) {
     cpu.increment_program_counter();
-    cpu.clock_cycles(4);
 
-    let instruction = memory.read_byte(cpu.program_counter());
-    let (_name, function) = INSTRUCTIONS[instruction as usize];
+    let opcode = memory.read_byte(cpu.program_counter());
+    let instruction = &INSTRUCTIONS[opcode as usize];
 
-    /*println!(
-        "[${:04X}] 0x{:02X} {}",
-        cpu.program_counter(),
-        instruction,
-        name
-    );*/
-    (function)(memory, cpu);
+    (instruction.execute)(memory, cpu);
 }
 
-const INSTRUCTIONS: [(&str, Op); 256] = [
+pub static INSTRUCTIONS: [Instruction; 256] = instructions! { prefixed: true;
     // 0x0x
-    unimpl_opcode!(0x00 RLC B28Z 0 0 C),
-    unimpl_opcode!(0x01 RLC C28Z 0 0 C),
-    unimpl_opcode!(0x02 RLC D28Z 0 0 C),
-    unimpl_opcode!(0x03 RLC E28Z 0 0 C),
-    unimpl_opcode!(0x04 RLC H28Z 0 0 C),
-    unimpl_opcode!(0x05 RLC L28Z 0 0 C),
-    unimpl_opcode!(0x06 RLC (HL)216Z 0 0 C),
-    unimpl_opcode!(0x07 RLC A28Z 0 0 C),
-    unimpl_opcode!(0x08 RRC B28Z 0 0 C),
-    unimpl_opcode!(0x09 RRC C28Z 0 0 C),
-    unimpl_opcode!(0x0A RRC D28Z 0 0 C),
-    unimpl_opcode!(0x0B RRC E28Z 0 0 C),
-    unimpl_opcode!(0x0C RRC H28Z 0 0 C),
-    unimpl_opcode!(0x0D RRC L28Z 0 0 C),
-    unimpl_opcode!(0x0E RRC (HL)216Z 0 0 C),
-    unimpl_opcode!(0x0F RRC A28Z 0 0 C),
+    0x00 Rlc [B] 8 [Z 0 0 C] shift::rlc_b,
+    0x01 Rlc [C] 8 [Z 0 0 C] shift::rlc_c,
+    0x02 Rlc [D] 8 [Z 0 0 C] shift::rlc_d,
+    0x03 Rlc [E] 8 [Z 0 0 C] shift::rlc_e,
+    0x04 Rlc [H] 8 [Z 0 0 C] shift::rlc_h,
+    0x05 Rlc [L] 8 [Z 0 0 C] shift::rlc_l,
+    0x06 Rlc [PtrHL] 16 [Z 0 0 C] shift::rlc_ptr_hl,
+    0x07 Rlc [A] 8 [Z 0 0 C] shift::rlc_a,
+    0x08 Rrc [B] 8 [Z 0 0 C] shift::rrc_b,
+    0x09 Rrc [C] 8 [Z 0 0 C] shift::rrc_c,
+    0x0A Rrc [D] 8 [Z 0 0 C] shift::rrc_d,
+    0x0B Rrc [E] 8 [Z 0 0 C] shift::rrc_e,
+    0x0C Rrc [H] 8 [Z 0 0 C] shift::rrc_h,
+    0x0D Rrc [L] 8 [Z 0 0 C] shift::rrc_l,
+    0x0E Rrc [PtrHL] 16 [Z 0 0 C] shift::rrc_ptr_hl,
+    0x0F Rrc [A] 8 [Z 0 0 C] shift::rrc_a,
     // 0x1x
-    unimpl_opcode!(0x10 RL B28Z 0 0 C),
-    ("0x11 RL C", shift::rl_c),
-    unimpl_opcode!(0x12 RL D28Z 0 0 C),
-    unimpl_opcode!(0x13 RL E28Z 0 0 C),
-    unimpl_opcode!(0x14 RL H28Z 0 0 C),
-    unimpl_opcode!(0x15 RL L28Z 0 0 C),
-    unimpl_opcode!(0x16 RL (HL)216Z 0 0 C),
-    unimpl_opcode!(0x17 RL A28Z 0 0 C),
-    unimpl_opcode!(0x18 RR B28Z 0 0 C),
-    unimpl_opcode!(0x19 RR C28Z 0 0 C),
-    unimpl_opcode!(0x1A RR D28Z 0 0 C),
-    unimpl_opcode!(0x1B RR E28Z 0 0 C),
-    unimpl_opcode!(0x1C RR H28Z 0 0 C),
-    unimpl_opcode!(0x1D RR L28Z 0 0 C),
-    unimpl_opcode!(0x1E RR (HL)216Z 0 0 C),
-    unimpl_opcode!(0x1F RR A28Z 0 0 C),
+    0x10 Rl [B] 8 [Z 0 0 C] shift::rl_b,
+    0x11 Rl [C] 8 [Z 0 0 C] shift::rl_c,
+    0x12 Rl [D] 8 [Z 0 0 C] shift::rl_d,
+    0x13 Rl [E] 8 [Z 0 0 C] shift::rl_e,
+    0x14 Rl [H] 8 [Z 0 0 C] shift::rl_h,
+    0x15 Rl [L] 8 [Z 0 0 C] shift::rl_l,
+    0x16 Rl [PtrHL] 16 [Z 0 0 C] shift::rl_ptr_hl,
+    0x17 Rl [A] 8 [Z 0 0 C] shift::rl_a,
+    0x18 Rr [B] 8 [Z 0 0 C] shift::rr_b,
+    0x19 Rr [C] 8 [Z 0 0 C] shift::rr_c,
+    0x1A Rr [D] 8 [Z 0 0 C] shift::rr_d,
+    0x1B Rr [E] 8 [Z 0 0 C] shift::rr_e,
+    0x1C Rr [H] 8 [Z 0 0 C] shift::rr_h,
+    0x1D Rr [L] 8 [Z 0 0 C] shift::rr_l,
+    0x1E Rr [PtrHL] 16 [Z 0 0 C] shift::rr_ptr_hl,
+    0x1F Rr [A] 8 [Z 0 0 C] shift::rr_a,
     // 0x2x
-    unimpl_opcode!(0x20 SLA B28Z 0 0 C),
-    unimpl_opcode!(0x21 SLA C28Z 0 0 C),
-    unimpl_opcode!(0x22 SLA D28Z 0 0 C),
-    unimpl_opcode!(0x23 SLA E28Z 0 0 C),
-    unimpl_opcode!(0x24 SLA H28Z 0 0 C),
-    unimpl_opcode!(0x25 SLA L28Z 0 0 C),
-    unimpl_opcode!(0x26 SLA (HL)216Z 0 0 C),
-    unimpl_opcode!(0x27 SLA A28Z 0 0 C),
-    unimpl_opcode!(0x28 SRA B28Z 0 0 0),
-    unimpl_opcode!(0x29 SRA C28Z 0 0 0),
-    unimpl_opcode!(0x2A SRA D28Z 0 0 0),
-    unimpl_opcode!(0x2B SRA E28Z 0 0 0),
-    unimpl_opcode!(0x2C SRA H28Z 0 0 0),
-    unimpl_opcode!(0x2D SRA L28Z 0 0 0),
-    unimpl_opcode!(0x2E SRA (HL)216Z 0 0 0),
-    unimpl_opcode!(0x2F SRA A28Z 0 0 0),
+    0x20 Sla [B] 8 [Z 0 0 C] shift::sla_b,
+    0x21 Sla [C] 8 [Z 0 0 C] shift::sla_c,
+    0x22 Sla [D] 8 [Z 0 0 C] shift::sla_d,
+    0x23 Sla [E] 8 [Z 0 0 C] shift::sla_e,
+    0x24 Sla [H] 8 [Z 0 0 C] shift::sla_h,
+    0x25 Sla [L] 8 [Z 0 0 C] shift::sla_l,
+    0x26 Sla [PtrHL] 16 [Z 0 0 C] shift::sla_ptr_hl,
+    0x27 Sla [A] 8 [Z 0 0 C] shift::sla_a,
+    0x28 Sra [B] 8 [Z 0 0 C] shift::sra_b,
+    0x29 Sra [C] 8 [Z 0 0 C] shift::sra_c,
+    0x2A Sra [D] 8 [Z 0 0 C] shift::sra_d,
+    0x2B Sra [E] 8 [Z 0 0 C] shift::sra_e,
+    0x2C Sra [H] 8 [Z 0 0 C] shift::sra_h,
+    0x2D Sra [L] 8 [Z 0 0 C] shift::sra_l,
+    0x2E Sra [PtrHL] 16 [Z 0 0 C] shift::sra_ptr_hl,
+    0x2F Sra [A] 8 [Z 0 0 C] shift::sra_a,
     // 0x3x
-    unimpl_opcode!(0x30 SWAP B28Z 0 0 0),
-    unimpl_opcode!(0x31 SWAP C28Z 0 0 0),
-    unimpl_opcode!(0x32 SWAP D28Z 0 0 0),
-    unimpl_opcode!(0x33 SWAP E28Z 0 0 0),
-    unimpl_opcode!(0x34 SWAP H28Z 0 0 0),
-    unimpl_opcode!(0x35 SWAP L28Z 0 0 0),
-    unimpl_opcode!(0x36 SWAP (HL)216Z 0 0 0),
-    unimpl_opcode!(0x37 SWAP A28Z 0 0 0),
-    unimpl_opcode!(0x38 SRL B28Z 0 0 C),
-    unimpl_opcode!(0x39 SRL C28Z 0 0 C),
-    unimpl_opcode!(0x3A SRL D28Z 0 0 C),
-    unimpl_opcode!(0x3B SRL E28Z 0 0 C),
-    unimpl_opcode!(0x3C SRL H28Z 0 0 C),
-    unimpl_opcode!(0x3D SRL L28Z 0 0 C),
-    unimpl_opcode!(0x3E SRL (HL)216Z 0 0 C),
-    unimpl_opcode!(0x3F SRL A28Z 0 0 C),
+    0x30 Swap [B] 8 [Z 0 0 0] shift::swap_b,
+    0x31 Swap [C] 8 [Z 0 0 0] shift::swap_c,
+    0x32 Swap [D] 8 [Z 0 0 0] shift::swap_d,
+    0x33 Swap [E] 8 [Z 0 0 0] shift::swap_e,
+    0x34 Swap [H] 8 [Z 0 0 0] shift::swap_h,
+    0x35 Swap [L] 8 [Z 0 0 0] shift::swap_l,
+    0x36 Swap [PtrHL] 16 [Z 0 0 0] shift::swap_ptr_hl,
+    0x37 Swap [A] 8 [Z 0 0 0] shift::swap_a,
+    0x38 Srl [B] 8 [Z 0 0 C] shift::srl_b,
+    0x39 Srl [C] 8 [Z 0 0 C] shift::srl_c,
+    0x3A Srl [D] 8 [Z 0 0 C] shift::srl_d,
+    0x3B Srl [E] 8 [Z 0 0 C] shift::srl_e,
+    0x3C Srl [H] 8 [Z 0 0 C] shift::srl_h,
+    0x3D Srl [L] 8 [Z 0 0 C] shift::srl_l,
+    0x3E Srl [PtrHL] 16 [Z 0 0 C] shift::srl_ptr_hl,
+    0x3F Srl [A] 8 [Z 0 0 C] shift::srl_a,
     // 0x4x
-    unimpl_opcode!(0x40 BIT 0,B28Z 0 1 -),
-    unimpl_opcode!(0x41 BIT 0,C28Z 0 1 -),
-    unimpl_opcode!(0x42 BIT 0,D28Z 0 1 -),
-    unimpl_opcode!(0x43 BIT 0,E28Z 0 1 -),
-    unimpl_opcode!(0x44 BIT 0,H28Z 0 1 -),
-    unimpl_opcode!(0x45 BIT 0,L28Z 0 1 -),
-    unimpl_opcode!(0x46 BIT 0,(HL)216Z 0 1 -),
-    unimpl_opcode!(0x47 BIT 0,A28Z 0 1 -),
-    unimpl_opcode!(0x48 BIT 1,B28Z 0 1 -),
-    unimpl_opcode!(0x49 BIT 1,C28Z 0 1 -),
-    unimpl_opcode!(0x4A BIT 1,D28Z 0 1 -),
-    unimpl_opcode!(0x4B BIT 1,E28Z 0 1 -),
-    unimpl_opcode!(0x4C BIT 1,H28Z 0 1 -),
-    unimpl_opcode!(0x4D BIT 1,L28Z 0 1 -),
-    unimpl_opcode!(0x4E BIT 1,(HL)216Z 0 1 -),
-    unimpl_opcode!(0x4F BIT 1,A28Z 0 1 -),
+    0x40 Bit [Bit(0), B] 8 [Z 0 1 -] bit::bit_0_b,
+    0x41 Bit [Bit(0), C] 8 [Z 0 1 -] bit::bit_0_c,
+    0x42 Bit [Bit(0), D] 8 [Z 0 1 -] bit::bit_0_d,
+    0x43 Bit [Bit(0), E] 8 [Z 0 1 -] bit::bit_0_e,
+    0x44 Bit [Bit(0), H] 8 [Z 0 1 -] bit::bit_0_h,
+    0x45 Bit [Bit(0), L] 8 [Z 0 1 -] bit::bit_0_l,
+    0x46 Bit [Bit(0), PtrHL] 12 [Z 0 1 -] bit::bit_0_ptr_hl,
+    0x47 Bit [Bit(0), A] 8 [Z 0 1 -] bit::bit_0_a,
+    0x48 Bit [Bit(1), B] 8 [Z 0 1 -] bit::bit_1_b,
+    0x49 Bit [Bit(1), C] 8 [Z 0 1 -] bit::bit_1_c,
+    0x4A Bit [Bit(1), D] 8 [Z 0 1 -] bit::bit_1_d,
+    0x4B Bit [Bit(1), E] 8 [Z 0 1 -] bit::bit_1_e,
+    0x4C Bit [Bit(1), H] 8 [Z 0 1 -] bit::bit_1_h,
+    0x4D Bit [Bit(1), L] 8 [Z 0 1 -] bit::bit_1_l,
+    0x4E Bit [Bit(1), PtrHL] 12 [Z 0 1 -] bit::bit_1_ptr_hl,
+    0x4F Bit [Bit(1), A] 8 [Z 0 1 -] bit::bit_1_a,
     // 0x5x
-    unimpl_opcode!(0x50 BIT 2,B28Z 0 1 -),
-    unimpl_opcode!(0x51 BIT 2,C28Z 0 1 -),
-    unimpl_opcode!(0x52 BIT 2,D28Z 0 1 -),
-    unimpl_opcode!(0x53 BIT 2,E28Z 0 1 -),
-    unimpl_opcode!(0x54 BIT 2,H28Z 0 1 -),
-    unimpl_opcode!(0x55 BIT 2,L28Z 0 1 -),
-    unimpl_opcode!(0x56 BIT 2,(HL)216Z 0 1 -),
-    unimpl_opcode!(0x57 BIT 2,A28Z 0 1 -),
-    unimpl_opcode!(0x58 BIT 3,B28Z 0 1 -),
-    unimpl_opcode!(0x59 BIT 3,C28Z 0 1 -),
-    unimpl_opcode!(0x5A BIT 3,D28Z 0 1 -),
-    unimpl_opcode!(0x5B BIT 3,E28Z 0 1 -),
-    unimpl_opcode!(0x5C BIT 3,H28Z 0 1 -),
-    unimpl_opcode!(0x5D BIT 3,L28Z 0 1 -),
-    unimpl_opcode!(0x5E BIT 3,(HL)216Z 0 1 -),
-    unimpl_opcode!(0x5F BIT 3,A28Z 0 1 -),
+    0x50 Bit [Bit(2), B] 8 [Z 0 1 -] bit::bit_2_b,
+    0x51 Bit [Bit(2), C] 8 [Z 0 1 -] bit::bit_2_c,
+    0x52 Bit [Bit(2), D] 8 [Z 0 1 -] bit::bit_2_d,
+    0x53 Bit [Bit(2), E] 8 [Z 0 1 -] bit::bit_2_e,
+    0x54 Bit [Bit(2), H] 8 [Z 0 1 -] bit::bit_2_h,
+    0x55 Bit [Bit(2), L] 8 [Z 0 1 -] bit::bit_2_l,
+    0x56 Bit [Bit(2), PtrHL] 12 [Z 0 1 -] bit::bit_2_ptr_hl,
+    0x57 Bit [Bit(2), A] 8 [Z 0 1 -] bit::bit_2_a,
+    0x58 Bit [Bit(3), B] 8 [Z 0 1 -] bit::bit_3_b,
+    0x59 Bit [Bit(3), C] 8 [Z 0 1 -] bit::bit_3_c,
+    0x5A Bit [Bit(3), D] 8 [Z 0 1 -] bit::bit_3_d,
+    0x5B Bit [Bit(3), E] 8 [Z 0 1 -] bit::bit_3_e,
+    0x5C Bit [Bit(3), H] 8 [Z 0 1 -] bit::bit_3_h,
+    0x5D Bit [Bit(3), L] 8 [Z 0 1 -] bit::bit_3_l,
+    0x5E Bit [Bit(3), PtrHL] 12 [Z 0 1 -] bit::bit_3_ptr_hl,
+    0x5F Bit [Bit(3), A] 8 [Z 0 1 -] bit::bit_3_a,
     // 0x6x
-    unimpl_opcode!(0x60 BIT 4,B28Z 0 1 -),
-    unimpl_opcode!(0x61 BIT 4,C28Z 0 1 -),
-    unimpl_opcode!(0x62 BIT 4,D28Z 0 1 -),
-    unimpl_opcode!(0x63 BIT 4,E28Z 0 1 -),
-    unimpl_opcode!(0x64 BIT 4,H28Z 0 1 -),
-    unimpl_opcode!(0x65 BIT 4,L28Z 0 1 -),
-    unimpl_opcode!(0x66 BIT 4,(HL)216Z 0 1 -),
-    unimpl_opcode!(0x67 BIT 4,A28Z 0 1 -),
-    unimpl_opcode!(0x68 BIT 5,B28Z 0 1 -),
-    unimpl_opcode!(0x69 BIT 5,C28Z 0 1 -),
-    unimpl_opcode!(0x6A BIT 5,D28Z 0 1 -),
-    unimpl_opcode!(0x6B BIT 5,E28Z 0 1 -),
-    unimpl_opcode!(0x6C BIT 5,H28Z 0 1 -),
-    unimpl_opcode!(0x6D BIT 5,L28Z 0 1 -),
-    unimpl_opcode!(0x6E BIT 5,(HL)216Z 0 1 -),
-    unimpl_opcode!(0x6F BIT 5,A28Z 0 1 -),
+    0x60 Bit [Bit(4), B] 8 [Z 0 1 -] bit::bit_4_b,
+    0x61 Bit [Bit(4), C] 8 [Z 0 1 -] bit::bit_4_c,
+    0x62 Bit [Bit(4), D] 8 [Z 0 1 -] bit::bit_4_d,
+    0x63 Bit [Bit(4), E] 8 [Z 0 1 -] bit::bit_4_e,
+    0x64 Bit [Bit(4), H] 8 [Z 0 1 -] bit::bit_4_h,
+    0x65 Bit [Bit(4), L] 8 [Z 0 1 -] bit::bit_4_l,
+    0x66 Bit [Bit(4), PtrHL] 12 [Z 0 1 -] bit::bit_4_ptr_hl,
+    0x67 Bit [Bit(4), A] 8 [Z 0 1 -] bit::bit_4_a,
+    0x68 Bit [Bit(5), B] 8 [Z 0 1 -] bit::bit_5_b,
+    0x69 Bit [Bit(5), C] 8 [Z 0 1 -] bit::bit_5_c,
+    0x6A Bit [Bit(5), D] 8 [Z 0 1 -] bit::bit_5_d,
+    0x6B Bit [Bit(5), E] 8 [Z 0 1 -] bit::bit_5_e,
+    0x6C Bit [Bit(5), H] 8 [Z 0 1 -] bit::bit_5_h,
+    0x6D Bit [Bit(5), L] 8 [Z 0 1 -] bit::bit_5_l,
+    0x6E Bit [Bit(5), PtrHL] 12 [Z 0 1 -] bit::bit_5_ptr_hl,
+    0x6F Bit [Bit(5), A] 8 [Z 0 1 -] bit::bit_5_a,
     // 0x7x
-    unimpl_opcode!(0x70 BIT 6,B28Z 0 1 -),
-    unimpl_opcode!(0x71 BIT 6,C28Z 0 1 -),
-    unimpl_opcode!(0x72 BIT 6,D28Z 0 1 -),
-    unimpl_opcode!(0x73 BIT 6,E28Z 0 1 -),
-    unimpl_opcode!(0x74 BIT 6,H28Z 0 1 -),
-    unimpl_opcode!(0x75 BIT 6,L28Z 0 1 -),
-    unimpl_opcode!(0x76 BIT 6,(HL)216Z 0 1 -),
-    unimpl_opcode!(0x77 BIT 6,A28Z 0 1 -),
-    unimpl_opcode!(0x78 BIT 7,B28Z 0 1 -),
-    unimpl_opcode!(0x79 BIT 7,C28Z 0 1 -),
-    unimpl_opcode!(0x7A BIT 7,D28Z 0 1 -),
-    unimpl_opcode!(0x7B BIT 7,E28Z 0 1 -),
-    ("BIT 7,H", bit::bit_7_h),
-    unimpl_opcode!(0x7D BIT 7,L28Z 0 1 -),
-    unimpl_opcode!(0x7E BIT 7,(HL)216Z 0 1 -),
-    unimpl_opcode!(0x7F BIT 7,A28Z 0 1 -),
+    0x70 Bit [Bit(6), B] 8 [Z 0 1 -] bit::bit_6_b,
+    0x71 Bit [Bit(6), C] 8 [Z 0 1 -] bit::bit_6_c,
+    0x72 Bit [Bit(6), D] 8 [Z 0 1 -] bit::bit_6_d,
+    0x73 Bit [Bit(6), E] 8 [Z 0 1 -] bit::bit_6_e,
+    0x74 Bit [Bit(6), H] 8 [Z 0 1 -] bit::bit_6_h,
+    0x75 Bit [Bit(6), L] 8 [Z 0 1 -] bit::bit_6_l,
+    0x76 Bit [Bit(6), PtrHL] 12 [Z 0 1 -] bit::bit_6_ptr_hl,
+    0x77 Bit [Bit(6), A] 8 [Z 0 1 -] bit::bit_6_a,
+    0x78 Bit [Bit(7), B] 8 [Z 0 1 -] bit::bit_7_b,
+    0x79 Bit [Bit(7), C] 8 [Z 0 1 -] bit::bit_7_c,
+    0x7A Bit [Bit(7), D] 8 [Z 0 1 -] bit::bit_7_d,
+    0x7B Bit [Bit(7), E] 8 [Z 0 1 -] bit::bit_7_e,
+    0x7C Bit [Bit(7), H] 8 [Z 0 1 -] bit::bit_7_h,
+    0x7D Bit [Bit(7), L] 8 [Z 0 1 -] bit::bit_7_l,
+    0x7E Bit [Bit(7), PtrHL] 12 [Z 0 1 -] bit::bit_7_ptr_hl,
+    0x7F Bit [Bit(7), A] 8 [Z 0 1 -] bit::bit_7_a,
     // 0x8x
-    unimpl_opcode!(0x80 RES 0,B28- - - -),
-    unimpl_opcode!(0x81 RES 0,C28- - - -),
-    unimpl_opcode!(0x82 RES 0,D28- - - -),
-    unimpl_opcode!(0x83 RES 0,E28- - - -),
-    unimpl_opcode!(0x84 RES 0,H28- - - -),
-    unimpl_opcode!(0x85 RES 0,L28- - - -),
-    unimpl_opcode!(0x86 RES 0,(HL)216- - - -),
-    ("0x87 RES 0, A", bit::res_0_a),
-    unimpl_opcode!(0x88 RES 1,B28- - - -),
-    unimpl_opcode!(0x89 RES 1,C28- - - -),
-    unimpl_opcode!(0x8A RES 1,D28- - - -),
-    unimpl_opcode!(0x8B RES 1,E28- - - -),
-    unimpl_opcode!(0x8C RES 1,H28- - - -),
-    unimpl_opcode!(0x8D RES 1,L28- - - -),
-    unimpl_opcode!(0x8E RES 1,(HL)216- - - -),
-    unimpl_opcode!(0x8F RES 1,A28- - - -),
+    0x80 Res [Bit(0), B] 8 [- - - -] bit::res_0_b,
+    0x81 Res [Bit(0), C] 8 [- - - -] bit::res_0_c,
+    0x82 Res [Bit(0), D] 8 [- - - -] bit::res_0_d,
+    0x83 Res [Bit(0), E] 8 [- - - -] bit::res_0_e,
+    0x84 Res [Bit(0), H] 8 [- - - -] bit::res_0_h,
+    0x85 Res [Bit(0), L] 8 [- - - -] bit::res_0_l,
+    0x86 Res [Bit(0), PtrHL] 16 [- - - -] bit::res_0_ptr_hl,
+    0x87 Res [Bit(0), A] 8 [- - - -] bit::res_0_a,
+    0x88 Res [Bit(1), B] 8 [- - - -] bit::res_1_b,
+    0x89 Res [Bit(1), C] 8 [- - - -] bit::res_1_c,
+    0x8A Res [Bit(1), D] 8 [- - - -] bit::res_1_d,
+    0x8B Res [Bit(1), E] 8 [- - - -] bit::res_1_e,
+    0x8C Res [Bit(1), H] 8 [- - - -] bit::res_1_h,
+    0x8D Res [Bit(1), L] 8 [- - - -] bit::res_1_l,
+    0x8E Res [Bit(1), PtrHL] 16 [- - - -] bit::res_1_ptr_hl,
+    0x8F Res [Bit(1), A] 8 [- - - -] bit::res_1_a,
     // 0x9x
-    unimpl_opcode!(0x90 RES 2,B28- - - -),
-    unimpl_opcode!(0x91 RES 2,C28- - - -),
-    unimpl_opcode!(0x92 RES 2,D28- - - -),
-    unimpl_opcode!(0x93 RES 2,E28- - - -),
-    unimpl_opcode!(0x94 RES 2,H28- - - -),
-    unimpl_opcode!(0x95 RES 2,L28- - - -),
-    unimpl_opcode!(0x96 RES 2,(HL)216- - - -),
-    unimpl_opcode!(0x97 RES 2,A28- - - -),
-    unimpl_opcode!(0x98 RES 3,B28- - - -),
-    unimpl_opcode!(0x99 RES 3,C28- - - -),
-    unimpl_opcode!(0x9A RES 3,D28- - - -),
-    unimpl_opcode!(0x9B RES 3,E28- - - -),
-    unimpl_opcode!(0x9C RES 3,H28- - - -),
-    unimpl_opcode!(0x9D RES 3,L28- - - -),
-    unimpl_opcode!(0x9E RES 3,(HL)216- - - -),
-    unimpl_opcode!(0x9F RES 3,A28- - - -),
+    0x90 Res [Bit(2), B] 8 [- - - -] bit::res_2_b,
+    0x91 Res [Bit(2), C] 8 [- - - -] bit::res_2_c,
+    0x92 Res [Bit(2), D] 8 [- - - -] bit::res_2_d,
+    0x93 Res [Bit(2), E] 8 [- - - -] bit::res_2_e,
+    0x94 Res [Bit(2), H] 8 [- - - -] bit::res_2_h,
+    0x95 Res [Bit(2), L] 8 [- - - -] bit::res_2_l,
+    0x96 Res [Bit(2), PtrHL] 16 [- - - -] bit::res_2_ptr_hl,
+    0x97 Res [Bit(2), A] 8 [- - - -] bit::res_2_a,
+    0x98 Res [Bit(3), B] 8 [- - - -] bit::res_3_b,
+    0x99 Res [Bit(3), C] 8 [- - - -] bit::res_3_c,
+    0x9A Res [Bit(3), D] 8 [- - - -] bit::res_3_d,
+    0x9B Res [Bit(3), E] 8 [- - - -] bit::res_3_e,
+    0x9C Res [Bit(3), H] 8 [- - - -] bit::res_3_h,
+    0x9D Res [Bit(3), L] 8 [- - - -] bit::res_3_l,
+    0x9E Res [Bit(3), PtrHL] 16 [- - - -] bit::res_3_ptr_hl,
+    0x9F Res [Bit(3), A] 8 [- - - -] bit::res_3_a,
     // 0xAx
-    unimpl_opcode!(0xA0 RES 4,B28- - - -),
-    unimpl_opcode!(0xA1 RES 4,C28- - - -),
-    unimpl_opcode!(0xA2 RES 4,D28- - - -),
-    unimpl_opcode!(0xA3 RES 4,E28- - - -),
-    unimpl_opcode!(0xA4 RES 4,H28- - - -),
-    unimpl_opcode!(0xA5 RES 4,L28- - - -),
-    unimpl_opcode!(0xA6 RES 4,(HL)216- - - -),
-    unimpl_opcode!(0xA7 RES 4,A28- - - -),
-    unimpl_opcode!(0xA8 RES 5,B28- - - -),
-    unimpl_opcode!(0xA9 RES 5,C28- - - -),
-    unimpl_opcode!(0xAA RES 5,D28- - - -),
-    unimpl_opcode!(0xAB RES 5,E28- - - -),
-    unimpl_opcode!(0xAC RES 5,H28- - - -),
-    unimpl_opcode!(0xAD RES 5,L28- - - -),
-    unimpl_opcode!(0xAE RES 5,(HL)216- - - -),
-    unimpl_opcode!(0xAF RES 5,A28- - - -),
+    0xA0 Res [Bit(4), B] 8 [- - - -] bit::res_4_b,
+    0xA1 Res [Bit(4), C] 8 [- - - -] bit::res_4_c,
+    0xA2 Res [Bit(4), D] 8 [- - - -] bit::res_4_d,
+    0xA3 Res [Bit(4), E] 8 [- - - -] bit::res_4_e,
+    0xA4 Res [Bit(4), H] 8 [- - - -] bit::res_4_h,
+    0xA5 Res [Bit(4), L] 8 [- - - -] bit::res_4_l,
+    0xA6 Res [Bit(4), PtrHL] 16 [- - - -] bit::res_4_ptr_hl,
+    0xA7 Res [Bit(4), A] 8 [- - - -] bit::res_4_a,
+    0xA8 Res [Bit(5), B] 8 [- - - -] bit::res_5_b,
+    0xA9 Res [Bit(5), C] 8 [- - - -] bit::res_5_c,
+    0xAA Res [Bit(5), D] 8 [- - - -] bit::res_5_d,
+    0xAB Res [Bit(5), E] 8 [- - - -] bit::res_5_e,
+    0xAC Res [Bit(5), H] 8 [- - - -] bit::res_5_h,
+    0xAD Res [Bit(5), L] 8 [- - - -] bit::res_5_l,
+    0xAE Res [Bit(5), PtrHL] 16 [- - - -] bit::res_5_ptr_hl,
+    0xAF Res [Bit(5), A] 8 [- - - -] bit::res_5_a,
     // 0xBx
-    unimpl_opcode!(0xB0 RES 6,B28- - - -),
-    unimpl_opcode!(0xB1 RES 6,C28- - - -),
-    unimpl_opcode!(0xB2 RES 6,D28- - - -),
-    unimpl_opcode!(0xB3 RES 6,E28- - - -),
-    unimpl_opcode!(0xB4 RES 6,H28- - - -),
-    unimpl_opcode!(0xB5 RES 6,L28- - - -),
-    unimpl_opcode!(0xB6 RES 6,(HL)216- - - -),
-    unimpl_opcode!(0xB7 RES 6,A28- - - -),
-    unimpl_opcode!(0xB8 RES 7,B28- - - -),
-    unimpl_opcode!(0xB9 RES 7,C28- - - -),
-    unimpl_opcode!(0xBA RES 7,D28- - - -),
-    unimpl_opcode!(0xBB RES 7,E28- - - -),
-    unimpl_opcode!(0xBC RES 7,H28- - - -),
-    unimpl_opcode!(0xBD RES 7,L28- - - -),
-    unimpl_opcode!(0xBE RES 7,(HL)216- - - -),
-    unimpl_opcode!(0xBF RES 7,A28- - - -),
+    0xB0 Res [Bit(6), B] 8 [- - - -] bit::res_6_b,
+    0xB1 Res [Bit(6), C] 8 [- - - -] bit::res_6_c,
+    0xB2 Res [Bit(6), D] 8 [- - - -] bit::res_6_d,
+    0xB3 Res [Bit(6), E] 8 [- - - -] bit::res_6_e,
+    0xB4 Res [Bit(6), H] 8 [- - - -] bit::res_6_h,
+    0xB5 Res [Bit(6), L] 8 [- - - -] bit::res_6_l,
+    0xB6 Res [Bit(6), PtrHL] 16 [- - - -] bit::res_6_ptr_hl,
+    0xB7 Res [Bit(6), A] 8 [- - - -] bit::res_6_a,
+    0xB8 Res [Bit(7), B] 8 [- - - -] bit::res_7_b,
+    0xB9 Res [Bit(7), C] 8 [- - - -] bit::res_7_c,
+    0xBA Res [Bit(7), D] 8 [- - - -] bit::res_7_d,
+    0xBB Res [Bit(7), E] 8 [- - - -] bit::res_7_e,
+    0xBC Res [Bit(7), H] 8 [- - - -] bit::res_7_h,
+    0xBD Res [Bit(7), L] 8 [- - - -] bit::res_7_l,
+    0xBE Res [Bit(7), PtrHL] 16 [- - - -] bit::res_7_ptr_hl,
+    0xBF Res [Bit(7), A] 8 [- - - -] bit::res_7_a,
     // 0xCx
-    unimpl_opcode!(0xC0 SET 0,B28- - - -),
-    unimpl_opcode!(0xC1 SET 0,C28- - - -),
-    unimpl_opcode!(0xC2 SET 0,D28- - - -),
-    unimpl_opcode!(0xC3 SET 0,E28- - - -),
-    unimpl_opcode!(0xC4 SET 0,H28- - - -),
-    unimpl_opcode!(0xC5 SET 0,L28- - - -),
-    unimpl_opcode!(0xC6 SET 0,(HL)216- - - -),
-    unimpl_opcode!(0xC7 SET 0,A28- - - -),
-    unimpl_opcode!(0xC8 SET 1,B28- - - -),
-    unimpl_opcode!(0xC9 SET 1,C28- - - -),
-    unimpl_opcode!(0xCA SET 1,D28- - - -),
-    unimpl_opcode!(0xCB SET 1,E28- - - -),
-    unimpl_opcode!(0xCC SET 1,H28- - - -),
-    unimpl_opcode!(0xCD SET 1,L28- - - -),
-    unimpl_opcode!(0xCE SET 1,(HL)216- - - -),
-    unimpl_opcode!(0xCF SET 1,A28- - - -),
+    0xC0 Set [Bit(0), B] 8 [- - - -] bit::set_0_b,
+    0xC1 Set [Bit(0), C] 8 [- - - -] bit::set_0_c,
+    0xC2 Set [Bit(0), D] 8 [- - - -] bit::set_0_d,
+    0xC3 Set [Bit(0), E] 8 [- - - -] bit::set_0_e,
+    0xC4 Set [Bit(0), H] 8 [- - - -] bit::set_0_h,
+    0xC5 Set [Bit(0), L] 8 [- - - -] bit::set_0_l,
+    0xC6 Set [Bit(0), PtrHL] 16 [- - - -] bit::set_0_ptr_hl,
+    0xC7 Set [Bit(0), A] 8 [- - - -] bit::set_0_a,
+    0xC8 Set [Bit(1), B] 8 [- - - -] bit::set_1_b,
+    0xC9 Set [Bit(1), C] 8 [- - - -] bit::set_1_c,
+    0xCA Set [Bit(1), D] 8 [- - - -] bit::set_1_d,
+    0xCB Set [Bit(1), E] 8 [- - - -] bit::set_1_e,
+    0xCC Set [Bit(1), H] 8 [- - - -] bit::set_1_h,
+    0xCD Set [Bit(1), L] 8 [- - - -] bit::set_1_l,
+    0xCE Set [Bit(1), PtrHL] 16 [- - - -] bit::set_1_ptr_hl,
+    0xCF Set [Bit(1), A] 8 [- - - -] bit::set_1_a,
     // 0xDx
-    unimpl_opcode!(0xD0 SET 2,B28- - - -),
-    unimpl_opcode!(0xD1 SET 2,C28- - - -),
-    unimpl_opcode!(0xD2 SET 2,D28- - - -),
-    unimpl_opcode!(0xD3 SET 2,E28- - - -),
-    unimpl_opcode!(0xD4 SET 2,H28- - - -),
-    unimpl_opcode!(0xD5 SET 2,L28- - - -),
-    unimpl_opcode!(0xD6 SET 2,(HL)216- - - -),
-    unimpl_opcode!(0xD7 SET 2,A28- - - -),
-    unimpl_opcode!(0xD8 SET 3,B28- - - -),
-    unimpl_opcode!(0xD9 SET 3,C28- - - -),
-    unimpl_opcode!(0xDA SET 3,D28- - - -),
-    unimpl_opcode!(0xDB SET 3,E28- - - -),
-    unimpl_opcode!(0xDC SET 3,H28- - - -),
-    unimpl_opcode!(0xDD SET 3,L28- - - -),
-    unimpl_opcode!(0xDE SET 3,(HL)216- - - -),
-    unimpl_opcode!(0xDF SET 3,A28- - - -),
+    0xD0 Set [Bit(2), B] 8 [- - - -] bit::set_2_b,
+    0xD1 Set [Bit(2), C] 8 [- - - -] bit::set_2_c,
+    0xD2 Set [Bit(2), D] 8 [- - - -] bit::set_2_d,
+    0xD3 Set [Bit(2), E] 8 [- - - -] bit::set_2_e,
+    0xD4 Set [Bit(2), H] 8 [- - - -] bit::set_2_h,
+    0xD5 Set [Bit(2), L] 8 [- - - -] bit::set_2_l,
+    0xD6 Set [Bit(2), PtrHL] 16 [- - - -] bit::set_2_ptr_hl,
+    0xD7 Set [Bit(2), A] 8 [- - - -] bit::set_2_a,
+    0xD8 Set [Bit(3), B] 8 [- - - -] bit::set_3_b,
+    0xD9 Set [Bit(3), C] 8 [- - - -] bit::set_3_c,
+    0xDA Set [Bit(3), D] 8 [- - - -] bit::set_3_d,
+    0xDB Set [Bit(3), E] 8 [- - - -] bit::set_3_e,
+    0xDC Set [Bit(3), H] 8 [- - - -] bit::set_3_h,
+    0xDD Set [Bit(3), L] 8 [- - - -] bit::set_3_l,
+    0xDE Set [Bit(3), PtrHL] 16 [- - - -] bit::set_3_ptr_hl,
+    0xDF Set [Bit(3), A] 8 [- - - -] bit::set_3_a,
     // 0xEx
-    unimpl_opcode!(0xE0 SET 4,B28- - - -),
-    unimpl_opcode!(0xE1 SET 4,C28- - - -),
-    unimpl_opcode!(0xE2 SET 4,D28- - - -),
-    unimpl_opcode!(0xE3 SET 4,E28- - - -),
-    unimpl_opcode!(0xE4 SET 4,H28- - - -),
-    unimpl_opcode!(0xE5 SET 4,L28- - - -),
-    unimpl_opcode!(0xE6 SET 4,(HL)216- - - -),
-    unimpl_opcode!(0xE7 SET 4,A28- - - -),
-    unimpl_opcode!(0xE8 SET 5,B28- - - -),
-    unimpl_opcode!(0xE9 SET 5,C28- - - -),
-    unimpl_opcode!(0xEA SET 5,D28- - - -),
-    unimpl_opcode!(0xEB SET 5,E28- - - -),
-    unimpl_opcode!(0xEC SET 5,H28- - - -),
-    unimpl_opcode!(0xED SET 5,L28- - - -),
-    unimpl_opcode!(0xEE SET 5,(HL)216- - - -),
-    unimpl_opcode!(0xEF SET 5,A28- - - -),
+    0xE0 Set [Bit(4), B] 8 [- - - -] bit::set_4_b,
+    0xE1 Set [Bit(4), C] 8 [- - - -] bit::set_4_c,
+    0xE2 Set [Bit(4), D] 8 [- - - -] bit::set_4_d,
+    0xE3 Set [Bit(4), E] 8 [- - - -] bit::set_4_e,
+    0xE4 Set [Bit(4), H] 8 [- - - -] bit::set_4_h,
+    0xE5 Set [Bit(4), L] 8 [- - - -] bit::set_4_l,
+    0xE6 Set [Bit(4), PtrHL] 16 [- - - -] bit::set_4_ptr_hl,
+    0xE7 Set [Bit(4), A] 8 [- - - -] bit::set_4_a,
+    0xE8 Set [Bit(5), B] 8 [- - - -] bit::set_5_b,
+    0xE9 Set [Bit(5), C] 8 [- - - -] bit::set_5_c,
+    0xEA Set [Bit(5), D] 8 [- - - -] bit::set_5_d,
+    0xEB Set [Bit(5), E] 8 [- - - -] bit::set_5_e,
+    0xEC Set [Bit(5), H] 8 [- - - -] bit::set_5_h,
+    0xED Set [Bit(5), L] 8 [- - - -] bit::set_5_l,
+    0xEE Set [Bit(5), PtrHL] 16 [- - - -] bit::set_5_ptr_hl,
+    0xEF Set [Bit(5), A] 8 [- - - -] bit::set_5_a,
     // 0xFx
-    unimpl_opcode!(0xF0 SET 6,B28- - - -),
-    unimpl_opcode!(0xF1 SET 6,C28- - - -),
-    unimpl_opcode!(0xF2 SET 6,D28- - - -),
-    unimpl_opcode!(0xF3 SET 6,E28- - - -),
-    unimpl_opcode!(0xF4 SET 6,H28- - - -),
-    unimpl_opcode!(0xF5 SET 6,L28- - - -),
-    unimpl_opcode!(0xF6 SET 6,(HL)216- - - -),
-    unimpl_opcode!(0xF7 SET 6,A28- - - -),
-    unimpl_opcode!(0xF8 SET 7,B28- - - -),
-    unimpl_opcode!(0xF9 SET 7,C28- - - -),
-    unimpl_opcode!(0xFA SET 7,D28- - - -),
-    unimpl_opcode!(0xFB SET 7,E28- - - -),
-    unimpl_opcode!(0xFC SET 7,H28- - - -),
-    unimpl_opcode!(0xFD SET 7,L28- - - -),
-    unimpl_opcode!(0xFE SET 7,(HL)216- - - -),
-    unimpl_opcode!(0xFF SET 7,A28- - - -),
-];
+    0xF0 Set [Bit(6), B] 8 [- - - -] bit::set_6_b,
+    0xF1 Set [Bit(6), C] 8 [- - - -] bit::set_6_c,
+    0xF2 Set [Bit(6), D] 8 [- - - -] bit::set_6_d,
+    0xF3 Set [Bit(6), E] 8 [- - - -] bit::set_6_e,
+    0xF4 Set [Bit(6), H] 8 [- - - -] bit::set_6_h,
+    0xF5 Set [Bit(6), L] 8 [- - - -] bit::set_6_l,
+    0xF6 Set [Bit(6), PtrHL] 16 [- - - -] bit::set_6_ptr_hl,
+    0xF7 Set [Bit(6), A] 8 [- - - -] bit::set_6_a,
+    0xF8 Set [Bit(7), B] 8 [- - - -] bit::set_7_b,
+    0xF9 Set [Bit(7), C] 8 [- - - -] bit::set_7_c,
+    0xFA Set [Bit(7), D] 8 [- - - -] bit::set_7_d,
+    0xFB Set [Bit(7), E] 8 [- - - -] bit::set_7_e,
+    0xFC Set [Bit(7), H] 8 [- - - -] bit::set_7_h,
+    0xFD Set [Bit(7), L] 8 [- - - -] bit::set_7_l,
+    0xFE Set [Bit(7), PtrHL] 16 [- - - -] bit::set_7_ptr_hl,
+    0xFF Set [Bit(7), A] 8 [- - - -] bit::set_7_a,
+};
@@ -1,13 +1,117 @@
-use crate::{Cpu, Memory};
+use crate::{Bus, Cpu};
 
-pub fn rl_c(_: &mut Memory, cpu: &mut Cpu) {
-    // 0x11 RL C 1 4 Z 0 0 C
-    cpu.increment_program_counter();
-    cpu.clock_cycles(4);
+macro_rules! impl_shift {
+    ($($code:tt $name:ident $op:ident $field:ident $set_field:ident),* $(,)?) => {
+        $(
+            pub fn $name(_: &mut dyn Bus, cpu: &mut Cpu) {
+                cpu.increment_program_counter();
 
-    let c = rl(cpu, cpu.c());
-    cpu.flags.update_zero(c == 0);
-    cpu.set_c(c);
+                let val = $op(cpu, cpu.$field());
+                cpu.$set_field(val);
+            }
+        )*
+    }
+}
+
+macro_rules! impl_shift_ptr_hl {
+    ($($code:tt $name:ident $op:ident),* $(,)?) => {
+        $(
+            pub fn $name(memory: &mut dyn Bus, cpu: &mut Cpu) {
+                cpu.increment_program_counter();
+
+                let address = cpu.hl();
+                let val = $op(cpu, memory.read_byte(address));
+                memory.write_byte(address, val);
+            }
+        )*
+    }
+}
+
+impl_shift! {
+    0x00 rlc_b rlc b set_b,
+    0x01 rlc_c rlc c set_c,
+    0x02 rlc_d rlc d set_d,
+    0x03 rlc_e rlc e set_e,
+    0x04 rlc_h rlc h set_h,
+    0x05 rlc_l rlc l set_l,
+    0x07 rlc_a rlc a set_a,
+    0x08 rrc_b rrc b set_b,
+    0x09 rrc_c rrc c set_c,
+    0x0A rrc_d rrc d set_d,
+    0x0B rrc_e rrc e set_e,
+    0x0C rrc_h rrc h set_h,
+    0x0D rrc_l rrc l set_l,
+    0x0F rrc_a rrc a set_a,
+    0x10 rl_b rl b set_b,
+    0x11 rl_c rl c set_c,
+    0x12 rl_d rl d set_d,
+    0x13 rl_e rl e set_e,
+    0x14 rl_h rl h set_h,
+    0x15 rl_l rl l set_l,
+    0x17 rl_a rl a set_a,
+    0x18 rr_b rr b set_b,
+    0x19 rr_c rr c set_c,
+    0x1A rr_d rr d set_d,
+    0x1B rr_e rr e set_e,
+    0x1C rr_h rr h set_h,
+    0x1D rr_l rr l set_l,
+    0x1F rr_a rr a set_a,
+    0x20 sla_b sla b set_b,
+    0x21 sla_c sla c set_c,
+    0x22 sla_d sla d set_d,
+    0x23 sla_e sla e set_e,
+    0x24 sla_h sla h set_h,
+    0x25 sla_l sla l set_l,
+    0x27 sla_a sla a set_a,
+    0x28 sra_b sra b set_b,
+    0x29 sra_c sra c set_c,
+    0x2A sra_d sra d set_d,
+    0x2B sra_e sra e set_e,
+    0x2C sra_h sra h set_h,
+    0x2D sra_l sra l set_l,
+    0x2F sra_a sra a set_a,
+    0x30 swap_b swap b set_b,
+    0x31 swap_c swap c set_c,
+    0x32 swap_d swap d set_d,
+    0x33 swap_e swap e set_e,
+    0x34 swap_h swap h set_h,
+    0x35 swap_l swap l set_l,
+    0x37 swap_a swap a set_a,
+    0x38 srl_b srl b set_b,
+    0x39 srl_c srl c set_c,
+    0x3A srl_d srl d set_d,
+    0x3B srl_e srl e set_e,
+    0x3C srl_h srl h set_h,
+    0x3D srl_l srl l set_l,
+    0x3F srl_a srl a set_a,
+}
+
+impl_shift_ptr_hl! {
+    0x06 rlc_ptr_hl rlc,
+    0x0E rrc_ptr_hl rrc,
+    0x16 rl_ptr_hl rl,
+    0x1E rr_ptr_hl rr,
+    0x26 sla_ptr_hl sla,
+    0x2E sra_ptr_hl sra,
+    0x36 swap_ptr_hl swap,
+    0x3E srl_ptr_hl srl,
+}
+
+fn update_flags(cpu: &mut Cpu, val: u8, carry: bool) -> u8 {
+    cpu.flags.update_zero(val == 0);
+    cpu.flags.clear_subtract();
+    cpu.flags.clear_half_carry();
+    cpu.flags.update_carry(carry);
+
+    val
+}
+
+fn rlc(cpu: &mut Cpu, val: u8) -> u8 {
+    update_flags(cpu, val.rotate_left(1), (val & 0b1000_0000) > 0)
+}
+
+fn rrc(cpu: &mut Cpu, val: u8) -> u8 {
+    update_flags(cpu, val.rotate_right(1), (val & 0b0000_0001) > 0)
 }
 
 fn rl(cpu: &mut Cpu, val: u8) -> u8 {
@@ -16,9 +120,93 @@ fn rl(cpu: &mut Cpu, val: u8) -> u8 {
     if cpu.flags.c() {
         new_val |= 0b0000_0001;
     }
-    cpu.flags.clear_half_carry();
-    cpu.flags.clear_subtract();
-    cpu.flags.update_carry(new_carry);
+    update_flags(cpu, new_val, new_carry)
+}
+
+fn rr(cpu: &mut Cpu, val: u8) -> u8 {
+    let new_carry = (val & 0b0000_0001) > 0;
+    let mut new_val = val.wrapping_shr(1);
+    if cpu.flags.c() {
+        new_val |= 0b1000_0000;
+    }
+    update_flags(cpu, new_val, new_carry)
+}
+
+fn sla(cpu: &mut Cpu, val: u8) -> u8 {
+    update_flags(cpu, val.wrapping_shl(1), (val & 0b1000_0000) > 0)
+}
+
+fn sra(cpu: &mut Cpu, val: u8) -> u8 {
+    // Arithmetic shift, bit 7 keeps its value
+    let new_val = (val >> 1) | (val & 0b1000_0000);
+    update_flags(cpu, new_val, (val & 0b0000_0001) > 0)
+}
+
+fn swap(cpu: &mut Cpu, val: u8) -> u8 {
+    update_flags(cpu, val.rotate_left(4), false)
+}
+
+fn srl(cpu: &mut Cpu, val: u8) -> u8 {
+    update_flags(cpu, val >> 1, (val & 0b0000_0001) > 0)
+}
+
+#[test]
+fn rotate_flags() {
+    let mut cpu = Cpu::default();
+
+    assert_eq!(rlc(&mut cpu, 0b1000_0001), 0b0000_0011);
+    assert!(cpu.flags.c() && !cpu.flags.z());
+    assert_eq!(rlc(&mut cpu, 0), 0);
+    assert!(!cpu.flags.c() && cpu.flags.z());
+
+    assert_eq!(rrc(&mut cpu, 0b0000_0001), 0b1000_0000);
+    assert!(cpu.flags.c() && !cpu.flags.z());
+
+    // RL and RR rotate through the carry flag that was set above
+    assert_eq!(rl(&mut cpu, 0b1000_0000), 0b0000_0001);
+    assert!(cpu.flags.c());
+    assert_eq!(rr(&mut cpu, 0b0000_0000), 0b1000_0000);
+    assert!(!cpu.flags.c());
+    assert_eq!(rr(&mut cpu, 0b0000_0001), 0);
+    assert!(cpu.flags.c() && cpu.flags.z());
+    assert!(!cpu.flags.n() && !cpu.flags.h());
+}
+
+#[test]
+fn shift_flags() {
+    let mut cpu = Cpu::default();
+
+    assert_eq!(sla(&mut cpu, 0b1000_0000), 0);
+    assert!(cpu.flags.c() && cpu.flags.z());
+
+    assert_eq!(sra(&mut cpu, 0b1000_0010), 0b1100_0001);
+    assert!(!cpu.flags.c() && !cpu.flags.z());
+
+    assert_eq!(srl(&mut cpu, 0b1000_0001), 0b0100_0000);
+    assert!(cpu.flags.c() && !cpu.flags.z());
+
+    assert_eq!(swap(&mut cpu, 0xF1), 0x1F);
+    assert!(!cpu.flags.c() && !cpu.flags.z());
+    assert_eq!(swap(&mut cpu, 0), 0);
+    assert!(cpu.flags.z());
+    assert!(!cpu.flags.n() && !cpu.flags.h());
+}
+
+#[test]
+fn shift_ptr_hl_timing() {
+    let mut memory = crate::opcodes::test_memory(&[0xCB, 0x06, 0xCB, 0x37]);
+    let mut cpu = crate::opcodes::test_cpu();
+    cpu.set_hl(0xC000);
+    memory.write_byte(0xC000, 0b1000_0000);
+
+    crate::opcodes::execute(&mut memory, &mut cpu);
+    assert_eq!(memory.read_byte(0xC000), 0b0000_0001);
+    assert!(cpu.flags.c());
+    assert_eq!(cpu.elapsed_cycles(), 16);
 
-    new_val
+    cpu.set_a(0xAB);
+    crate::opcodes::execute(&mut memory, &mut cpu);
+    assert_eq!(cpu.a(), 0xBA);
+    assert_eq!(cpu.program_counter(), 0x104);
+    assert_eq!(cpu.elapsed_cycles(), 16 + 8);
 }
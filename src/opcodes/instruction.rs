@@ -0,0 +1,395 @@
+use super::Op;
+use core::fmt;
+
+/// A single entry of the instruction tables. Both `INSTRUCTIONS` and `CB_INSTRUCTIONS` are
+/// generated by the `instructions!` macro, which derives the length from the operands so the
+/// name, size and timing of an instruction are all defined in one place.
+#[derive(Clone, Copy)]
+pub struct Instruction {
+    pub opcode: u8,
+    /// `true` for instructions in the 0xCB table
+    pub prefixed: bool,
+    pub mnemonic: Mnemonic,
+    pub operands: &'static [Operand],
+    /// The size in bytes, including the 0xCB prefix and any immediate operands
+    pub length: u8,
+    /// The number of clock cycles, or the number of cycles when a conditional branch is not taken
+    pub cycles: u8,
+    /// The number of clock cycles when a conditional branch is taken
+    pub branch_cycles: Option<u8>,
+    pub flags: FlagEffects,
+    pub execute: Op,
+}
+
+impl Instruction {
+    pub fn is_illegal(&self) -> bool {
+        self.mnemonic == Mnemonic::Illegal
+    }
+}
+
+impl fmt::Debug for Instruction {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Instruction")
+            .field("opcode", &self.opcode)
+            .field("prefixed", &self.prefixed)
+            .field("mnemonic", &self.mnemonic)
+            .field("operands", &self.operands)
+            .field("length", &self.length)
+            .field("cycles", &self.cycles)
+            .field("branch_cycles", &self.branch_cycles)
+            .field("flags", &self.flags)
+            .finish()
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{}", self.mnemonic)?;
+        for (index, operand) in self.operands.iter().enumerate() {
+            let separator = if index == 0 { " " } else { ", " };
+            write!(fmt, "{}{}", separator, operand)?;
+        }
+        Ok(())
+    }
+}
+
+/// Calculates the size of an instruction from its operands. Used by `instructions!`.
+pub const fn instruction_length(prefixed: bool, operands: &[Operand]) -> u8 {
+    let mut length = if prefixed { 2 } else { 1 };
+    let mut index = 0;
+    while index < operands.len() {
+        length += operands[index].immediate_bytes();
+        index += 1;
+    }
+    length
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Mnemonic {
+    Nop,
+    Stop,
+    Halt,
+    Di,
+    Ei,
+    Ld,
+    Ldh,
+    Push,
+    Pop,
+    Inc,
+    Dec,
+    Add,
+    Adc,
+    Sub,
+    Sbc,
+    And,
+    Xor,
+    Or,
+    Cp,
+    Daa,
+    Cpl,
+    Scf,
+    Ccf,
+    Rlca,
+    Rrca,
+    Rla,
+    Rra,
+    Jr,
+    Jp,
+    Call,
+    Ret,
+    Reti,
+    Rst,
+    Prefix,
+    Rlc,
+    Rrc,
+    Rl,
+    Rr,
+    Sla,
+    Sra,
+    Swap,
+    Srl,
+    Bit,
+    Res,
+    Set,
+    /// One of the 11 unused opcodes, which lock up the CPU on hardware
+    Illegal,
+}
+
+impl Mnemonic {
+    pub fn name(self) -> &'static str {
+        match self {
+            Mnemonic::Nop => "NOP",
+            Mnemonic::Stop => "STOP",
+            Mnemonic::Halt => "HALT",
+            Mnemonic::Di => "DI",
+            Mnemonic::Ei => "EI",
+            Mnemonic::Ld => "LD",
+            Mnemonic::Ldh => "LDH",
+            Mnemonic::Push => "PUSH",
+            Mnemonic::Pop => "POP",
+            Mnemonic::Inc => "INC",
+            Mnemonic::Dec => "DEC",
+            Mnemonic::Add => "ADD",
+            Mnemonic::Adc => "ADC",
+            Mnemonic::Sub => "SUB",
+            Mnemonic::Sbc => "SBC",
+            Mnemonic::And => "AND",
+            Mnemonic::Xor => "XOR",
+            Mnemonic::Or => "OR",
+            Mnemonic::Cp => "CP",
+            Mnemonic::Daa => "DAA",
+            Mnemonic::Cpl => "CPL",
+            Mnemonic::Scf => "SCF",
+            Mnemonic::Ccf => "CCF",
+            Mnemonic::Rlca => "RLCA",
+            Mnemonic::Rrca => "RRCA",
+            Mnemonic::Rla => "RLA",
+            Mnemonic::Rra => "RRA",
+            Mnemonic::Jr => "JR",
+            Mnemonic::Jp => "JP",
+            Mnemonic::Call => "CALL",
+            Mnemonic::Ret => "RET",
+            Mnemonic::Reti => "RETI",
+            Mnemonic::Rst => "RST",
+            Mnemonic::Prefix => "PREFIX CB",
+            Mnemonic::Rlc => "RLC",
+            Mnemonic::Rrc => "RRC",
+            Mnemonic::Rl => "RL",
+            Mnemonic::Rr => "RR",
+            Mnemonic::Sla => "SLA",
+            Mnemonic::Sra => "SRA",
+            Mnemonic::Swap => "SWAP",
+            Mnemonic::Srl => "SRL",
+            Mnemonic::Bit => "BIT",
+            Mnemonic::Res => "RES",
+            Mnemonic::Set => "SET",
+            Mnemonic::Illegal => "ILLEGAL",
+        }
+    }
+}
+
+impl fmt::Display for Mnemonic {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.write_str(self.name())
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Operand {
+    A,
+    B,
+    C,
+    D,
+    E,
+    H,
+    L,
+    AF,
+    BC,
+    DE,
+    HL,
+    SP,
+    /// (BC)
+    PtrBC,
+    /// (DE)
+    PtrDE,
+    /// (HL)
+    PtrHL,
+    /// (HL+), HL is incremented after the access
+    PtrHLIncrement,
+    /// (HL-), HL is decremented after the access
+    PtrHLDecrement,
+    /// (C), the address 0xFF00 + C
+    PtrC,
+    /// d8, an 8-bit immediate value
+    Data8,
+    /// d16, a 16-bit immediate value
+    Data16,
+    /// (a8), the address 0xFF00 + an 8-bit immediate value
+    PtrAddress8,
+    /// a16, the target of a jump or call
+    Address16,
+    /// (a16), the value at a 16-bit immediate address
+    PtrAddress16,
+    /// r8, a signed jump offset relative to the next instruction
+    Relative8,
+    /// r8, a signed offset added to SP
+    Signed8,
+    /// SP+r8
+    SPPlusSigned8,
+    /// NZ, branch if the zero flag is not set
+    CondNZ,
+    /// Z, branch if the zero flag is set
+    CondZ,
+    /// NC, branch if the carry flag is not set
+    CondNC,
+    /// C, branch if the carry flag is set
+    CondC,
+    /// The bit index of BIT, RES and SET
+    Bit(u8),
+    /// The target address of RST
+    Vector(u8),
+}
+
+impl Operand {
+    /// The number of bytes this operand takes up after the opcode.
+    pub const fn immediate_bytes(self) -> u8 {
+        match self {
+            Operand::Data8
+            | Operand::PtrAddress8
+            | Operand::Relative8
+            | Operand::Signed8
+            | Operand::SPPlusSigned8 => 1,
+            Operand::Data16 | Operand::Address16 | Operand::PtrAddress16 => 2,
+            _ => 0,
+        }
+    }
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let text = match self {
+            Operand::A => "A",
+            Operand::B => "B",
+            Operand::C => "C",
+            Operand::D => "D",
+            Operand::E => "E",
+            Operand::H => "H",
+            Operand::L => "L",
+            Operand::AF => "AF",
+            Operand::BC => "BC",
+            Operand::DE => "DE",
+            Operand::HL => "HL",
+            Operand::SP => "SP",
+            Operand::PtrBC => "(BC)",
+            Operand::PtrDE => "(DE)",
+            Operand::PtrHL => "(HL)",
+            Operand::PtrHLIncrement => "(HL+)",
+            Operand::PtrHLDecrement => "(HL-)",
+            Operand::PtrC => "(C)",
+            Operand::Data8 => "d8",
+            Operand::Data16 => "d16",
+            Operand::PtrAddress8 => "(a8)",
+            Operand::Address16 => "a16",
+            Operand::PtrAddress16 => "(a16)",
+            Operand::Relative8 | Operand::Signed8 => "r8",
+            Operand::SPPlusSigned8 => "SP+r8",
+            Operand::CondNZ => "NZ",
+            Operand::CondZ => "Z",
+            Operand::CondNC => "NC",
+            Operand::CondC => "C",
+            Operand::Bit(bit) => return write!(fmt, "{}", bit),
+            Operand::Vector(address) => return write!(fmt, "{:02X}H", address),
+        };
+        fmt.write_str(text)
+    }
+}
+
+/// How an instruction affects each of the flags.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct FlagEffects {
+    pub zero: FlagEffect,
+    pub subtract: FlagEffect,
+    pub half_carry: FlagEffect,
+    pub carry: FlagEffect,
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum FlagEffect {
+    /// `-`, the flag keeps its value
+    Unaffected,
+    /// `0`, the flag is always cleared
+    Reset,
+    /// `1`, the flag is always set
+    Set,
+    /// The flag depends on the result of the instruction
+    Affected,
+}
+
+#[test]
+fn instruction_tables_are_ordered() {
+    use super::{CB_INSTRUCTIONS, INSTRUCTIONS};
+
+    for (index, instruction) in INSTRUCTIONS.iter().enumerate() {
+        assert_eq!(instruction.opcode as usize, index);
+        assert!(!instruction.prefixed);
+    }
+    for (index, instruction) in CB_INSTRUCTIONS.iter().enumerate() {
+        assert_eq!(instruction.opcode as usize, index);
+        assert!(instruction.prefixed);
+        assert_eq!(instruction.length, 2);
+    }
+    let illegal = INSTRUCTIONS.iter().filter(|i| i.is_illegal()).count();
+    assert_eq!(illegal, 11);
+}
+
+#[test]
+fn instruction_metadata() {
+    use super::{CB_INSTRUCTIONS, INSTRUCTIONS};
+
+    let ld_bc_d16 = &INSTRUCTIONS[0x01];
+    assert_eq!(ld_bc_d16.to_string(), "LD BC, d16");
+    assert_eq!(ld_bc_d16.length, 3);
+    assert_eq!(ld_bc_d16.cycles, 12);
+    assert_eq!(ld_bc_d16.branch_cycles, None);
+
+    let jr_nz = &INSTRUCTIONS[0x20];
+    assert_eq!(jr_nz.to_string(), "JR NZ, r8");
+    assert_eq!(jr_nz.length, 2);
+    assert_eq!(jr_nz.cycles, 8);
+    assert_eq!(jr_nz.branch_cycles, Some(12));
+
+    let ld_hl_sp = &INSTRUCTIONS[0xF8];
+    assert_eq!(ld_hl_sp.to_string(), "LD HL, SP+r8");
+    assert_eq!(ld_hl_sp.flags.zero, FlagEffect::Reset);
+    assert_eq!(ld_hl_sp.flags.carry, FlagEffect::Affected);
+
+    assert_eq!(INSTRUCTIONS[0xFF].to_string(), "RST 38H");
+    assert_eq!(CB_INSTRUCTIONS[0x7E].to_string(), "BIT 7, (HL)");
+    assert_eq!(CB_INSTRUCTIONS[0x7E].cycles, 12);
+    assert_eq!(CB_INSTRUCTIONS[0x86].cycles, 16);
+}
+
+#[test]
+fn program_counter_advances_by_length() {
+    use super::{execute, CB_INSTRUCTIONS, INSTRUCTIONS};
+    use crate::{bus::FlatRam, Cpu};
+
+    let run = |program: &[u8]| {
+        let mut ram = FlatRam::default();
+        ram.0[0x100..0x100 + program.len()].copy_from_slice(program);
+        let mut cpu = Cpu::default();
+        cpu.set_program_counter(0x100);
+        execute(&mut ram, &mut cpu);
+        cpu.program_counter() - 0x100
+    };
+
+    for instruction in INSTRUCTIONS.iter() {
+        let control_flow = matches!(
+            instruction.mnemonic,
+            Mnemonic::Jr
+                | Mnemonic::Jp
+                | Mnemonic::Call
+                | Mnemonic::Ret
+                | Mnemonic::Reti
+                | Mnemonic::Rst
+                | Mnemonic::Prefix
+                | Mnemonic::Illegal
+        );
+        if !control_flow {
+            assert_eq!(
+                run(&[instruction.opcode]),
+                instruction.length as u16,
+                "{}",
+                instruction
+            );
+        }
+    }
+    for instruction in CB_INSTRUCTIONS.iter() {
+        assert_eq!(
+            run(&[0xCB, instruction.opcode]),
+            instruction.length as u16,
+            "{}",
+            instruction
+        );
+    }
+}
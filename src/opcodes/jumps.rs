@@ -1,110 +1,236 @@
-use crate::{Cpu, Memory};
+use crate::{Bus, Cpu};
 
-pub fn rst_38(memory: &mut Memory, cpu: &mut Cpu) {
-    // RST 38H 1 16 - - - -
-    cpu.clock_cycles(16);
-    cpu.increment_program_counter();
-    cpu.push_stack(memory, cpu.program_counter());
-    cpu.set_program_counter(38);
+fn nz(cpu: &Cpu) -> bool {
+    !cpu.flags.z()
 }
 
-pub fn call_z_a16(memory: &mut Memory, cpu: &mut Cpu) {
-    // 0xCC CALL Z, a16 3 24/12- - - -
-    cpu.increment_program_counter();
+fn z(cpu: &Cpu) -> bool {
+    cpu.flags.z()
+}
 
-    if cpu.flags.z() {
-        cpu.push_stack(memory, cpu.program_counter());
+fn nc(cpu: &Cpu) -> bool {
+    !cpu.flags.c()
+}
+
+fn c(cpu: &Cpu) -> bool {
+    cpu.flags.c()
+}
 
-        cpu.clock_cycles(24);
-        let address = memory.read_word(cpu.program_counter());
-        cpu.set_program_counter(address);
-    } else {
-        cpu.clock_cycles(16);
-        cpu.increment_program_counter();
-        cpu.increment_program_counter();
+fn relative_jump(cpu: &mut Cpu, offset: i8) {
+    // Casting an i8 to a u16 sign-extends it, so a wrapping add is a signed add
+    let program_counter = cpu.program_counter().wrapping_add(offset as u16);
+    cpu.set_program_counter(program_counter);
+}
+
+macro_rules! impl_jr_cc_r8 {
+    ($($code:tt $name:ident $condition:ident),* $(,)?) => {
+        $(
+            pub fn $name(memory: &mut dyn Bus, cpu: &mut Cpu) {
+                cpu.increment_program_counter();
+                let val = memory.read_byte(cpu.program_counter()) as i8;
+                cpu.increment_program_counter();
+
+                if $condition(cpu) {
+                    cpu.take_branch();
+                    relative_jump(cpu, val);
+                }
+            }
+        )*
     }
 }
 
-pub fn jr_nz_r8(memory: &mut Memory, cpu: &mut Cpu) {
-    // 0x20 JR NZ, r8 2 12/8 - - - -
-    cpu.increment_program_counter();
-    let val = memory.read_byte(cpu.program_counter()) as i8;
+impl_jr_cc_r8! {
+    0x20 jr_nz_r8 nz,
+    0x28 jr_z_r8 z,
+    0x30 jr_nc_r8 nc,
+    0x38 jr_c_r8 c,
+}
 
-    cpu.increment_program_counter();
-    if !cpu.flags.z() {
-        let program_counter = if val < 0 {
-            cpu.program_counter() - (val.abs() as u16)
-        } else {
-            cpu.program_counter() + (val as u16)
-        };
-        cpu.set_program_counter(program_counter);
-        cpu.clock_cycles(12);
-    } else {
-        cpu.clock_cycles(8);
+macro_rules! impl_jp_cc_a16 {
+    ($($code:tt $name:ident $condition:ident),* $(,)?) => {
+        $(
+            pub fn $name(memory: &mut dyn Bus, cpu: &mut Cpu) {
+                cpu.increment_program_counter();
+                let address = memory.read_word(cpu.program_counter());
+                cpu.increment_program_counter();
+                cpu.increment_program_counter();
+
+                if $condition(cpu) {
+                    cpu.take_branch();
+                    cpu.set_program_counter(address);
+                }
+            }
+        )*
     }
 }
 
-pub fn jr_z_r8(memory: &mut Memory, cpu: &mut Cpu) {
-    // 0x28 JR Z, r8 2 12/8 - - - -
-    cpu.increment_program_counter();
-    let val = memory.read_byte(cpu.program_counter()) as i8;
+impl_jp_cc_a16! {
+    0xC2 jp_nz_a16 nz,
+    0xCA jp_z_a16 z,
+    0xD2 jp_nc_a16 nc,
+    0xDA jp_c_a16 c,
+}
 
-    cpu.increment_program_counter();
-    if cpu.flags.z() {
-        let program_counter = if val < 0 {
-            cpu.program_counter() - (val.abs() as u16)
-        } else {
-            cpu.program_counter() + (val as u16)
-        };
-        cpu.set_program_counter(program_counter);
-        cpu.clock_cycles(12);
-    } else {
-        cpu.clock_cycles(8);
+macro_rules! impl_call_cc_a16 {
+    ($($code:tt $name:ident $condition:ident),* $(,)?) => {
+        $(
+            pub fn $name(memory: &mut dyn Bus, cpu: &mut Cpu) {
+                cpu.increment_program_counter();
+                let address = memory.read_word(cpu.program_counter());
+                cpu.increment_program_counter();
+                cpu.increment_program_counter();
+
+                if $condition(cpu) {
+                    cpu.take_branch();
+                    cpu.push_stack(memory, cpu.program_counter());
+                    cpu.set_program_counter(address);
+                }
+            }
+        )*
+    }
+}
+
+impl_call_cc_a16! {
+    0xC4 call_nz_a16 nz,
+    0xCC call_z_a16 z,
+    0xD4 call_nc_a16 nc,
+    0xDC call_c_a16 c,
+}
+
+macro_rules! impl_ret_cc {
+    ($($code:tt $name:ident $condition:ident),* $(,)?) => {
+        $(
+            pub fn $name(memory: &mut dyn Bus, cpu: &mut Cpu) {
+                cpu.increment_program_counter();
+                // The condition is checked in an internal cycle before popping the address
+                memory.tick(4);
+
+                if $condition(cpu) {
+                    cpu.take_branch();
+                    let address = cpu.pop_stack(memory);
+                    cpu.set_program_counter(address);
+                }
+            }
+        )*
     }
 }
 
-pub fn jr_r8(memory: &mut Memory, cpu: &mut Cpu) {
-    // 0x18 JR r8 2 12 - - - -
+impl_ret_cc! {
+    0xC0 ret_nz nz,
+    0xC8 ret_z z,
+    0xD0 ret_nc nc,
+    0xD8 ret_c c,
+}
+
+macro_rules! impl_rst {
+    ($($code:tt $name:ident $address:expr),* $(,)?) => {
+        $(
+            pub fn $name(memory: &mut dyn Bus, cpu: &mut Cpu) {
+                cpu.increment_program_counter();
+
+                cpu.push_stack(memory, cpu.program_counter());
+                cpu.set_program_counter($address);
+            }
+        )*
+    }
+}
+
+impl_rst! {
+    0xC7 rst_00 0x00,
+    0xCF rst_08 0x08,
+    0xD7 rst_10 0x10,
+    0xDF rst_18 0x18,
+    0xE7 rst_20 0x20,
+    0xEF rst_28 0x28,
+    0xF7 rst_30 0x30,
+    0xFF rst_38 0x38,
+}
+
+pub fn jr_r8(memory: &mut dyn Bus, cpu: &mut Cpu) {
     cpu.increment_program_counter();
     let val = memory.read_byte(cpu.program_counter()) as i8;
+    cpu.increment_program_counter();
+
+    relative_jump(cpu, val);
+}
 
+pub fn jp_a16(memory: &mut dyn Bus, cpu: &mut Cpu) {
     cpu.increment_program_counter();
-    let program_counter = if val < 0 {
-        cpu.program_counter() - (val.abs() as u16)
-    } else {
-        cpu.program_counter() + (val as u16)
-    };
-    cpu.set_program_counter(program_counter);
-    cpu.clock_cycles(12);
+
+    let address = memory.read_word(cpu.program_counter());
+
+    cpu.set_program_counter(address);
+}
+
+pub fn jp_hl(_: &mut dyn Bus, cpu: &mut Cpu) {
+    cpu.increment_program_counter();
+
+    cpu.set_program_counter(cpu.hl());
 }
 
-pub fn call_a16(memory: &mut Memory, cpu: &mut Cpu) {
-    // 0xCD CALL a16 3 24 - - - -
+pub fn call_a16(memory: &mut dyn Bus, cpu: &mut Cpu) {
     cpu.increment_program_counter();
-    cpu.clock_cycles(24);
 
     let address = memory.read_word(cpu.program_counter());
     cpu.increment_program_counter();
+    cpu.increment_program_counter();
 
     cpu.push_stack(memory, cpu.program_counter());
     cpu.set_program_counter(address);
 }
 
-pub fn jp_a16(memory: &mut Memory, cpu: &mut Cpu) {
-    // 0xC3 JP a16 3 16 - - - -
-    cpu.clock_cycles(16);
+pub fn ret(memory: &mut dyn Bus, cpu: &mut Cpu) {
     cpu.increment_program_counter();
 
-    let address = memory.read_word(cpu.program_counter());
-
+    let address = cpu.pop_stack(memory);
     cpu.set_program_counter(address);
 }
 
-pub fn ret(memory: &mut Memory, cpu: &mut Cpu) {
-    // 0xC9 RET 1 16 - - - -
+pub fn reti(memory: &mut dyn Bus, cpu: &mut Cpu) {
     cpu.increment_program_counter();
-    cpu.clock_cycles(16);
 
     let address = cpu.pop_stack(memory);
     cpu.set_program_counter(address);
+    cpu.enable_interrupts();
+}
+
+#[test]
+fn jump_family() {
+    let mut memory = super::test_memory(&[
+        0x20, 0x02, // 0x100 JR NZ, +2 (taken)
+        0x00, 0x00, //
+        0x38, 0xFA, // 0x104 JR C, -6 (not taken)
+        0xCD, 0x10, 0x01, // 0x106 CALL $0110
+        0xDA, 0x00, 0x00, // 0x109 JP C, $0000 (not taken)
+        0xFF, // 0x10C RST 38H
+        0x00, 0x00, 0x00, //
+        0xC0, // 0x110 RET NZ
+    ]);
+    let mut cpu = super::test_cpu();
+    cpu.set_sp(0xFFFE);
+
+    super::execute(&mut memory, &mut cpu);
+    assert_eq!(cpu.program_counter(), 0x104);
+    assert_eq!(cpu.elapsed_cycles(), 12);
+
+    super::execute(&mut memory, &mut cpu);
+    assert_eq!(cpu.program_counter(), 0x106);
+    assert_eq!(cpu.elapsed_cycles(), 12 + 8);
+
+    super::execute(&mut memory, &mut cpu);
+    assert_eq!(cpu.program_counter(), 0x110);
+    assert_eq!(cpu.stack_pointer(), 0xFFFC);
+    assert_eq!(memory.read_word(0xFFFC), 0x109);
+
+    super::execute(&mut memory, &mut cpu);
+    assert_eq!(cpu.program_counter(), 0x109);
+    assert_eq!(cpu.stack_pointer(), 0xFFFE);
+
+    super::execute(&mut memory, &mut cpu);
+    assert_eq!(cpu.program_counter(), 0x10C);
+
+    super::execute(&mut memory, &mut cpu);
+    assert_eq!(cpu.program_counter(), 0x38);
+    assert_eq!(memory.read_word(0xFFFC), 0x10D);
+    assert_eq!(cpu.elapsed_cycles(), 12 + 8 + 24 + 20 + 12 + 16);
 }
@@ -1,120 +1,211 @@
-use crate::{Cpu, Memory};
-
-pub fn ld_h_ptr_hl(memory: &mut Memory, cpu: &mut Cpu) {
-    // 0x66 LD H,(HL) 1 8 - - - -
-    cpu.increment_program_counter();
-    cpu.clock_cycles(18);
+use crate::{Bus, Cpu};
+
+macro_rules! impl_ld_r_r {
+    ($($code:tt $name:ident $set_field:ident $field:ident),* $(,)?) => {
+        $(
+            pub fn $name(_: &mut dyn Bus, cpu: &mut Cpu) {
+                cpu.increment_program_counter();
+
+                let val = cpu.$field();
+                cpu.$set_field(val);
+            }
+        )*
+    }
+}
 
-    let hl = cpu.hl();
-    let val = memory.read_byte(hl);
-    cpu.set_h(val);
+impl_ld_r_r! {
+    0x40 ld_b_b set_b b,
+    0x41 ld_b_c set_b c,
+    0x42 ld_b_d set_b d,
+    0x43 ld_b_e set_b e,
+    0x44 ld_b_h set_b h,
+    0x45 ld_b_l set_b l,
+    0x47 ld_b_a set_b a,
+    0x48 ld_c_b set_c b,
+    0x49 ld_c_c set_c c,
+    0x4A ld_c_d set_c d,
+    0x4B ld_c_e set_c e,
+    0x4C ld_c_h set_c h,
+    0x4D ld_c_l set_c l,
+    0x4F ld_c_a set_c a,
+    0x50 ld_d_b set_d b,
+    0x51 ld_d_c set_d c,
+    0x52 ld_d_d set_d d,
+    0x53 ld_d_e set_d e,
+    0x54 ld_d_h set_d h,
+    0x55 ld_d_l set_d l,
+    0x57 ld_d_a set_d a,
+    0x58 ld_e_b set_e b,
+    0x59 ld_e_c set_e c,
+    0x5A ld_e_d set_e d,
+    0x5B ld_e_e set_e e,
+    0x5C ld_e_h set_e h,
+    0x5D ld_e_l set_e l,
+    0x5F ld_e_a set_e a,
+    0x60 ld_h_b set_h b,
+    0x61 ld_h_c set_h c,
+    0x62 ld_h_d set_h d,
+    0x63 ld_h_e set_h e,
+    0x64 ld_h_h set_h h,
+    0x65 ld_h_l set_h l,
+    0x67 ld_h_a set_h a,
+    0x68 ld_l_b set_l b,
+    0x69 ld_l_c set_l c,
+    0x6A ld_l_d set_l d,
+    0x6B ld_l_e set_l e,
+    0x6C ld_l_h set_l h,
+    0x6D ld_l_l set_l l,
+    0x6F ld_l_a set_l a,
+    0x78 ld_a_b set_a b,
+    0x79 ld_a_c set_a c,
+    0x7A ld_a_d set_a d,
+    0x7B ld_a_e set_a e,
+    0x7C ld_a_h set_a h,
+    0x7D ld_a_l set_a l,
+    0x7F ld_a_a set_a a,
 }
 
-pub fn ld_ptr_hl_e(memory: &mut Memory, cpu: &mut Cpu) {
-    // LD (HL),E 1 8 - - - -
-    cpu.clock_cycles(8);
-    cpu.increment_program_counter();
+macro_rules! impl_ld_r_d8 {
+    ($($code:tt $name:ident $set_field:ident),* $(,)?) => {
+        $(
+            pub fn $name(memory: &mut dyn Bus, cpu: &mut Cpu) {
+                cpu.increment_program_counter();
 
-    let val = cpu.e();
-    let address = cpu.hl();
+                let val = memory.read_byte(cpu.program_counter());
+                cpu.increment_program_counter();
 
-    memory.write_byte(address, val);
+                cpu.$set_field(val);
+            }
+        )*
+    }
 }
 
-pub fn ld_ptr_a16_sp(memory: &mut Memory, cpu: &mut Cpu) {
-    // LD (a16), SP 3 20 - - - -
-    cpu.clock_cycles(20);
-    cpu.increment_program_counter();
-    let address = memory.read_word(cpu.program_counter());
-    cpu.increment_program_counter();
-    cpu.increment_program_counter();
+impl_ld_r_d8! {
+    0x06 ld_b_d8 set_b,
+    0x0E ld_c_d8 set_c,
+    0x16 ld_d_d8 set_d,
+    0x1E ld_e_d8 set_e,
+    0x26 ld_h_d8 set_h,
+    0x2E ld_l_d8 set_l,
+    0x3E ld_a_d8 set_a,
+}
 
-    memory.write_word(address, cpu.stack_pointer());
+macro_rules! impl_ld_r_ptr_hl {
+    ($($code:tt $name:ident $set_field:ident),* $(,)?) => {
+        $(
+            pub fn $name(memory: &mut dyn Bus, cpu: &mut Cpu) {
+                cpu.increment_program_counter();
+
+                let val = memory.read_byte(cpu.hl());
+                cpu.$set_field(val);
+            }
+        )*
+    }
 }
 
-pub fn ld_c_d8(memory: &mut Memory, cpu: &mut Cpu) {
-    // LD C, d8 2 8 - - - -
-    cpu.clock_cycles(8);
-    cpu.increment_program_counter();
+impl_ld_r_ptr_hl! {
+    0x46 ld_b_ptr_hl set_b,
+    0x4E ld_c_ptr_hl set_c,
+    0x56 ld_d_ptr_hl set_d,
+    0x5E ld_e_ptr_hl set_e,
+    0x66 ld_h_ptr_hl set_h,
+    0x6E ld_l_ptr_hl set_l,
+    0x7E ld_a_ptr_hl set_a,
+}
 
-    let val = memory.read_byte(cpu.program_counter());
-    cpu.increment_program_counter();
+macro_rules! impl_ld_ptr_hl_r {
+    ($($code:tt $name:ident $field:ident),* $(,)?) => {
+        $(
+            pub fn $name(memory: &mut dyn Bus, cpu: &mut Cpu) {
+                cpu.increment_program_counter();
+
+                let val = cpu.$field();
+                memory.write_byte(cpu.hl(), val);
+            }
+        )*
+    }
+}
 
-    cpu.set_c(val);
+impl_ld_ptr_hl_r! {
+    0x70 ld_ptr_hl_b b,
+    0x71 ld_ptr_hl_c c,
+    0x72 ld_ptr_hl_d d,
+    0x73 ld_ptr_hl_e e,
+    0x74 ld_ptr_hl_h h,
+    0x75 ld_ptr_hl_l l,
+    0x77 ld_ptr_hl_a a,
 }
 
-pub fn ld_ptr_a16_a(memory: &mut Memory, cpu: &mut Cpu) {
-    // 0xEA LD (a16), A 3 16 - - - -
-    cpu.increment_program_counter();
-    cpu.clock_cycles(16);
-    let address = memory.read_word(cpu.program_counter());
-    cpu.increment_program_counter();
-    cpu.increment_program_counter();
+macro_rules! impl_ld_rr_d16 {
+    ($($code:tt $name:ident $set_field:ident),* $(,)?) => {
+        $(
+            pub fn $name(memory: &mut dyn Bus, cpu: &mut Cpu) {
+                cpu.increment_program_counter();
 
-    memory.write_byte(address, cpu.a());
+                let val = memory.read_word(cpu.program_counter());
+                cpu.increment_program_counter();
+                cpu.increment_program_counter();
+
+                cpu.$set_field(val);
+            }
+        )*
+    }
+}
+
+impl_ld_rr_d16! {
+    0x01 ld_bc_d16 set_bc,
+    0x11 ld_de_d16 set_de,
+    0x21 ld_hl_d16 set_hl,
+    0x31 ld_sp_d16 set_sp,
 }
 
-pub fn ldh_ptr_a8_a(memory: &mut Memory, cpu: &mut Cpu) {
-    // 0xE0 LDH (a8), A 2 12 - - - -
+pub fn ld_ptr_hl_d8(memory: &mut dyn Bus, cpu: &mut Cpu) {
     cpu.increment_program_counter();
-    cpu.clock_cycles(12);
+
     let val = memory.read_byte(cpu.program_counter());
     cpu.increment_program_counter();
 
-    memory.write_byte(0xFF00 + (val as u16), cpu.a());
+    memory.write_byte(cpu.hl(), val);
 }
 
-pub fn ld_a_d8(memory: &mut Memory, cpu: &mut Cpu) {
-    // 0x3E LD A, d8 2 8 - - - -
-    cpu.increment_program_counter();
-    cpu.clock_cycles(8);
-
-    let value = memory.read_byte(cpu.program_counter());
+pub fn ld_ptr_bc_a(memory: &mut dyn Bus, cpu: &mut Cpu) {
     cpu.increment_program_counter();
 
-    cpu.set_a(value);
+    memory.write_byte(cpu.bc(), cpu.a());
 }
 
-pub fn ldh_a_ptr_a8(memory: &mut Memory, cpu: &mut Cpu) {
-    // 0xF0 LDH A, (a8) 2 12 - - - -
-    cpu.increment_program_counter();
-    cpu.clock_cycles(12);
-    let address = memory.read_byte(cpu.program_counter());
+pub fn ld_ptr_de_a(memory: &mut dyn Bus, cpu: &mut Cpu) {
     cpu.increment_program_counter();
 
-    let address = 0xFF00 + (address as u16);
-    let value = memory.read_byte(address);
-    cpu.set_a(value);
+    memory.write_byte(cpu.de(), cpu.a());
 }
 
-pub fn ld_sp_d16(memory: &mut Memory, cpu: &mut Cpu) {
-    // 0x31 LD SP, d16 3 12 - - -
+pub fn ld_a_ptr_bc(memory: &mut dyn Bus, cpu: &mut Cpu) {
     cpu.increment_program_counter();
-    cpu.clock_cycles(12);
 
-    let val = memory.read_word(cpu.program_counter());
-    cpu.increment_program_counter();
+    let val = memory.read_byte(cpu.bc());
+    cpu.set_a(val);
+}
+
+pub fn ld_a_ptr_de(memory: &mut dyn Bus, cpu: &mut Cpu) {
     cpu.increment_program_counter();
 
-    cpu.set_sp(val);
+    let val = memory.read_byte(cpu.de());
+    cpu.set_a(val);
 }
 
-pub fn ld_hl_d16(memory: &mut Memory, cpu: &mut Cpu) {
-    // 0x21 LD HL, d16 3 12 - - -
+pub fn ld_ptr_hl_plus_a(memory: &mut dyn Bus, cpu: &mut Cpu) {
     cpu.increment_program_counter();
-    cpu.clock_cycles(12);
 
-    let val = memory.read_word(cpu.program_counter());
-    cpu.increment_program_counter();
-    cpu.increment_program_counter();
+    let hl_address = cpu.hl();
+    let a = cpu.a();
 
-    cpu.set_hl(val);
+    memory.write_byte(hl_address, a);
+    cpu.set_hl(hl_address.wrapping_add(1));
 }
 
-pub fn ld_ptr_hl_minus_a(memory: &mut Memory, cpu: &mut Cpu) {
-    // 0x32 LD (HL -), A 1 8 - - - -
+pub fn ld_ptr_hl_minus_a(memory: &mut dyn Bus, cpu: &mut Cpu) {
     cpu.increment_program_counter();
-    cpu.clock_cycles(8);
     let address = cpu.hl();
     let val = cpu.a();
 
@@ -122,174 +213,134 @@ pub fn ld_ptr_hl_minus_a(memory: &mut Memory, cpu: &mut Cpu) {
     memory.write_byte(address, val);
 }
 
-pub fn ld_ptr_c_a(memory: &mut Memory, cpu: &mut Cpu) {
-    // 0xE2 LD (C), A 1 8 - - - -
+pub fn ld_a_ptr_hl_plus(memory: &mut dyn Bus, cpu: &mut Cpu) {
     cpu.increment_program_counter();
-    cpu.clock_cycles(8);
 
-    let c = cpu.c();
-    let val = cpu.a();
-    let addr = (0xff00) | (c as u16);
+    let address = cpu.hl();
+    let val = memory.read_byte(address);
 
-    memory.write_byte(addr, val);
+    cpu.set_hl(address.wrapping_add(1));
+    cpu.set_a(val);
 }
 
-pub fn ld_ptr_hl_a(memory: &mut Memory, cpu: &mut Cpu) {
-    // 0x77 LD (HL), A 1 8 - - - -
+pub fn ld_a_ptr_hl_minus(memory: &mut dyn Bus, cpu: &mut Cpu) {
     cpu.increment_program_counter();
-    cpu.clock_cycles(8);
 
     let address = cpu.hl();
-    let val = cpu.a();
+    let val = memory.read_byte(address);
 
-    memory.write_byte(address, val);
+    cpu.set_hl(address.wrapping_sub(1));
+    cpu.set_a(val);
 }
 
-pub fn ld_de_d16(memory: &mut Memory, cpu: &mut Cpu) {
-    // 0x11 LD DE, d16 3 12 - - - -
-    cpu.increment_program_counter();
-    cpu.clock_cycles(12);
-
-    let word = memory.read_word(cpu.program_counter());
+pub fn ld_ptr_a16_sp(memory: &mut dyn Bus, cpu: &mut Cpu) {
     cpu.increment_program_counter();
+    let address = memory.read_word(cpu.program_counter());
     cpu.increment_program_counter();
-
-    cpu.set_de(word);
-}
-
-pub fn ld_a_ptr_de(memory: &mut Memory, cpu: &mut Cpu) {
-    // 0x1A LD A, (DE) 1 8 - - - -
     cpu.increment_program_counter();
-    cpu.clock_cycles(8);
 
-    let val = memory.read_byte(cpu.de());
-    cpu.set_a(val);
+    memory.write_word(address, cpu.stack_pointer());
 }
 
-pub fn ld_c_a(_: &mut Memory, cpu: &mut Cpu) {
-    // 0x4F LD C, A 1 4 - - - -
+pub fn ld_ptr_a16_a(memory: &mut dyn Bus, cpu: &mut Cpu) {
     cpu.increment_program_counter();
-    cpu.clock_cycles(4);
-
-    cpu.set_a(cpu.c());
-}
-
-pub fn ld_b_d8(memory: &mut Memory, cpu: &mut Cpu) {
-    // 0x06 LD B, d8 2 8 - - - -
+    let address = memory.read_word(cpu.program_counter());
     cpu.increment_program_counter();
-    cpu.clock_cycles(8);
-
-    let val = memory.read_byte(cpu.program_counter());
     cpu.increment_program_counter();
 
-    cpu.set_b(val);
+    memory.write_byte(address, cpu.a());
 }
 
-pub fn ld_ptr_hl_plus_a(memory: &mut Memory, cpu: &mut Cpu) {
-    // 0x22 LD (HL+), A 1 8 - - - -
+pub fn ld_a_ptr_a16(memory: &mut dyn Bus, cpu: &mut Cpu) {
+    cpu.increment_program_counter();
+    let address = memory.read_word(cpu.program_counter());
     cpu.increment_program_counter();
-    cpu.clock_cycles(8);
-
-    let hl_address = cpu.hl();
-    let a = cpu.a();
-
-    memory.write_byte(hl_address, a);
-    cpu.set_hl(hl_address.wrapping_add(1));
-}
-
-pub fn ld_a_e(_: &mut Memory, cpu: &mut Cpu) {
-    // 0x7B LD A, E 1 4 - - - -
     cpu.increment_program_counter();
-    cpu.clock_cycles(4);
 
-    let e = cpu.e();
-    cpu.set_a(e);
+    let val = memory.read_byte(address);
+    cpu.set_a(val);
 }
 
-pub fn ld_l_d8(memory: &mut Memory, cpu: &mut Cpu) {
-    // 0x2E LD L, d8 2 8 - - - -
+pub fn ldh_ptr_a8_a(memory: &mut dyn Bus, cpu: &mut Cpu) {
     cpu.increment_program_counter();
-    cpu.clock_cycles(8);
-
     let val = memory.read_byte(cpu.program_counter());
     cpu.increment_program_counter();
 
-    cpu.set_l(val);
+    memory.write_byte(0xFF00 + (val as u16), cpu.a());
 }
 
-pub fn ld_h_a(_: &mut Memory, cpu: &mut Cpu) {
-    // 0x67 LD H, A 1 8 - - - -
+pub fn ldh_a_ptr_a8(memory: &mut dyn Bus, cpu: &mut Cpu) {
     cpu.increment_program_counter();
-    cpu.clock_cycles(8);
-
-    let val = cpu.a();
-    cpu.set_h(val);
-}
-
-pub fn ld_d_a(_: &mut Memory, cpu: &mut Cpu) {
-    // 0x57 LD D, A 1 4 - - - -
+    let address = memory.read_byte(cpu.program_counter());
     cpu.increment_program_counter();
-    cpu.clock_cycles(8);
 
-    let val = cpu.a();
-    cpu.set_d(val);
+    let address = 0xFF00 + (address as u16);
+    let value = memory.read_byte(address);
+    cpu.set_a(value);
 }
 
-pub fn ld_e_d8(memory: &mut Memory, cpu: &mut Cpu) {
-    // 0x1E LD E, d8 2 8 - - - -
-    cpu.increment_program_counter();
-    cpu.clock_cycles(8);
-
-    let val = memory.read_byte(cpu.program_counter());
+pub fn ld_ptr_c_a(memory: &mut dyn Bus, cpu: &mut Cpu) {
     cpu.increment_program_counter();
 
-    cpu.set_e(val);
-}
-
-pub fn ld_a_h(_: &mut Memory, cpu: &mut Cpu) {
-    // 0x7C LD A, H 1 4 - - - -
-    cpu.increment_program_counter();
-    cpu.clock_cycles(4);
+    let c = cpu.c();
+    let val = cpu.a();
+    let addr = (0xff00) | (c as u16);
 
-    let val = cpu.h();
-    cpu.set_h(val);
+    memory.write_byte(addr, val);
 }
 
-pub fn ld_d_d8(memory: &mut Memory, cpu: &mut Cpu) {
-    // 0x16 LD D, d8 2 8 - - - -
+pub fn ld_a_ptr_c(memory: &mut dyn Bus, cpu: &mut Cpu) {
     cpu.increment_program_counter();
-    cpu.clock_cycles(8);
 
-    let val = memory.read_byte(cpu.program_counter());
-    cpu.increment_program_counter();
+    let addr = (0xff00) | (cpu.c() as u16);
+    let val = memory.read_byte(addr);
 
-    cpu.set_d(val);
+    cpu.set_a(val);
 }
 
-pub fn ld_a_l(_: &mut Memory, cpu: &mut Cpu) {
-    // 0x7D LD A, L 1 4 - - - -
+pub fn ld_hl_sp_plus_r8(memory: &mut dyn Bus, cpu: &mut Cpu) {
     cpu.increment_program_counter();
-    cpu.clock_cycles(4);
 
-    let val = cpu.l();
-    cpu.set_a(val);
-}
-
-pub fn ld_a_b(_: &mut Memory, cpu: &mut Cpu) {
-    // 0x78 LD A, B 1 4 - - - -
+    let offset = memory.read_byte(cpu.program_counter()) as i8;
     cpu.increment_program_counter();
-    cpu.clock_cycles(4);
 
-    let val = cpu.b();
-    cpu.set_a(val);
+    let val = cpu.sp_plus_offset(offset);
+    cpu.set_hl(val);
 }
 
-pub fn ld_b_a(_: &mut Memory, cpu: &mut Cpu) {
-    // 0x47 LD B, A 1 4 - - - -
+pub fn ld_sp_hl(_: &mut dyn Bus, cpu: &mut Cpu) {
     cpu.increment_program_counter();
-    cpu.clock_cycles(4);
 
-    let val = cpu.a();
-    cpu.set_b(val);
+    cpu.set_sp(cpu.hl());
 }
 
+#[test]
+fn ld_register_family() {
+    let mut memory = super::test_memory(&[0x41, 0x7E, 0x2A, 0x36, 0x99, 0xF8, 0xFE]);
+    let mut cpu = super::test_cpu();
+    cpu.set_c(0x12);
+    cpu.set_hl(0xC000);
+    memory.write_byte(0xC000, 0x34);
+    cpu.set_sp(0xFFF8);
+
+    super::execute(&mut memory, &mut cpu);
+    assert_eq!(cpu.b(), 0x12);
+    assert_eq!(cpu.elapsed_cycles(), 4);
+
+    super::execute(&mut memory, &mut cpu);
+    assert_eq!(cpu.a(), 0x34);
+
+    super::execute(&mut memory, &mut cpu);
+    assert_eq!(cpu.a(), 0x34);
+    assert_eq!(cpu.hl(), 0xC001);
+
+    super::execute(&mut memory, &mut cpu);
+    assert_eq!(memory.read_byte(0xC001), 0x99);
+
+    super::execute(&mut memory, &mut cpu);
+    assert_eq!(cpu.hl(), 0xFFF6);
+    assert!(cpu.flags.h());
+    assert!(cpu.flags.c());
+    assert!(!cpu.flags.z());
+    assert_eq!(cpu.elapsed_cycles(), 4 + 8 + 8 + 12 + 12);
+}
@@ -1,26 +1,70 @@
-use crate::{Cpu, Memory};
+use crate::{Bus, Cpu};
 
-pub fn push_bc(memory: &mut Memory, cpu: &mut Cpu) {
-    // 0xC5 PUSH BC 1 16 - - - -
+macro_rules! impl_push_rr {
+    ($($code:tt $name:ident $field:ident),* $(,)?) => {
+        $(
+            pub fn $name(memory: &mut dyn Bus, cpu: &mut Cpu) {
+                cpu.increment_program_counter();
+
+                cpu.push_stack(memory, cpu.$field());
+            }
+        )*
+    }
+}
+
+impl_push_rr! {
+    0xC5 push_bc bc,
+    0xD5 push_de de,
+    0xE5 push_hl hl,
+    0xF5 push_af af,
+}
+
+macro_rules! impl_pop_rr {
+    ($($code:tt $name:ident $set_field:ident),* $(,)?) => {
+        $(
+            pub fn $name(memory: &mut dyn Bus, cpu: &mut Cpu) {
+                cpu.increment_program_counter();
+
+                let val = cpu.pop_stack(memory);
+                cpu.$set_field(val);
+            }
+        )*
+    }
+}
+
+impl_pop_rr! {
+    0xC1 pop_bc set_bc,
+    0xD1 pop_de set_de,
+    0xE1 pop_hl set_hl,
+    // The lower nibble of F is discarded
+    0xF1 pop_af set_af,
+}
+
+fn set_rotate_a_flags(cpu: &mut Cpu, carry: bool) {
+    cpu.flags.clear_zero();
+    cpu.flags.clear_half_carry();
+    cpu.flags.clear_subtract();
+    cpu.flags.update_carry(carry);
+}
+
+pub fn rlca(_: &mut dyn Bus, cpu: &mut Cpu) {
     cpu.increment_program_counter();
-    cpu.clock_cycles(16);
 
-    cpu.push_stack(memory, cpu.bc());
+    let val = cpu.a();
+    set_rotate_a_flags(cpu, (val & 0b1000_0000) > 0);
+    cpu.set_a(val.rotate_left(1));
 }
 
-pub fn pop_bc(memory: &mut Memory, cpu: &mut Cpu) {
-    // 0xC1 POP BC 1 12 - - - -
+pub fn rrca(_: &mut dyn Bus, cpu: &mut Cpu) {
     cpu.increment_program_counter();
-    cpu.clock_cycles(12);
 
-    let bc = cpu.pop_stack(memory);
-    cpu.set_bc(bc);
+    let val = cpu.a();
+    set_rotate_a_flags(cpu, (val & 0b0000_0001) > 0);
+    cpu.set_a(val.rotate_right(1));
 }
 
-pub fn rla(_: &mut Memory, cpu: &mut Cpu) {
-    // 0x17 RLA 1 4 0 0 0 C
+pub fn rla(_: &mut dyn Bus, cpu: &mut Cpu) {
     cpu.increment_program_counter();
-    cpu.clock_cycles(4);
 
     let val = cpu.a();
     let new_carry = (val & 0b1000_0000) > 0;
@@ -28,10 +72,264 @@ pub fn rla(_: &mut Memory, cpu: &mut Cpu) {
     if cpu.flags.c() {
         new_val |= 0b0000_0001;
     }
-    cpu.flags.clear_zero();
+    set_rotate_a_flags(cpu, new_carry);
+
+    cpu.set_a(new_val);
+}
+
+pub fn rra(_: &mut dyn Bus, cpu: &mut Cpu) {
+    cpu.increment_program_counter();
+
+    let val = cpu.a();
+    let new_carry = (val & 0b0000_0001) > 0;
+    let mut new_val = val.wrapping_shr(1);
+    if cpu.flags.c() {
+        new_val |= 0b1000_0000;
+    }
+    set_rotate_a_flags(cpu, new_carry);
+
+    cpu.set_a(new_val);
+}
+
+pub fn daa(_: &mut dyn Bus, cpu: &mut Cpu) {
+    cpu.increment_program_counter();
+
+    cpu.daa();
+}
+
+pub fn cpl(_: &mut dyn Bus, cpu: &mut Cpu) {
+    cpu.increment_program_counter();
+
+    cpu.set_a(!cpu.a());
+    cpu.flags.set_subtract();
+    cpu.flags.set_half_carry();
+}
+
+pub fn scf(_: &mut dyn Bus, cpu: &mut Cpu) {
+    cpu.increment_program_counter();
+
+    cpu.flags.clear_subtract();
     cpu.flags.clear_half_carry();
+    cpu.flags.set_carry();
+}
+
+pub fn ccf(_: &mut dyn Bus, cpu: &mut Cpu) {
+    cpu.increment_program_counter();
+
+    let carry = cpu.flags.c();
     cpu.flags.clear_subtract();
-    cpu.flags.update_carry(new_carry);
+    cpu.flags.clear_half_carry();
+    cpu.flags.update_carry(!carry);
+}
 
-    cpu.set_a(new_val);
+pub fn halt(memory: &mut dyn Bus, cpu: &mut Cpu) {
+    cpu.increment_program_counter();
+
+    if !cpu.interrupts_enabled() && memory.pending_interrupts() != 0 {
+        // HALT exits immediately, but the byte after it is executed twice
+        cpu.trigger_halt_bug();
+    } else {
+        cpu.halt();
+    }
+}
+
+pub fn stop(memory: &mut dyn Bus, cpu: &mut Cpu) {
+    // The byte following STOP is skipped
+    cpu.increment_program_counter();
+    cpu.increment_program_counter();
+
+    memory.reset_divider();
+    let joypad_lines = memory.joypad_lines();
+    cpu.stop(joypad_lines);
+}
+
+#[test]
+fn stack_family() {
+    let mut memory = super::test_memory(&[0xC5, 0xD1, 0xF5, 0xF1]);
+    let mut cpu = super::test_cpu();
+    cpu.set_sp(0xFFFE);
+    cpu.set_bc(0x1234);
+
+    super::execute(&mut memory, &mut cpu);
+    assert_eq!(cpu.stack_pointer(), 0xFFFC);
+    assert_eq!(memory.read_byte(0xFFFD), 0x12);
+    assert_eq!(memory.read_byte(0xFFFC), 0x34);
+
+    super::execute(&mut memory, &mut cpu);
+    assert_eq!(cpu.de(), 0x1234);
+    assert_eq!(cpu.stack_pointer(), 0xFFFE);
+
+    cpu.set_a(0xAB);
+    cpu.flags.set_zero();
+    cpu.flags.set_carry();
+    super::execute(&mut memory, &mut cpu);
+    assert_eq!(memory.read_word(0xFFFC), 0xAB90);
+
+    memory.write_word(0xFFFC, 0x12FF);
+    super::execute(&mut memory, &mut cpu);
+    assert_eq!(cpu.a(), 0x12);
+    assert_eq!(cpu.af(), 0x12F0, "the lower nibble of F is always zero");
+    assert_eq!(cpu.elapsed_cycles(), 16 + 12 + 16 + 12);
+}
+
+#[test]
+fn accumulator_family() {
+    let mut memory = super::test_memory(&[0x07, 0x1F, 0x27, 0x2F, 0x37, 0x3F]);
+    let mut cpu = super::test_cpu();
+
+    cpu.set_a(0b1000_0001);
+    super::execute(&mut memory, &mut cpu);
+    assert_eq!(cpu.a(), 0b0000_0011);
+    assert!(cpu.flags.c() && !cpu.flags.z());
+
+    super::execute(&mut memory, &mut cpu);
+    assert_eq!(cpu.a(), 0b1000_0001);
+    assert!(cpu.flags.c());
+
+    // 0x45 + 0x38 = 0x7D, which DAA turns back into BCD 83
+    cpu.set_a(0x45);
+    cpu.add_a(0x38, false);
+    super::execute(&mut memory, &mut cpu);
+    assert_eq!(cpu.a(), 0x83);
+    assert!(!cpu.flags.c() && !cpu.flags.h());
+
+    super::execute(&mut memory, &mut cpu);
+    assert_eq!(cpu.a(), 0x7C);
+    assert!(cpu.flags.n() && cpu.flags.h());
+
+    super::execute(&mut memory, &mut cpu);
+    assert!(cpu.flags.c() && !cpu.flags.n() && !cpu.flags.h());
+
+    super::execute(&mut memory, &mut cpu);
+    assert!(!cpu.flags.c());
+    assert_eq!(cpu.elapsed_cycles(), 6 * 4);
+}
+
+#[test]
+fn halt_until_interrupt() {
+    use crate::Interrupt;
+
+    let mut memory = super::test_memory(&[0x76, 0x00]);
+    let mut cpu = super::test_cpu();
+    memory.write_byte(crate::memory::INTERRUPT_ADDRESS, Interrupt::VBlank.mask());
+
+    super::execute(&mut memory, &mut cpu);
+    assert!(cpu.is_halted());
+    for _ in 0..10 {
+        super::execute(&mut memory, &mut cpu);
+    }
+    assert_eq!(cpu.program_counter(), 0x101);
+    assert_eq!(cpu.elapsed_cycles(), 11 * 4);
+
+    // With IME disabled the CPU wakes up and continues without servicing the interrupt
+    memory.request_interrupt(Interrupt::VBlank);
+    super::execute(&mut memory, &mut cpu);
+    assert!(!cpu.is_halted());
+    assert_eq!(cpu.program_counter(), 0x102);
+}
+
+#[test]
+fn halt_bug() {
+    use crate::Interrupt;
+
+    let mut memory = super::test_memory(&[0x76, 0x3C, 0x00]);
+    let mut cpu = super::test_cpu();
+    memory.write_byte(crate::memory::INTERRUPT_ADDRESS, Interrupt::Timer.mask());
+    memory.request_interrupt(Interrupt::Timer);
+    cpu.set_a(0);
+
+    super::execute(&mut memory, &mut cpu);
+    assert!(!cpu.is_halted());
+
+    // INC A is executed twice
+    super::execute(&mut memory, &mut cpu);
+    assert_eq!(cpu.program_counter(), 0x101);
+    super::execute(&mut memory, &mut cpu);
+    assert_eq!(cpu.program_counter(), 0x102);
+    assert_eq!(cpu.a(), 2);
+}
+
+#[test]
+fn stop_resets_divider() {
+    let mut memory = super::test_memory(&[0x10, 0x00, 0x00]);
+    let mut cpu = super::test_cpu();
+
+    super::execute(&mut memory, &mut cpu);
+    assert!(cpu.is_stopped());
+    assert_eq!(cpu.program_counter(), 0x102);
+    assert_eq!(memory.read_byte(0xFF04), 0);
+
+    // The test video never presses any buttons, and DIV stays reset while stopped
+    for _ in 0..100 {
+        super::execute(&mut memory, &mut cpu);
+    }
+    assert!(cpu.is_stopped());
+    assert_eq!(cpu.program_counter(), 0x102);
+    assert_eq!(memory.read_byte(0xFF04), 0);
+}
+
+/// A bus with P1 input lines that the test controls.
+#[cfg(test)]
+struct JoypadBus {
+    ram: crate::bus::FlatRam,
+    lines: u8,
+}
+
+#[cfg(test)]
+impl JoypadBus {
+    fn new(lines: u8) -> JoypadBus {
+        let mut ram = crate::bus::FlatRam::default();
+        ram.0[0x100] = 0x10;
+        JoypadBus { ram, lines }
+    }
+}
+
+#[cfg(test)]
+impl Bus for JoypadBus {
+    fn read_byte(&mut self, address: u16) -> u8 {
+        self.ram.read_byte(address)
+    }
+
+    fn write_byte(&mut self, address: u16, value: u8) {
+        self.ram.write_byte(address, value)
+    }
+
+    fn tick(&mut self, _cycles: u16) {}
+
+    fn joypad_lines(&mut self) -> u8 {
+        self.lines
+    }
+}
+
+#[test]
+fn stop_wakes_on_joypad_edge() {
+    let mut bus = JoypadBus::new(0x0F);
+    let mut cpu = super::test_cpu();
+
+    super::execute(&mut bus, &mut cpu);
+    super::execute(&mut bus, &mut cpu);
+    assert!(cpu.is_stopped());
+
+    bus.lines = 0x0E;
+    super::execute(&mut bus, &mut cpu);
+    assert!(!cpu.is_stopped());
+}
+
+#[test]
+fn stop_ignores_held_button() {
+    // The button that led to STOP is still held down
+    let mut bus = JoypadBus::new(0x0E);
+    let mut cpu = super::test_cpu();
+
+    for _ in 0..10 {
+        super::execute(&mut bus, &mut cpu);
+    }
+    assert!(cpu.is_stopped());
+
+    bus.lines = 0x0F;
+    super::execute(&mut bus, &mut cpu);
+    assert!(cpu.is_stopped());
+    bus.lines = 0x0E;
+    super::execute(&mut bus, &mut cpu);
+    assert!(!cpu.is_stopped());
 }
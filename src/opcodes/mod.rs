@@ -1,318 +1,625 @@
-use crate::{Cpu, Memory};
+use crate::{Bus, Cpu};
 
-#[macro_export]
-macro_rules! unimpl_opcode {
-    ($opcode:tt $($descr:tt)*) => {{
-        fn unimplemented_opcode(_memory: &mut Memory, cpu: &mut Cpu) {
-            panic!("Not implemented:\n${:04X}: 0x{:02X} {}", cpu.program_counter(), $opcode, stringify!($($descr)*));
-        }
-        (stringify!($opcode, $($descr)*), unimplemented_opcode)
-    }}
+/// Builds an instruction table. Every entry is written as
+/// `opcode Mnemonic [operands] cycles [Z N H C] function`, where conditional branches list their
+/// cycles as `taken/not_taken` and the flags use `-`, `0`, `1` or the flag's letter.
+macro_rules! instructions {
+    (prefixed: $prefixed:expr; $(
+        $opcode:literal $mnemonic:ident [$($operand:ident $(($arg:literal))?),*]
+        $cycles:literal $(/ $not_taken:literal)?
+        [$z:tt $n:tt $h:tt $c:tt]
+        $execute:path
+    ),* $(,)?) => {
+        [$(
+            $crate::opcodes::Instruction {
+                opcode: $opcode,
+                prefixed: $prefixed,
+                mnemonic: $crate::opcodes::Mnemonic::$mnemonic,
+                operands: &[$($crate::opcodes::Operand::$operand $(($arg))?),*],
+                length: $crate::opcodes::instruction_length(
+                    $prefixed,
+                    &[$($crate::opcodes::Operand::$operand $(($arg))?),*],
+                ),
+                cycles: instructions!(@cycles $cycles $(/ $not_taken)?),
+                branch_cycles: instructions!(@branch_cycles $cycles $(/ $not_taken)?),
+                flags: $crate::opcodes::FlagEffects {
+                    zero: instructions!(@flag $z),
+                    subtract: instructions!(@flag $n),
+                    half_carry: instructions!(@flag $h),
+                    carry: instructions!(@flag $c),
+                },
+                execute: $execute,
+            },
+        )*]
+    };
+    (@cycles $cycles:literal) => { $cycles };
+    (@cycles $taken:literal / $not_taken:literal) => { $not_taken };
+    (@branch_cycles $cycles:literal) => { None };
+    (@branch_cycles $taken:literal / $not_taken:literal) => { Some($taken) };
+    (@flag -) => { $crate::opcodes::FlagEffect::Unaffected };
+    (@flag 0) => { $crate::opcodes::FlagEffect::Reset };
+    (@flag 1) => { $crate::opcodes::FlagEffect::Set };
+    (@flag $flag:ident) => { $crate::opcodes::FlagEffect::Affected };
 }
 
+mod disassembler;
 mod extended;
+mod instruction;
 
 mod add;
+mod and;
 mod cmp;
 mod jumps;
 mod loads;
 mod misc;
+mod or;
 mod sub;
 mod xor;
 
-pub fn execute(memory: &mut Memory, cpu: &mut Cpu) {
-    let instruction = memory.read_byte(cpu.program_counter());
-    let (_name, function) = INSTRUCTIONS[instruction as usize];
+#[cfg(test)]
+mod single_step;
+
+pub use self::{
+    disassembler::{disassemble, disassemble_with, Disassembly},
+    extended::INSTRUCTIONS as CB_INSTRUCTIONS,
+    instruction::{instruction_length, FlagEffect, FlagEffects, Instruction, Mnemonic, Operand},
+};
+
+/// Executes a single instruction, or services a pending interrupt instead if interrupts are
+/// enabled.
+///
+/// Every memory access takes one M-cycle (4 clock cycles) and ticks the bus the moment it happens,
+/// so the rest of the hardware is up to date when a register like LY is read.
+pub fn execute<B: Bus>(bus: &mut B, cpu: &mut Cpu) {
+    let mut memory = Clocked { bus, cycles: 0 };
+    step(&mut memory, cpu);
+    cpu.clock_cycles(memory.cycles);
+}
+
+fn step(memory: &mut Clocked, cpu: &mut Cpu) {
+    if cpu.is_stopped() {
+        // STOP halts the system clock, so the rest of the hardware and DIV do not advance. The
+        // cycles are still counted so the frontend keeps polling the input every frame.
+        memory.cycles += 4;
+        let lines = memory.joypad_lines();
+        if cpu.update_joypad_lines(lines) {
+            cpu.resume();
+        }
+        return;
+    }
+    if cpu.is_halted() {
+        // Keep consuming cycles so the rest of the hardware advances until an interrupt shows up
+        if memory.pending_interrupts() == 0 {
+            memory.tick(4);
+            return;
+        }
+        cpu.resume();
+    }
+
+    if crate::interrupt::dispatch(memory, cpu) {
+        return;
+    }
+
+    // EI only takes effect after the instruction that follows it, unless that instruction is DI
+    let enable_interrupts = cpu.interrupts_scheduled();
+
+    let opcode = memory.read_byte(cpu.program_counter());
+    run(&INSTRUCTIONS[opcode as usize], memory, cpu);
+
+    if enable_interrupts && cpu.interrupts_scheduled() {
+        cpu.enable_interrupts();
+    }
+}
+
+/// Runs a single instruction and clocks its cycles. Conditional instructions call
+/// `Cpu::take_branch` to consume their `branch_cycles` instead.
+fn run(instruction: &Instruction, memory: &mut Clocked, cpu: &mut Cpu) {
+    (instruction.execute)(memory, cpu);
+
+    let cycles = match instruction.branch_cycles {
+        Some(branch_cycles) if cpu.branch_taken() => branch_cycles,
+        _ => instruction.cycles,
+    } as u16;
+    // The cycles that were not spent on memory accesses are internal to the CPU. The CB
+    // instructions have none, so the prefix entry never has cycles left.
+    memory.tick(cycles.saturating_sub(memory.cycles));
+}
+
+/// Wraps the bus for the duration of a single `execute`, and ticks it by an M-cycle before every
+/// read and write. Instructions call `Bus::tick` themselves for internal cycles that have to
+/// happen before a memory access.
+struct Clocked<'a> {
+    bus: &'a mut dyn Bus,
+    /// The cycles that have elapsed since the start of `execute`
+    cycles: u16,
+}
+
+impl Bus for Clocked<'_> {
+    fn read_byte(&mut self, address: u16) -> u8 {
+        self.tick(4);
+        self.bus.read_byte(address)
+    }
+
+    fn write_byte(&mut self, address: u16, value: u8) {
+        self.tick(4);
+        self.bus.write_byte(address, value);
+    }
+
+    fn tick(&mut self, cycles: u16) {
+        self.cycles += cycles;
+        self.bus.tick(cycles);
+    }
+
+    fn joypad_lines(&mut self) -> u8 {
+        self.bus.joypad_lines()
+    }
+
+    fn reset_divider(&mut self) {
+        self.bus.reset_divider()
+    }
+
+    // The interrupt registers and the divider are wired to the CPU directly, these do not take any
+    // cycles.
+    fn request_interrupt(&mut self, interrupt: crate::Interrupt) {
+        self.bus.request_interrupt(interrupt)
+    }
 
-    (function)(memory, cpu);
+    fn acknowledge_interrupt(&mut self, interrupt: crate::Interrupt) {
+        self.bus.acknowledge_interrupt(interrupt)
+    }
+
+    fn pending_interrupts(&mut self) -> u8 {
+        self.bus.pending_interrupts()
+    }
+}
+
+/// Looks up the instruction at the given address, following the 0xCB prefix into
+/// `CB_INSTRUCTIONS`.
+pub fn decode(memory: &mut dyn Bus, address: u16) -> &'static Instruction {
+    let opcode = memory.read_byte(address);
+    if opcode == 0xCB {
+        let opcode = memory.read_byte(address.wrapping_add(1));
+        &CB_INSTRUCTIONS[opcode as usize]
+    } else {
+        &INSTRUCTIONS[opcode as usize]
+    }
 }
 
-pub type Op = fn(memory: &mut Memory, cpu: &mut Cpu);
+pub type Op = fn(memory: &mut dyn Bus, cpu: &mut Cpu);
 
-fn nop(_: &mut Memory, cpu: &mut Cpu) {
-    cpu.clock_cycles(4);
+fn nop(_: &mut dyn Bus, cpu: &mut Cpu) {
     cpu.increment_program_counter();
 }
 
-const INSTRUCTIONS: [(&str, Op); 256] = [
+fn illegal(memory: &mut dyn Bus, cpu: &mut Cpu) {
+    let opcode = memory.read_byte(cpu.program_counter());
+    panic!(
+        "Illegal opcode:\n${:04X}: 0x{:02X}",
+        cpu.program_counter(),
+        opcode
+    );
+}
+
+/// The base instruction set. 0xCB is the prefix for `CB_INSTRUCTIONS`.
+pub static INSTRUCTIONS: [Instruction; 256] = instructions! { prefixed: false;
     // 0x0x
-    ("nop", nop),
-    unimpl_opcode!(0x01 LD BC,d16 3 12 - - - -),
-    unimpl_opcode!(0x02 LD (BC),A 1 8 - - - -),
-    ("INC bc", add::inc_bc),
-    ("0x04 INC B", add::inc_b),
-    ("0x05 DEC B", sub::dec_b),
-    ("0x06 LD B, d8", loads::ld_b_d8),
-    unimpl_opcode!(0x07 RLCA 1 4 0 0 0 C),
-    ("LD (a16), sp", loads::ld_ptr_a16_sp),
-    unimpl_opcode!(0x09 ADD HL,BC 1 8 - 0 H C),
-    unimpl_opcode!(0x0A LD A,(BC) 1 8 - - - -),
-    ("DEC bc", sub::dec_bc),
-    ("INC c", add::inc_c),
-    ("DEC c", sub::dec_c),
-    ("LD c, d8", loads::ld_c_d8),
-    unimpl_opcode!(0x0F RRCA 1 4 0 0 0 C),
+    0x00 Nop [] 4 [- - - -] nop,
+    0x01 Ld [BC, Data16] 12 [- - - -] loads::ld_bc_d16,
+    0x02 Ld [PtrBC, A] 8 [- - - -] loads::ld_ptr_bc_a,
+    0x03 Inc [BC] 8 [- - - -] add::inc_bc,
+    0x04 Inc [B] 4 [Z 0 H -] add::inc_b,
+    0x05 Dec [B] 4 [Z 1 H -] sub::dec_b,
+    0x06 Ld [B, Data8] 8 [- - - -] loads::ld_b_d8,
+    0x07 Rlca [] 4 [0 0 0 C] misc::rlca,
+    0x08 Ld [PtrAddress16, SP] 20 [- - - -] loads::ld_ptr_a16_sp,
+    0x09 Add [HL, BC] 8 [- 0 H C] add::add_hl_bc,
+    0x0A Ld [A, PtrBC] 8 [- - - -] loads::ld_a_ptr_bc,
+    0x0B Dec [BC] 8 [- - - -] sub::dec_bc,
+    0x0C Inc [C] 4 [Z 0 H -] add::inc_c,
+    0x0D Dec [C] 4 [Z 1 H -] sub::dec_c,
+    0x0E Ld [C, Data8] 8 [- - - -] loads::ld_c_d8,
+    0x0F Rrca [] 4 [0 0 0 C] misc::rrca,
     // 0x1x
-    unimpl_opcode!(0x10 STOP 0 1 4 - - - -), // actually 1 byte size https://stackoverflow.com/a/41422692
-    ("LD DE, d16", loads::ld_de_d16),
-    unimpl_opcode!(0x12 LD (DE),A 1 8 - - - -),
-    ("0x13 INC DE", add::inc_de),
-    unimpl_opcode!(0x14 INC D 1 4 Z 0 H -),
-    ("0x15 DEC D", sub::dec_d),
-    ("0x16 LD D, d8", loads::ld_d_d8),
-    ("0x17 RLA", misc::rla),
-    ("JR r8", jumps::jr_r8),
-    unimpl_opcode!(0x19 ADD HL,DE 1 8 - 0 H C),
-    ("LD A, (DE)", loads::ld_a_ptr_de),
-    unimpl_opcode!(0x1B DEC DE 1 8 - - - -),
-    unimpl_opcode!(0x1C INC E 1 4 Z 0 H -),
-    ("0x1D DEC E", sub::dec_e),
-    ("0x1E LD E, d8", loads::ld_e_d8),
-    unimpl_opcode!(0x1F RRA 1 4 0 0 0 C),
+    0x10 Stop [Data8] 4 [- - - -] misc::stop,
+    0x11 Ld [DE, Data16] 12 [- - - -] loads::ld_de_d16,
+    0x12 Ld [PtrDE, A] 8 [- - - -] loads::ld_ptr_de_a,
+    0x13 Inc [DE] 8 [- - - -] add::inc_de,
+    0x14 Inc [D] 4 [Z 0 H -] add::inc_d,
+    0x15 Dec [D] 4 [Z 1 H -] sub::dec_d,
+    0x16 Ld [D, Data8] 8 [- - - -] loads::ld_d_d8,
+    0x17 Rla [] 4 [0 0 0 C] misc::rla,
+    0x18 Jr [Relative8] 12 [- - - -] jumps::jr_r8,
+    0x19 Add [HL, DE] 8 [- 0 H C] add::add_hl_de,
+    0x1A Ld [A, PtrDE] 8 [- - - -] loads::ld_a_ptr_de,
+    0x1B Dec [DE] 8 [- - - -] sub::dec_de,
+    0x1C Inc [E] 4 [Z 0 H -] add::inc_e,
+    0x1D Dec [E] 4 [Z 1 H -] sub::dec_e,
+    0x1E Ld [E, Data8] 8 [- - - -] loads::ld_e_d8,
+    0x1F Rra [] 4 [0 0 0 C] misc::rra,
     // 0x2x
-    ("0x20 JR NZ,r8", jumps::jr_nz_r8),
-    ("LD HL, d16", loads::ld_hl_d16),
-    ("0x22 LD (HL+), A", loads::ld_ptr_hl_plus_a),
-    ("0x23 INC HL", add::inc_hl),
-    ("0x24 INC H", add::inc_h),
-    unimpl_opcode!(0x25 DEC H 1 4 Z 1 H -),
-    unimpl_opcode!(0x26 LD H,d8 2 8 - - - -),
-    unimpl_opcode!(0x27 DAA 1 4 Z - 0 C),
-    ("JR z, r8", jumps::jr_z_r8),
-    unimpl_opcode!(0x29 ADD HL,HL 1 8 - 0 H C),
-    unimpl_opcode!(0x2A LD A,(HL+) 1 8 - - - -),
-    unimpl_opcode!(0x2B DEC HL 1 8 - - - -),
-    unimpl_opcode!(0x2C INC L 1 4 Z 0 H -),
-    unimpl_opcode!(0x2D DEC L 1 4 Z 1 H -),
-    ("0x2E LD L, d8", loads::ld_l_d8),
-    unimpl_opcode!(0x2F CPL 1 4 - 1 1 -),
+    0x20 Jr [CondNZ, Relative8] 12/8 [- - - -] jumps::jr_nz_r8,
+    0x21 Ld [HL, Data16] 12 [- - - -] loads::ld_hl_d16,
+    0x22 Ld [PtrHLIncrement, A] 8 [- - - -] loads::ld_ptr_hl_plus_a,
+    0x23 Inc [HL] 8 [- - - -] add::inc_hl,
+    0x24 Inc [H] 4 [Z 0 H -] add::inc_h,
+    0x25 Dec [H] 4 [Z 1 H -] sub::dec_h,
+    0x26 Ld [H, Data8] 8 [- - - -] loads::ld_h_d8,
+    0x27 Daa [] 4 [Z - 0 C] misc::daa,
+    0x28 Jr [CondZ, Relative8] 12/8 [- - - -] jumps::jr_z_r8,
+    0x29 Add [HL, HL] 8 [- 0 H C] add::add_hl_hl,
+    0x2A Ld [A, PtrHLIncrement] 8 [- - - -] loads::ld_a_ptr_hl_plus,
+    0x2B Dec [HL] 8 [- - - -] sub::dec_hl,
+    0x2C Inc [L] 4 [Z 0 H -] add::inc_l,
+    0x2D Dec [L] 4 [Z 1 H -] sub::dec_l,
+    0x2E Ld [L, Data8] 8 [- - - -] loads::ld_l_d8,
+    0x2F Cpl [] 4 [- 1 1 -] misc::cpl,
     // 0x3x
-    unimpl_opcode!(0x30 JR NC,r8 2 12/8 - - - -),
-    ("LD sp, d16", loads::ld_sp_d16),
-    ("LD (HL-), A", loads::ld_ptr_hl_minus_a),
-    unimpl_opcode!(0x33 INC SP 1 8 - - - -),
-    unimpl_opcode!(0x34 INC (HL) 1 12 Z 0 H -),
-    unimpl_opcode!(0x35 DEC (HL) 1 12 Z 1 H -),
-    unimpl_opcode!(0x36 LD (HL),d8 2 12 - - - -),
-    unimpl_opcode!(0x37 SCF 1 4 - 0 0 1),
-    unimpl_opcode!(0x38 JR C,r8 2 12/8 - - - -),
-    unimpl_opcode!(0x39 ADD HL,SP 1 8 - 0 H C),
-    unimpl_opcode!(0x3A LD A,(HL-) 1 8 - - - -),
-    unimpl_opcode!(0x3B DEC SP 1 8 - - - -),
-    unimpl_opcode!(0x3C INC A 1 4 Z 0 H -),
-    ("0x3D DEC A", sub::dec_a),
-    ("LD a, d8", loads::ld_a_d8),
-    unimpl_opcode!(0x3F CCF14- 0 0 C),
+    0x30 Jr [CondNC, Relative8] 12/8 [- - - -] jumps::jr_nc_r8,
+    0x31 Ld [SP, Data16] 12 [- - - -] loads::ld_sp_d16,
+    0x32 Ld [PtrHLDecrement, A] 8 [- - - -] loads::ld_ptr_hl_minus_a,
+    0x33 Inc [SP] 8 [- - - -] add::inc_sp,
+    0x34 Inc [PtrHL] 12 [Z 0 H -] add::inc_ptr_hl,
+    0x35 Dec [PtrHL] 12 [Z 1 H -] sub::dec_ptr_hl,
+    0x36 Ld [PtrHL, Data8] 12 [- - - -] loads::ld_ptr_hl_d8,
+    0x37 Scf [] 4 [- 0 0 1] misc::scf,
+    0x38 Jr [CondC, Relative8] 12/8 [- - - -] jumps::jr_c_r8,
+    0x39 Add [HL, SP] 8 [- 0 H C] add::add_hl_sp,
+    0x3A Ld [A, PtrHLDecrement] 8 [- - - -] loads::ld_a_ptr_hl_minus,
+    0x3B Dec [SP] 8 [- - - -] sub::dec_sp,
+    0x3C Inc [A] 4 [Z 0 H -] add::inc_a,
+    0x3D Dec [A] 4 [Z 1 H -] sub::dec_a,
+    0x3E Ld [A, Data8] 8 [- - - -] loads::ld_a_d8,
+    0x3F Ccf [] 4 [- 0 0 C] misc::ccf,
     // 0x4x
-    unimpl_opcode!(0x40 LD B,B 1 4 - - - -),
-    unimpl_opcode!(0x41 LD B,C 1 4 - - - -),
-    unimpl_opcode!(0x42 LD B,D 1 4 - - - -),
-    unimpl_opcode!(0x43 LD B,E 1 4 - - - -),
-    unimpl_opcode!(0x44 LD B,H 1 4 - - - -),
-    unimpl_opcode!(0x45 LD B,L 1 4 - - - -),
-    unimpl_opcode!(0x46 LD B,(HL)1 8 - - - -),
-    ("0x47 LD B, A", loads::ld_b_a),
-    unimpl_opcode!(0x48 LD C,B 1 4 - - - -),
-    unimpl_opcode!(0x49 LD C,C 1 4 - - - -),
-    unimpl_opcode!(0x4A LD C,D 1 4 - - - -),
-    unimpl_opcode!(0x4B LD C,E 1 4 - - - -),
-    unimpl_opcode!(0x4C LD C,H 1 4 - - - -),
-    unimpl_opcode!(0x4D LD C,L 1 4 - - - -),
-    unimpl_opcode!(0x4E LD C,(HL) 1 8 - - - -),
-    ("0x4F LD C,A", loads::ld_c_a),
+    0x40 Ld [B, B] 4 [- - - -] loads::ld_b_b,
+    0x41 Ld [B, C] 4 [- - - -] loads::ld_b_c,
+    0x42 Ld [B, D] 4 [- - - -] loads::ld_b_d,
+    0x43 Ld [B, E] 4 [- - - -] loads::ld_b_e,
+    0x44 Ld [B, H] 4 [- - - -] loads::ld_b_h,
+    0x45 Ld [B, L] 4 [- - - -] loads::ld_b_l,
+    0x46 Ld [B, PtrHL] 8 [- - - -] loads::ld_b_ptr_hl,
+    0x47 Ld [B, A] 4 [- - - -] loads::ld_b_a,
+    0x48 Ld [C, B] 4 [- - - -] loads::ld_c_b,
+    0x49 Ld [C, C] 4 [- - - -] loads::ld_c_c,
+    0x4A Ld [C, D] 4 [- - - -] loads::ld_c_d,
+    0x4B Ld [C, E] 4 [- - - -] loads::ld_c_e,
+    0x4C Ld [C, H] 4 [- - - -] loads::ld_c_h,
+    0x4D Ld [C, L] 4 [- - - -] loads::ld_c_l,
+    0x4E Ld [C, PtrHL] 8 [- - - -] loads::ld_c_ptr_hl,
+    0x4F Ld [C, A] 4 [- - - -] loads::ld_c_a,
     // 0x5x
-    unimpl_opcode!(0x50 LD D,B 1 4 - - - -),
-    unimpl_opcode!(0x51 LD D,C 1 4 - - - -),
-    unimpl_opcode!(0x52 LD D,D 1 4 - - - -),
-    unimpl_opcode!(0x53 LD D,E 1 4 - - - -),
-    unimpl_opcode!(0x54 LD D,H 1 4 - - - -),
-    unimpl_opcode!(0x55 LD D,L 1 4 - - - -),
-    unimpl_opcode!(0x56 LD D,(HL) 1 8 - - - -),
-    ("0x57 LD D, A", loads::ld_d_a),
-    unimpl_opcode!(0x58 LD E,B 1 4 - - - -),
-    unimpl_opcode!(0x59 LD E,C 1 4 - - - -),
-    unimpl_opcode!(0x5A LD E,D 1 4 - - - -),
-    unimpl_opcode!(0x5B LD E,E 1 4 - - - -),
-    unimpl_opcode!(0x5C LD E,H 1 4 - - - -),
-    unimpl_opcode!(0x5D LD E,L 1 4 - - - -),
-    unimpl_opcode!(0x5E LD E,(HL) 1 8 - - - -),
-    unimpl_opcode!(0x5F LD E,A 1 4 - - - -),
+    0x50 Ld [D, B] 4 [- - - -] loads::ld_d_b,
+    0x51 Ld [D, C] 4 [- - - -] loads::ld_d_c,
+    0x52 Ld [D, D] 4 [- - - -] loads::ld_d_d,
+    0x53 Ld [D, E] 4 [- - - -] loads::ld_d_e,
+    0x54 Ld [D, H] 4 [- - - -] loads::ld_d_h,
+    0x55 Ld [D, L] 4 [- - - -] loads::ld_d_l,
+    0x56 Ld [D, PtrHL] 8 [- - - -] loads::ld_d_ptr_hl,
+    0x57 Ld [D, A] 4 [- - - -] loads::ld_d_a,
+    0x58 Ld [E, B] 4 [- - - -] loads::ld_e_b,
+    0x59 Ld [E, C] 4 [- - - -] loads::ld_e_c,
+    0x5A Ld [E, D] 4 [- - - -] loads::ld_e_d,
+    0x5B Ld [E, E] 4 [- - - -] loads::ld_e_e,
+    0x5C Ld [E, H] 4 [- - - -] loads::ld_e_h,
+    0x5D Ld [E, L] 4 [- - - -] loads::ld_e_l,
+    0x5E Ld [E, PtrHL] 8 [- - - -] loads::ld_e_ptr_hl,
+    0x5F Ld [E, A] 4 [- - - -] loads::ld_e_a,
     // 0x6x
-    unimpl_opcode!(0x60 LD H,B 1 4 - - - -),
-    unimpl_opcode!(0x61 LD H,C 1 4 - - - -),
-    unimpl_opcode!(0x62 LD H,D 1 4 - - - -),
-    unimpl_opcode!(0x63 LD H,E 1 4 - - - -),
-    unimpl_opcode!(0x64 LD H,H 1 4 - - - -),
-    unimpl_opcode!(0x65 LD H,L 1 4 - - - -),
-    ("LD h, (hl)", loads::ld_h_ptr_hl),
-    ("0x67 LD H, A", loads::ld_h_a),
-    unimpl_opcode!(0x68 LD L,B 1 4 - - - -),
-    unimpl_opcode!(0x69 LD L,C 1 4 - - - -),
-    unimpl_opcode!(0x6A LD L,D 1 4 - - - -),
-    unimpl_opcode!(0x6B LD L,E 1 4 - - - -),
-    unimpl_opcode!(0x6C LD L,H 1 4 - - - -),
-    unimpl_opcode!(0x6D LD L,L 1 4 - - - -),
-    unimpl_opcode!(0x6E LD L,(HL) 1 8 - - - -),
-    unimpl_opcode!(0x6F LD L,A 1 4 - - - -),
+    0x60 Ld [H, B] 4 [- - - -] loads::ld_h_b,
+    0x61 Ld [H, C] 4 [- - - -] loads::ld_h_c,
+    0x62 Ld [H, D] 4 [- - - -] loads::ld_h_d,
+    0x63 Ld [H, E] 4 [- - - -] loads::ld_h_e,
+    0x64 Ld [H, H] 4 [- - - -] loads::ld_h_h,
+    0x65 Ld [H, L] 4 [- - - -] loads::ld_h_l,
+    0x66 Ld [H, PtrHL] 8 [- - - -] loads::ld_h_ptr_hl,
+    0x67 Ld [H, A] 4 [- - - -] loads::ld_h_a,
+    0x68 Ld [L, B] 4 [- - - -] loads::ld_l_b,
+    0x69 Ld [L, C] 4 [- - - -] loads::ld_l_c,
+    0x6A Ld [L, D] 4 [- - - -] loads::ld_l_d,
+    0x6B Ld [L, E] 4 [- - - -] loads::ld_l_e,
+    0x6C Ld [L, H] 4 [- - - -] loads::ld_l_h,
+    0x6D Ld [L, L] 4 [- - - -] loads::ld_l_l,
+    0x6E Ld [L, PtrHL] 8 [- - - -] loads::ld_l_ptr_hl,
+    0x6F Ld [L, A] 4 [- - - -] loads::ld_l_a,
     // 0x7x
-    unimpl_opcode!(0x70 LD (HL),B18- - - -),
-    unimpl_opcode!(0x71 LD (HL),C18- - - -),
-    unimpl_opcode!(0x72 LD (HL),D18- - - -),
-    ("LD (hl), e", loads::ld_ptr_hl_e),
-    unimpl_opcode!(0x74 LD (HL),H18- - - -),
-    unimpl_opcode!(0x75 LD (HL),L18- - - -),
-    unimpl_opcode!(0x76 HALT14- - - -),
-    ("LD (HL),A", loads::ld_ptr_hl_a),
-    ("0x78 LD A, B", loads::ld_a_b),
-    unimpl_opcode!(0x79 LD A,C14- - - -),
-    unimpl_opcode!(0x7A LD A,D14- - - -),
-    ("0x7B LD A, E", loads::ld_a_e),
-    ("0x7C LD A, H", loads::ld_a_h),
-    ("0x7D LD A, L", loads::ld_a_l),
-    unimpl_opcode!(0x7E LD A,(HL)18- - - -),
-    unimpl_opcode!(0x7F LD A,A14- - - -),
+    0x70 Ld [PtrHL, B] 8 [- - - -] loads::ld_ptr_hl_b,
+    0x71 Ld [PtrHL, C] 8 [- - - -] loads::ld_ptr_hl_c,
+    0x72 Ld [PtrHL, D] 8 [- - - -] loads::ld_ptr_hl_d,
+    0x73 Ld [PtrHL, E] 8 [- - - -] loads::ld_ptr_hl_e,
+    0x74 Ld [PtrHL, H] 8 [- - - -] loads::ld_ptr_hl_h,
+    0x75 Ld [PtrHL, L] 8 [- - - -] loads::ld_ptr_hl_l,
+    0x76 Halt [] 4 [- - - -] misc::halt,
+    0x77 Ld [PtrHL, A] 8 [- - - -] loads::ld_ptr_hl_a,
+    0x78 Ld [A, B] 4 [- - - -] loads::ld_a_b,
+    0x79 Ld [A, C] 4 [- - - -] loads::ld_a_c,
+    0x7A Ld [A, D] 4 [- - - -] loads::ld_a_d,
+    0x7B Ld [A, E] 4 [- - - -] loads::ld_a_e,
+    0x7C Ld [A, H] 4 [- - - -] loads::ld_a_h,
+    0x7D Ld [A, L] 4 [- - - -] loads::ld_a_l,
+    0x7E Ld [A, PtrHL] 8 [- - - -] loads::ld_a_ptr_hl,
+    0x7F Ld [A, A] 4 [- - - -] loads::ld_a_a,
     // 0x8x
-    unimpl_opcode!(0x80 ADD A,B14Z 0 H C),
-    unimpl_opcode!(0x81 ADD A,C14Z 0 H C),
-    unimpl_opcode!(0x82 ADD A,D14Z 0 H C),
-    ("ADD a, e", add::add_a_e),
-    unimpl_opcode!(0x84 ADD A,H 1 4 Z 0 H C),
-    unimpl_opcode!(0x85 ADD A,L 1 4 Z 0 H C),
-    ("0x86 ADD A, (HL)", add::add_a_ptr_hl),
-    unimpl_opcode!(0x87 ADD A,A 1 4 Z 0 H C),
-    ("ADC a, b", add::adc_a_b),
-    ("ADC a, c", add::adc_a_c),
-    unimpl_opcode!(0x8A ADC A,D 1 4 Z 0 H C),
-    unimpl_opcode!(0x8B ADC A,E 1 4 Z 0 H C),
-    unimpl_opcode!(0x8C ADC A,H 1 4 Z 0 H C),
-    unimpl_opcode!(0x8D ADC A,L 1 4 Z 0 H C),
-    unimpl_opcode!(0x8E ADC A,(HL) 1 8 Z 0 H C),
-    unimpl_opcode!(0x8F ADC A,A 1 4 Z 0 H C),
+    0x80 Add [A, B] 4 [Z 0 H C] add::add_a_b,
+    0x81 Add [A, C] 4 [Z 0 H C] add::add_a_c,
+    0x82 Add [A, D] 4 [Z 0 H C] add::add_a_d,
+    0x83 Add [A, E] 4 [Z 0 H C] add::add_a_e,
+    0x84 Add [A, H] 4 [Z 0 H C] add::add_a_h,
+    0x85 Add [A, L] 4 [Z 0 H C] add::add_a_l,
+    0x86 Add [A, PtrHL] 8 [Z 0 H C] add::add_a_ptr_hl,
+    0x87 Add [A, A] 4 [Z 0 H C] add::add_a_a,
+    0x88 Adc [A, B] 4 [Z 0 H C] add::adc_a_b,
+    0x89 Adc [A, C] 4 [Z 0 H C] add::adc_a_c,
+    0x8A Adc [A, D] 4 [Z 0 H C] add::adc_a_d,
+    0x8B Adc [A, E] 4 [Z 0 H C] add::adc_a_e,
+    0x8C Adc [A, H] 4 [Z 0 H C] add::adc_a_h,
+    0x8D Adc [A, L] 4 [Z 0 H C] add::adc_a_l,
+    0x8E Adc [A, PtrHL] 8 [Z 0 H C] add::adc_a_ptr_hl,
+    0x8F Adc [A, A] 4 [Z 0 H C] add::adc_a_a,
     // 0x9x
-    ("0x90 SUB B", sub::sub_b),
-    unimpl_opcode!(0x91 SUB C14Z 1 H C),
-    unimpl_opcode!(0x92 SUB D14Z 1 H C),
-    unimpl_opcode!(0x93 SUB E14Z 1 H C),
-    unimpl_opcode!(0x94 SUB H14Z 1 H C),
-    unimpl_opcode!(0x95 SUB L14Z 1 H C),
-    unimpl_opcode!(0x96 SUB (HL)18Z 1 H C),
-    unimpl_opcode!(0x97 SUB A14Z 1 H C),
-    unimpl_opcode!(0x98 SBC A,B14Z 1 H C),
-    unimpl_opcode!(0x99 SBC A,C14Z 1 H C),
-    unimpl_opcode!(0x9A SBC A,D14Z 1 H C),
-    unimpl_opcode!(0x9B SBC A,E14Z 1 H C),
-    unimpl_opcode!(0x9C SBC A,H14Z 1 H C),
-    unimpl_opcode!(0x9D SBC A,L14Z 1 H C),
-    unimpl_opcode!(0x9E SBC A,(HL)18Z 1 H C),
-    unimpl_opcode!(0x9F SBC A,A14Z 1 H C),
+    0x90 Sub [B] 4 [Z 1 H C] sub::sub_b,
+    0x91 Sub [C] 4 [Z 1 H C] sub::sub_c,
+    0x92 Sub [D] 4 [Z 1 H C] sub::sub_d,
+    0x93 Sub [E] 4 [Z 1 H C] sub::sub_e,
+    0x94 Sub [H] 4 [Z 1 H C] sub::sub_h,
+    0x95 Sub [L] 4 [Z 1 H C] sub::sub_l,
+    0x96 Sub [PtrHL] 8 [Z 1 H C] sub::sub_ptr_hl,
+    0x97 Sub [A] 4 [Z 1 H C] sub::sub_a,
+    0x98 Sbc [A, B] 4 [Z 1 H C] sub::sbc_a_b,
+    0x99 Sbc [A, C] 4 [Z 1 H C] sub::sbc_a_c,
+    0x9A Sbc [A, D] 4 [Z 1 H C] sub::sbc_a_d,
+    0x9B Sbc [A, E] 4 [Z 1 H C] sub::sbc_a_e,
+    0x9C Sbc [A, H] 4 [Z 1 H C] sub::sbc_a_h,
+    0x9D Sbc [A, L] 4 [Z 1 H C] sub::sbc_a_l,
+    0x9E Sbc [A, PtrHL] 8 [Z 1 H C] sub::sbc_a_ptr_hl,
+    0x9F Sbc [A, A] 4 [Z 1 H C] sub::sbc_a_a,
     // 0xAx
-    unimpl_opcode!(0xA0 AND B 1 4 Z 0 1 0),
-    unimpl_opcode!(0xA1 AND C 1 4 Z 0 1 0),
-    unimpl_opcode!(0xA2 AND D 1 4 Z 0 1 0),
-    unimpl_opcode!(0xA3 AND E 1 4 Z 0 1 0),
-    unimpl_opcode!(0xA4 AND H 1 4 Z 0 1 0),
-    unimpl_opcode!(0xA5 AND L 1 4 Z 0 1 0),
-    unimpl_opcode!(0xA6 AND (HL) 1 8 Z 0 1 0),
-    unimpl_opcode!(0xA7 AND A 1 4 Z 0 1 0),
-    unimpl_opcode!(0xA8 XOR B 1 4 Z 0 0 0),
-    unimpl_opcode!(0xA9 XOR C 1 4 Z 0 0 0),
-    unimpl_opcode!(0xAA XOR D 14 Z  0 0 0),
-    unimpl_opcode!(0xAB XOR E 1 4 Z 0 0 0),
-    unimpl_opcode!(0xAC XOR H 1 4 Z 0 0 0),
-    unimpl_opcode!(0xAD XOR L 1 4 Z 0 0 0),
-    unimpl_opcode!(0xAE XOR (HL) 1 8 Z 0 0 0),
-    ("XOR a", xor::xor_a),
+    0xA0 And [B] 4 [Z 0 1 0] and::and_b,
+    0xA1 And [C] 4 [Z 0 1 0] and::and_c,
+    0xA2 And [D] 4 [Z 0 1 0] and::and_d,
+    0xA3 And [E] 4 [Z 0 1 0] and::and_e,
+    0xA4 And [H] 4 [Z 0 1 0] and::and_h,
+    0xA5 And [L] 4 [Z 0 1 0] and::and_l,
+    0xA6 And [PtrHL] 8 [Z 0 1 0] and::and_ptr_hl,
+    0xA7 And [A] 4 [Z 0 1 0] and::and_a,
+    0xA8 Xor [B] 4 [Z 0 0 0] xor::xor_b,
+    0xA9 Xor [C] 4 [Z 0 0 0] xor::xor_c,
+    0xAA Xor [D] 4 [Z 0 0 0] xor::xor_d,
+    0xAB Xor [E] 4 [Z 0 0 0] xor::xor_e,
+    0xAC Xor [H] 4 [Z 0 0 0] xor::xor_h,
+    0xAD Xor [L] 4 [Z 0 0 0] xor::xor_l,
+    0xAE Xor [PtrHL] 8 [Z 0 0 0] xor::xor_ptr_hl,
+    0xAF Xor [A] 4 [Z 0 0 0] xor::xor_a,
     // 0xBx
-    unimpl_opcode!(0xB0 OR B14Z 0 0 0),
-    unimpl_opcode!(0xB1 OR C14Z 0 0 0),
-    unimpl_opcode!(0xB2 OR D14Z 0 0 0),
-    unimpl_opcode!(0xB3 OR E14Z 0 0 0),
-    unimpl_opcode!(0xB4 OR H14Z 0 0 0),
-    unimpl_opcode!(0xB5 OR L14Z 0 0 0),
-    unimpl_opcode!(0xB6 OR (HL)18Z 0 0 0),
-    unimpl_opcode!(0xB7 OR A14Z 0 0 0),
-    unimpl_opcode!(0xB8 CP B14Z 1 H C),
-    unimpl_opcode!(0xB9 CP C14Z 1 H C),
-    unimpl_opcode!(0xBA CP D14Z 1 H C),
-    unimpl_opcode!(0xBB CP E14Z 1 H C),
-    unimpl_opcode!(0xBC CP H14Z 1 H C),
-    unimpl_opcode!(0xBD CP L14Z 1 H C),
-    ("0xBE CP (HL)", cmp::cp_ptr_hl),
-    unimpl_opcode!(0xBF CP A14Z 1 H C),
+    0xB0 Or [B] 4 [Z 0 0 0] or::or_b,
+    0xB1 Or [C] 4 [Z 0 0 0] or::or_c,
+    0xB2 Or [D] 4 [Z 0 0 0] or::or_d,
+    0xB3 Or [E] 4 [Z 0 0 0] or::or_e,
+    0xB4 Or [H] 4 [Z 0 0 0] or::or_h,
+    0xB5 Or [L] 4 [Z 0 0 0] or::or_l,
+    0xB6 Or [PtrHL] 8 [Z 0 0 0] or::or_ptr_hl,
+    0xB7 Or [A] 4 [Z 0 0 0] or::or_a,
+    0xB8 Cp [B] 4 [Z 1 H C] cmp::cp_b,
+    0xB9 Cp [C] 4 [Z 1 H C] cmp::cp_c,
+    0xBA Cp [D] 4 [Z 1 H C] cmp::cp_d,
+    0xBB Cp [E] 4 [Z 1 H C] cmp::cp_e,
+    0xBC Cp [H] 4 [Z 1 H C] cmp::cp_h,
+    0xBD Cp [L] 4 [Z 1 H C] cmp::cp_l,
+    0xBE Cp [PtrHL] 8 [Z 1 H C] cmp::cp_ptr_hl,
+    0xBF Cp [A] 4 [Z 1 H C] cmp::cp_a,
     // 0xCx
-    unimpl_opcode!(0xC0 RET NZ 1 20/8 - - - -),
-    ("0xC1 POP BC", misc::pop_bc),
-    unimpl_opcode!(0xC2 JP NZ,a16 3 16/12 - - - -),
-    ("JP a16", jumps::jp_a16),
-    unimpl_opcode!(0xC4 CALL NZ,a16 3 24/12 - - - -),
-    ("0xC5 PUSH BC", misc::push_bc),
-    unimpl_opcode!(0xC6 ADD A,d8 2 8 Z 0 H C),
-    unimpl_opcode!(0xC7 RST 00H 1 16 - - - -),
-    unimpl_opcode!(0xC8 RET Z 1 20/8 - - - -),
-    ("0xC9 RET", jumps::ret),
-    unimpl_opcode!(0xCA JP Z,a16 3 16/12 - - - -),
-    ("CB", extended::execute),
-    ("CALL z, a16", jumps::call_z_a16),
-    ("CALL a16", jumps::call_a16),
-    ("ADC a, d8", add::adc_a_d8),
-    unimpl_opcode!(0xCF RST 08H 1 16 - - - -),
+    0xC0 Ret [CondNZ] 20/8 [- - - -] jumps::ret_nz,
+    0xC1 Pop [BC] 12 [- - - -] misc::pop_bc,
+    0xC2 Jp [CondNZ, Address16] 16/12 [- - - -] jumps::jp_nz_a16,
+    0xC3 Jp [Address16] 16 [- - - -] jumps::jp_a16,
+    0xC4 Call [CondNZ, Address16] 24/12 [- - - -] jumps::call_nz_a16,
+    0xC5 Push [BC] 16 [- - - -] misc::push_bc,
+    0xC6 Add [A, Data8] 8 [Z 0 H C] add::add_a_d8,
+    0xC7 Rst [Vector(0x00)] 16 [- - - -] jumps::rst_00,
+    0xC8 Ret [CondZ] 20/8 [- - - -] jumps::ret_z,
+    0xC9 Ret [] 16 [- - - -] jumps::ret,
+    0xCA Jp [CondZ, Address16] 16/12 [- - - -] jumps::jp_z_a16,
+    0xCB Prefix [] 4 [- - - -] extended::execute,
+    0xCC Call [CondZ, Address16] 24/12 [- - - -] jumps::call_z_a16,
+    0xCD Call [Address16] 24 [- - - -] jumps::call_a16,
+    0xCE Adc [A, Data8] 8 [Z 0 H C] add::adc_a_d8,
+    0xCF Rst [Vector(0x08)] 16 [- - - -] jumps::rst_08,
     // 0xDx
-    unimpl_opcode!(0xD0 RET NC 1 20/8 - - - -),
-    unimpl_opcode!(0xD1 POP DE 1 12 - - - -),
-    unimpl_opcode!(0xD2 JP NC,a16 3 16/12 - - - -),
-    unimpl_opcode!(0xD3),
-    unimpl_opcode!(0xD4 CALL NC,a16 3 24/12 - - - -),
-    unimpl_opcode!(0xD5 PUSH DE 1 16 - - - -),
-    unimpl_opcode!(0xD6 SUB d8 28 Z 1 H C),
-    unimpl_opcode!(0xD7 RST 10H 1 16 - - - -),
-    unimpl_opcode!(0xD8 RET C 1 20/8 - - - -),
-    unimpl_opcode!(0xD9 RETI 1 16 - - - -),
-    unimpl_opcode!(0xDA JP C,a16 3 16/12 - - - -),
-    unimpl_opcode!(0xDB),
-    unimpl_opcode!(0xDC CALL C,a16 3 24/12 - - - -),
-    unimpl_opcode!(0xDD),
-    unimpl_opcode!(0xDE SBC A,d8 2 8 Z 1 H C),
-    unimpl_opcode!(0xDF RST 18H 1 16 - - - -),
+    0xD0 Ret [CondNC] 20/8 [- - - -] jumps::ret_nc,
+    0xD1 Pop [DE] 12 [- - - -] misc::pop_de,
+    0xD2 Jp [CondNC, Address16] 16/12 [- - - -] jumps::jp_nc_a16,
+    0xD3 Illegal [] 4 [- - - -] illegal,
+    0xD4 Call [CondNC, Address16] 24/12 [- - - -] jumps::call_nc_a16,
+    0xD5 Push [DE] 16 [- - - -] misc::push_de,
+    0xD6 Sub [Data8] 8 [Z 1 H C] sub::sub_d8,
+    0xD7 Rst [Vector(0x10)] 16 [- - - -] jumps::rst_10,
+    0xD8 Ret [CondC] 20/8 [- - - -] jumps::ret_c,
+    0xD9 Reti [] 16 [- - - -] jumps::reti,
+    0xDA Jp [CondC, Address16] 16/12 [- - - -] jumps::jp_c_a16,
+    0xDB Illegal [] 4 [- - - -] illegal,
+    0xDC Call [CondC, Address16] 24/12 [- - - -] jumps::call_c_a16,
+    0xDD Illegal [] 4 [- - - -] illegal,
+    0xDE Sbc [A, Data8] 8 [Z 1 H C] sub::sbc_a_d8,
+    0xDF Rst [Vector(0x18)] 16 [- - - -] jumps::rst_18,
     // 0xEx
-    ("LDH (a8), a", loads::ldh_ptr_a8_a),
-    unimpl_opcode!(0xE1 POP HL112- - - -),
-    ("0xE2 LD (C),A", loads::ld_ptr_c_a), // actually 1 byte size https://stackoverflow.com/a/41422692
-    unimpl_opcode!(0xE3),
-    unimpl_opcode!(0xE4),
-    unimpl_opcode!(0xE5 PUSH HL116- - - -),
-    unimpl_opcode!(0xE6 AND d828Z 0 1 0),
-    unimpl_opcode!(0xE7 RST 20H116- - - -),
-    unimpl_opcode!(0xE8 ADD SP,r82160 0 H C),
-    unimpl_opcode!(0xE9 JP (HL)14- - - -),
-    ("LD (a16), a", loads::ld_ptr_a16_a),
-    unimpl_opcode!(0xEB),
-    unimpl_opcode!(0xEC),
-    unimpl_opcode!(0xED),
-    unimpl_opcode!(0xEE XOR d828Z 0 0 0),
-    unimpl_opcode!(0xEF RST 28H116- - - -),
+    0xE0 Ldh [PtrAddress8, A] 12 [- - - -] loads::ldh_ptr_a8_a,
+    0xE1 Pop [HL] 12 [- - - -] misc::pop_hl,
+    0xE2 Ld [PtrC, A] 8 [- - - -] loads::ld_ptr_c_a,
+    0xE3 Illegal [] 4 [- - - -] illegal,
+    0xE4 Illegal [] 4 [- - - -] illegal,
+    0xE5 Push [HL] 16 [- - - -] misc::push_hl,
+    0xE6 And [Data8] 8 [Z 0 1 0] and::and_d8,
+    0xE7 Rst [Vector(0x20)] 16 [- - - -] jumps::rst_20,
+    0xE8 Add [SP, Signed8] 16 [0 0 H C] add::add_sp_r8,
+    0xE9 Jp [HL] 4 [- - - -] jumps::jp_hl,
+    0xEA Ld [PtrAddress16, A] 16 [- - - -] loads::ld_ptr_a16_a,
+    0xEB Illegal [] 4 [- - - -] illegal,
+    0xEC Illegal [] 4 [- - - -] illegal,
+    0xED Illegal [] 4 [- - - -] illegal,
+    0xEE Xor [Data8] 8 [Z 0 0 0] xor::xor_d8,
+    0xEF Rst [Vector(0x28)] 16 [- - - -] jumps::rst_28,
     // 0xFx
-    ("LDH a, (a8)", loads::ldh_a_ptr_a8),
-    unimpl_opcode!(0xF1 POP AF 1 12 Z N H C),
-    unimpl_opcode!(0xF2 LD A,(C) 1 8 - - - -), // actually 1 byte size https://stackoverflow.com/a/41422692
-    ("di", di),
-    unimpl_opcode!(0xF4),
-    unimpl_opcode!(0xF5 PUSH AF 1 16 - - - -),
-    unimpl_opcode!(0xF6 OR d8 2 8 Z 0 0 0),
-    unimpl_opcode!(0xF7 RST 30H 1 16 - - - -),
-    unimpl_opcode!(0xF8 LD HL,SP+r8 2 12 0 0 H C),
-    unimpl_opcode!(0xF9 LD SP,HL 1 8 - - - -),
-    unimpl_opcode!(0xFA LD A,(a16) 3 16 - - - -),
-    unimpl_opcode!(0xFB EI 1 4 - - - -),
-    unimpl_opcode!(0xFC),
-    unimpl_opcode!(0xFD),
-    ("CP d8", cmp::cp_d8),
-    ("RST 38", jumps::rst_38),
-];
-
-fn di(memory: &mut Memory, cpu: &mut Cpu) {
-    // 0xF3 DI 1 4 - - - -
+    0xF0 Ldh [A, PtrAddress8] 12 [- - - -] loads::ldh_a_ptr_a8,
+    0xF1 Pop [AF] 12 [Z N H C] misc::pop_af,
+    0xF2 Ld [A, PtrC] 8 [- - - -] loads::ld_a_ptr_c,
+    0xF3 Di [] 4 [- - - -] di,
+    0xF4 Illegal [] 4 [- - - -] illegal,
+    0xF5 Push [AF] 16 [- - - -] misc::push_af,
+    0xF6 Or [Data8] 8 [Z 0 0 0] or::or_d8,
+    0xF7 Rst [Vector(0x30)] 16 [- - - -] jumps::rst_30,
+    0xF8 Ld [HL, SPPlusSigned8] 12 [0 0 H C] loads::ld_hl_sp_plus_r8,
+    0xF9 Ld [SP, HL] 8 [- - - -] loads::ld_sp_hl,
+    0xFA Ld [A, PtrAddress16] 16 [- - - -] loads::ld_a_ptr_a16,
+    0xFB Ei [] 4 [- - - -] ei,
+    0xFC Illegal [] 4 [- - - -] illegal,
+    0xFD Illegal [] 4 [- - - -] illegal,
+    0xFE Cp [Data8] 8 [Z 1 H C] cmp::cp_d8,
+    0xFF Rst [Vector(0x38)] 16 [- - - -] jumps::rst_38,
+};
+
+fn di(_: &mut dyn Bus, cpu: &mut Cpu) {
     cpu.increment_program_counter();
-    cpu.clock_cycles(4);
 
-    memory.write_byte(crate::memory::INTERRUPT_ADDRESS, 0);
+    cpu.disable_interrupts();
+}
+
+fn ei(_: &mut dyn Bus, cpu: &mut Cpu) {
+    cpu.increment_program_counter();
+
+    cpu.schedule_enable_interrupts();
+}
+
+#[cfg(test)]
+pub(crate) struct TestVideo;
+
+#[cfg(test)]
+impl crate::Video for TestVideo {
+    fn is_running(&self) -> bool {
+        true
+    }
+    fn render(&mut self) {}
+    fn set_tile_pixel(&mut self, _: u16, _: u16, _: usize, _: crate::Color) {}
+    fn button_state(&mut self) -> crate::ButtonState {
+        crate::ButtonState {
+            start: false,
+            select: false,
+            a: false,
+            b: false,
+        }
+    }
+    fn direction_state(&mut self) -> crate::DirectionState {
+        crate::DirectionState {
+            up: false,
+            down: false,
+            left: false,
+            right: false,
+        }
+    }
+}
+
+/// Creates a memory map with `program` loaded at 0x0100, the address `test_cpu` starts at.
+#[cfg(test)]
+pub(crate) fn test_memory(program: &[u8]) -> crate::Memory {
+    test_memory_with_rom(crate::cartridge::test_rom(program))
+}
+
+#[cfg(test)]
+pub(crate) fn test_memory_with_rom(rom: Vec<u8>) -> crate::Memory {
+    let cartridge = crate::Cartridge::new(rom).unwrap();
+    let mut memory = crate::Memory::new(cartridge, Box::new(TestVideo));
+    // Unmap the BIOS so tests can place code at the restart and interrupt vectors
+    memory.write_byte(0xFF50, 1);
+    memory
+}
+
+#[cfg(test)]
+pub(crate) fn test_cpu() -> Cpu {
+    let mut cpu = Cpu::default();
+    cpu.set_program_counter(0x100);
+    cpu
+}
+
+/// A memory access recorded by `TracingBus`.
+#[cfg(test)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+struct Access {
+    /// The cycle at which the access happens
+    cycle: u16,
+    address: u16,
+    value: u8,
+    write: bool,
+}
+
+/// Records every memory access, and the cycle at which it happens.
+#[cfg(test)]
+#[derive(Default)]
+struct TracingBus {
+    ram: crate::bus::FlatRam,
+    cycles: u16,
+    accesses: Vec<Access>,
+}
+
+#[cfg(test)]
+impl TracingBus {
+    fn record(&mut self, address: u16, value: u8, write: bool) {
+        self.accesses.push(Access {
+            cycle: self.cycles,
+            address,
+            value,
+            write,
+        });
+    }
+
+    /// The cycle and address of every access.
+    fn addresses(&self) -> Vec<(u16, u16)> {
+        self.accesses
+            .iter()
+            .map(|access| (access.cycle, access.address))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+impl Bus for TracingBus {
+    fn read_byte(&mut self, address: u16) -> u8 {
+        let value = self.ram.read_byte(address);
+        self.record(address, value, false);
+        value
+    }
+
+    fn write_byte(&mut self, address: u16, value: u8) {
+        self.record(address, value, true);
+        self.ram.write_byte(address, value)
+    }
+
+    fn tick(&mut self, cycles: u16) {
+        self.cycles += cycles;
+    }
+}
+
+#[test]
+fn memory_accesses_are_clocked() {
+    let mut bus = TracingBus::default();
+    bus.ram.0[..3].copy_from_slice(&[0xCD, 0x34, 0x12]); // CALL $1234
+    bus.ram.0[0x1234] = 0x35; // DEC (HL)
+    let mut cpu = Cpu::default();
+    cpu.set_sp(0xD000);
+    cpu.set_hl(0xC000);
+
+    execute(&mut bus, &mut cpu);
+    assert_eq!(
+        bus.addresses(),
+        [
+            (4, 0x0000),
+            (8, 0x0001),
+            (12, 0x0002),
+            (20, 0xCFFF),
+            (24, 0xCFFE)
+        ]
+    );
+    assert_eq!(cpu.elapsed_cycles(), 24);
+
+    bus.accesses.clear();
+    execute(&mut bus, &mut cpu);
+    assert_eq!(bus.addresses(), [(28, 0x1234), (32, 0xC000), (36, 0xC000)]);
+    assert_eq!(cpu.elapsed_cycles(), 24 + 12);
 }
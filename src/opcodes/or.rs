@@ -0,0 +1,59 @@
+use crate::{Bus, Cpu};
+
+macro_rules! impl_or_r {
+    ($($code:tt $name:ident $field:ident),* $(,)?) => {
+        $(
+            pub fn $name(_: &mut dyn Bus, cpu: &mut Cpu) {
+                cpu.increment_program_counter();
+
+                let val = cpu.$field();
+                cpu.or_a(val);
+            }
+        )*
+    }
+}
+
+impl_or_r! {
+    0xB0 or_b b,
+    0xB1 or_c c,
+    0xB2 or_d d,
+    0xB3 or_e e,
+    0xB4 or_h h,
+    0xB5 or_l l,
+    0xB7 or_a a,
+}
+
+pub fn or_ptr_hl(memory: &mut dyn Bus, cpu: &mut Cpu) {
+    cpu.increment_program_counter();
+
+    let val = memory.read_byte(cpu.hl());
+    cpu.or_a(val);
+}
+
+pub fn or_d8(memory: &mut dyn Bus, cpu: &mut Cpu) {
+    cpu.increment_program_counter();
+
+    let val = memory.read_byte(cpu.program_counter());
+    cpu.increment_program_counter();
+
+    cpu.or_a(val);
+}
+
+#[test]
+fn or_family() {
+    let mut memory = super::test_memory(&[0xB7, 0xB6]);
+    let mut cpu = super::test_cpu();
+
+    cpu.set_a(0);
+    cpu.flags.set_carry();
+    super::execute(&mut memory, &mut cpu);
+    assert_eq!(cpu.a(), 0);
+    assert!(cpu.flags.z() && !cpu.flags.n() && !cpu.flags.h() && !cpu.flags.c());
+
+    cpu.set_hl(0xC000);
+    memory.write_byte(0xC000, 0x81);
+    super::execute(&mut memory, &mut cpu);
+    assert_eq!(cpu.a(), 0x81);
+    assert!(!cpu.flags.z());
+    assert_eq!(cpu.elapsed_cycles(), 4 + 8);
+}
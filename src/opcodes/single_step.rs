@@ -0,0 +1,252 @@
+//! Runs the SM83 single step tests from https://github.com/SingleStepTests/sm83 against the
+//! opcodes. Every file in the test directory holds the test cases for one opcode (e.g. `3e.json`
+//! or `cb 7c.json`), and every case is a single instruction with the register and RAM state
+//! before and after it executes, and the memory access of every M-cycle in between.
+//!
+//! The test files are not part of this repository, so the test is ignored by default. Clone them
+//! somewhere and point `SM83_TESTS_DIR` to the `v1` directory, or place them in `tests/sm83/v1`,
+//! then run `cargo test -- --ignored single_step_tests`.
+
+use super::{execute, TracingBus};
+use crate::{Bus, Cpu};
+use serde_json::Value;
+use std::{
+    fs,
+    panic::{self, AssertUnwindSafe},
+    path::PathBuf,
+};
+
+const DEFAULT_TESTS_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/sm83/v1");
+
+#[derive(Default)]
+struct Report {
+    passed: usize,
+    failed: usize,
+    first_failure: Option<String>,
+}
+
+#[test]
+#[ignore = "needs the SM83 test files, see the module documentation"]
+fn single_step_tests() {
+    let dir = std::env::var_os("SM83_TESTS_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_TESTS_DIR));
+    let entries = fs::read_dir(&dir)
+        .unwrap_or_else(|e| panic!("Could not read the tests in {}: {}", dir.display(), e));
+
+    let mut files = entries
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect::<Vec<_>>();
+    files.sort();
+
+    let mut failed_opcodes = Vec::new();
+    for file in files {
+        let name = file.file_stem().unwrap().to_string_lossy().into_owned();
+        let json = fs::read_to_string(&file).unwrap();
+        let cases: Vec<Value> = serde_json::from_str(&json)
+            .unwrap_or_else(|e| panic!("Could not parse {}: {}", file.display(), e));
+
+        let mut report = Report::default();
+        for case in &cases {
+            match run_case(case) {
+                Ok(()) => report.passed += 1,
+                Err(e) => {
+                    report.failed += 1;
+                    if report.first_failure.is_none() {
+                        report.first_failure = Some(format!("{}: {}", case["name"], e));
+                    }
+                }
+            }
+        }
+
+        let status = if report.failed == 0 { "ok" } else { "FAILED" };
+        println!(
+            "{:<6} {:<6} {} passed, {} failed",
+            name, status, report.passed, report.failed
+        );
+        if let Some(failure) = report.first_failure {
+            println!("       {}", failure);
+            failed_opcodes.push(name);
+        }
+    }
+
+    assert!(
+        failed_opcodes.is_empty(),
+        "Opcodes failed: {}",
+        failed_opcodes.join(", ")
+    );
+}
+
+fn run_case(case: &Value) -> Result<(), String> {
+    let initial = &case["initial"];
+    let expected = &case["final"];
+    let cycles = case["cycles"].as_array().map_or(&[][..], Vec::as_slice);
+
+    let mut memory = TracingBus::default();
+    let mut cpu = Cpu::default();
+    set_state(&mut memory, &mut cpu, initial);
+    memory.accesses.clear();
+
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        execute(&mut memory, &mut cpu);
+    }));
+    if let Err(e) = result {
+        let message = e
+            .downcast_ref::<String>()
+            .cloned()
+            .or_else(|| e.downcast_ref::<&str>().map(|s| s.to_string()))
+            .unwrap_or_default();
+        return Err(format!("panicked: {}", message));
+    }
+
+    let actual = cpu.elapsed_cycles() as usize;
+    if actual != cycles.len() * 4 {
+        return Err(format!(
+            "took {} cycles, expected {}",
+            actual,
+            cycles.len() * 4
+        ));
+    }
+    compare_accesses(&memory, cycles)?;
+    compare_state(&mut memory, &cpu, expected)?;
+    Ok(())
+}
+
+/// Compares the memory accesses with the `[address, data, kind]` of every M-cycle, where the kind
+/// is `r-m` for a read, `-wm` for a write and `---` for an M-cycle without a memory access.
+fn compare_accesses(memory: &TracingBus, cycles: &[Value]) -> Result<(), String> {
+    let mut accesses = memory.accesses.iter().peekable();
+    for (index, cycle) in cycles.iter().enumerate() {
+        let kind = cycle[2].as_str().unwrap_or("---");
+        let write = match kind {
+            "r-m" => false,
+            "-wm" => true,
+            _ => continue,
+        };
+        let expected = (
+            (index as u16 + 1) * 4,
+            cycle[0].as_u64().unwrap_or(0) as u16,
+            cycle[1].as_u64().unwrap_or(0) as u8,
+            write,
+        );
+        let access = accesses.next().ok_or_else(|| {
+            format!(
+                "M-cycle {} does not access memory, expected {}",
+                index, kind
+            )
+        })?;
+        let actual = (access.cycle, access.address, access.value, access.write);
+        if actual != expected {
+            return Err(format!(
+                "M-cycle {} is {} 0x{:02X} at (0x{:04X}) at cycle {}, expected {} 0x{:02X} at (0x{:04X}) at cycle {}",
+                index,
+                if access.write { "write" } else { "read" },
+                access.value,
+                access.address,
+                access.cycle,
+                kind,
+                expected.2,
+                expected.1,
+                expected.0,
+            ));
+        }
+    }
+    if let Some(access) = accesses.next() {
+        return Err(format!(
+            "unexpected access to (0x{:04X}) at cycle {}",
+            access.address, access.cycle
+        ));
+    }
+    Ok(())
+}
+
+fn ram(state: &Value) -> impl Iterator<Item = (u16, u8)> + '_ {
+    state["ram"].as_array().into_iter().flatten().map(|entry| {
+        let address = entry[0].as_u64().unwrap() as u16;
+        let value = entry[1].as_u64().unwrap() as u8;
+        (address, value)
+    })
+}
+
+fn byte(state: &Value, name: &str) -> u8 {
+    state[name].as_u64().unwrap_or(0) as u8
+}
+
+fn word(state: &Value, name: &str) -> u16 {
+    state[name].as_u64().unwrap_or(0) as u16
+}
+
+fn set_state(memory: &mut dyn Bus, cpu: &mut Cpu, state: &Value) {
+    cpu.set_a(byte(state, "a"));
+    cpu.set_b(byte(state, "b"));
+    cpu.set_c(byte(state, "c"));
+    cpu.set_d(byte(state, "d"));
+    cpu.set_e(byte(state, "e"));
+    cpu.set_f(byte(state, "f"));
+    cpu.set_h(byte(state, "h"));
+    cpu.set_l(byte(state, "l"));
+    cpu.set_sp(word(state, "sp"));
+    cpu.set_program_counter(word(state, "pc"));
+    if byte(state, "ime") == 1 {
+        cpu.enable_interrupts();
+    } else {
+        cpu.disable_interrupts();
+    }
+
+    for (address, value) in ram(state) {
+        memory.write_byte(address, value);
+    }
+}
+
+fn compare_state(memory: &mut dyn Bus, cpu: &Cpu, state: &Value) -> Result<(), String> {
+    let registers = [
+        ("a", cpu.a(), byte(state, "a")),
+        ("b", cpu.b(), byte(state, "b")),
+        ("c", cpu.c(), byte(state, "c")),
+        ("d", cpu.d(), byte(state, "d")),
+        ("e", cpu.e(), byte(state, "e")),
+        ("f", cpu.f(), byte(state, "f")),
+        ("h", cpu.h(), byte(state, "h")),
+        ("l", cpu.l(), byte(state, "l")),
+    ];
+    for (name, actual, expected) in registers.iter() {
+        if actual != expected {
+            return Err(format!(
+                "{} is 0x{:02X}, expected 0x{:02X}",
+                name, actual, expected
+            ));
+        }
+    }
+
+    let words = [
+        ("sp", cpu.stack_pointer(), word(state, "sp")),
+        ("pc", cpu.program_counter(), word(state, "pc")),
+    ];
+    for (name, actual, expected) in words.iter() {
+        if actual != expected {
+            return Err(format!(
+                "{} is 0x{:04X}, expected 0x{:04X}",
+                name, actual, expected
+            ));
+        }
+    }
+
+    // EI only enables interrupts after the next instruction, the test cases count it as enabled
+    let ime = cpu.interrupts_enabled() || cpu.interrupts_scheduled();
+    if ime != (byte(state, "ime") == 1) {
+        return Err(format!("ime is {}, expected {}", ime, !ime));
+    }
+
+    for (address, expected) in ram(state) {
+        let actual = memory.read_byte(address);
+        if actual != expected {
+            return Err(format!(
+                "(0x{:04X}) is 0x{:02X}, expected 0x{:02X}",
+                address, actual, expected
+            ));
+        }
+    }
+
+    Ok(())
+}
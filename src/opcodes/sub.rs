@@ -1,93 +1,154 @@
-use crate::{Cpu, Memory};
+use crate::{Bus, Cpu};
+
+macro_rules! impl_dec_r {
+    ($($code:tt $name:ident $field:ident $set_field:ident),* $(,)?) => {
+        $(
+            pub fn $name(_: &mut dyn Bus, cpu: &mut Cpu) {
+                cpu.increment_program_counter();
+
+                let val = cpu.dec(cpu.$field());
+                cpu.$set_field(val);
+            }
+        )*
+    }
+}
 
-pub fn dec_bc(_: &mut Memory, cpu: &mut Cpu) {
-    // 0x0B DEC BC 1 8 - - - -
-    cpu.clock_cycles(8);
-    cpu.increment_program_counter();
-    cpu.set_bc(cpu.bc().wrapping_sub(1));
+impl_dec_r! {
+    0x05 dec_b b set_b,
+    0x0D dec_c c set_c,
+    0x15 dec_d d set_d,
+    0x1D dec_e e set_e,
+    0x25 dec_h h set_h,
+    0x2D dec_l l set_l,
+    0x3D dec_a a set_a,
 }
 
-pub fn dec_c(_: &mut Memory, cpu: &mut Cpu) {
-    // 0x0D DEC C 1 4 Z 1 H -
-    cpu.clock_cycles(4);
-    cpu.increment_program_counter();
-    let c = cpu.c();
-    cpu.flags.set_subtract();
-    cpu.flags.update_zero(c == 1);
-    cpu.flags.update_half_carry(c == 0x10);
-    cpu.set_c(c.wrapping_sub(1));
+macro_rules! impl_dec_rr {
+    ($($code:tt $name:ident $field:ident $set_field:ident),* $(,)?) => {
+        $(
+            pub fn $name(_: &mut dyn Bus, cpu: &mut Cpu) {
+                cpu.increment_program_counter();
+
+                let val = cpu.$field().wrapping_sub(1);
+                cpu.$set_field(val);
+            }
+        )*
+    }
 }
 
-pub fn dec_b(_: &mut Memory, cpu: &mut Cpu) {
-    // 0x05 DEC B 1 4 Z 1 H -
-    cpu.clock_cycles(4);
-    cpu.increment_program_counter();
+impl_dec_rr! {
+    0x0B dec_bc bc set_bc,
+    0x1B dec_de de set_de,
+    0x2B dec_hl hl set_hl,
+    0x3B dec_sp stack_pointer set_sp,
+}
 
-    let b = cpu.b();
-    cpu.flags.update_zero(b == 1);
-    cpu.flags.update_half_carry(b == 0x10);
-    cpu.flags.set_subtract();
+macro_rules! impl_sub_r {
+    ($($code:tt $name:ident $field:ident $with_carry:expr),* $(,)?) => {
+        $(
+            pub fn $name(_: &mut dyn Bus, cpu: &mut Cpu) {
+                cpu.increment_program_counter();
+
+                let val = cpu.$field();
+                cpu.sub_a(val, $with_carry);
+            }
+        )*
+    }
+}
 
-    cpu.set_b(b.wrapping_sub(1));
+impl_sub_r! {
+    0x90 sub_b b false,
+    0x91 sub_c c false,
+    0x92 sub_d d false,
+    0x93 sub_e e false,
+    0x94 sub_h h false,
+    0x95 sub_l l false,
+    0x97 sub_a a false,
+    0x98 sbc_a_b b true,
+    0x99 sbc_a_c c true,
+    0x9A sbc_a_d d true,
+    0x9B sbc_a_e e true,
+    0x9C sbc_a_h h true,
+    0x9D sbc_a_l l true,
+    0x9F sbc_a_a a true,
 }
 
-pub fn dec_a(_: &mut Memory, cpu: &mut Cpu) {
-    // 0x3D DEC A 1 4 Z 1 H -
-    cpu.clock_cycles(4);
+pub fn dec_ptr_hl(memory: &mut dyn Bus, cpu: &mut Cpu) {
     cpu.increment_program_counter();
 
-    let a = cpu.a();
-    cpu.flags.update_zero(a == 1);
-    cpu.flags.update_half_carry(a == 0x10);
-    cpu.flags.set_subtract();
-
-    cpu.set_a(a.wrapping_sub(1));
+    let address = cpu.hl();
+    let val = cpu.dec(memory.read_byte(address));
+    memory.write_byte(address, val);
 }
 
-pub fn dec_e(_: &mut Memory, cpu: &mut Cpu) {
-    // 0x1D DEC E 1 4 Z 1 H -
-    cpu.clock_cycles(4);
+pub fn sub_ptr_hl(memory: &mut dyn Bus, cpu: &mut Cpu) {
     cpu.increment_program_counter();
 
-    let e = cpu.e();
-    cpu.flags.update_zero(e == 1);
-    cpu.flags.update_half_carry(e == 0x10);
-    cpu.flags.set_subtract();
-
-    cpu.set_e(e.wrapping_sub(1));
+    let val = memory.read_byte(cpu.hl());
+    cpu.sub_a(val, false);
 }
 
-pub fn dec_d(_: &mut Memory, cpu: &mut Cpu) {
-    // 0x16 DEC D 1 4 Z 1 H -
-    cpu.clock_cycles(4);
+pub fn sbc_a_ptr_hl(memory: &mut dyn Bus, cpu: &mut Cpu) {
     cpu.increment_program_counter();
 
-    let d = cpu.d();
-    cpu.flags.update_zero(d == 1);
-    cpu.flags.update_half_carry(d == 0x10);
-    cpu.flags.set_subtract();
-
-    cpu.set_d(d.wrapping_sub(1));
+    let val = memory.read_byte(cpu.hl());
+    cpu.sub_a(val, true);
 }
-pub fn sub_b(_: &mut Memory, cpu: &mut Cpu) {
-    // 0x90 SUB B 1 4 Z 1 H C
+
+pub fn sub_d8(memory: &mut dyn Bus, cpu: &mut Cpu) {
+    cpu.increment_program_counter();
+
+    let val = memory.read_byte(cpu.program_counter());
     cpu.increment_program_counter();
-    cpu.clock_cycles(4);
 
-    let b = cpu.b();
-    do_sub(cpu, b);
+    cpu.sub_a(val, false);
 }
 
-fn do_sub(cpu: &mut Cpu, val: u8) {
-    let a = cpu.a();
-    let new_a = a.wrapping_sub(val);
+pub fn sbc_a_d8(memory: &mut dyn Bus, cpu: &mut Cpu) {
+    cpu.increment_program_counter();
 
-    let carry_bit = (a as u16) ^ (val as u16) ^ (new_a as u16);
+    let val = memory.read_byte(cpu.program_counter());
+    cpu.increment_program_counter();
 
-    cpu.flags.update_zero(new_a == 0);
-    cpu.flags.update_carry(carry_bit & 0x100 > 0);
-    cpu.flags.set_subtract();
-    cpu.flags.update_half_carry(carry_bit & 0x10 > 0);
+    cpu.sub_a(val, true);
+}
 
-    cpu.set_a(new_a);
+#[test]
+fn sub_family() {
+    let mut memory = super::test_memory(&[0x90, 0x9F, 0x0D, 0x35, 0x0B, 0xDE, 0x0F]);
+    let mut cpu = super::test_cpu();
+
+    cpu.set_a(0x10);
+    cpu.set_b(0x20);
+    super::execute(&mut memory, &mut cpu);
+    assert_eq!(cpu.a(), 0xF0);
+    assert!(!cpu.flags.z() && cpu.flags.n() && !cpu.flags.h() && cpu.flags.c());
+
+    // SBC A, A with carry set always results in 0xFF
+    super::execute(&mut memory, &mut cpu);
+    assert_eq!(cpu.a(), 0xFF);
+    assert!(!cpu.flags.z() && cpu.flags.h() && cpu.flags.c());
+
+    cpu.set_c(0x01);
+    super::execute(&mut memory, &mut cpu);
+    assert_eq!(cpu.c(), 0);
+    assert!(cpu.flags.z() && cpu.flags.n() && !cpu.flags.h());
+    assert!(cpu.flags.c(), "DEC does not touch the carry flag");
+
+    cpu.set_hl(0xC000);
+    memory.write_byte(0xC000, 0x00);
+    super::execute(&mut memory, &mut cpu);
+    assert_eq!(memory.read_byte(0xC000), 0xFF);
+    assert!(!cpu.flags.z() && cpu.flags.h());
+
+    cpu.set_bc(0x0000);
+    super::execute(&mut memory, &mut cpu);
+    assert_eq!(cpu.bc(), 0xFFFF);
+
+    cpu.set_a(0x10);
+    cpu.flags.clear_carry();
+    super::execute(&mut memory, &mut cpu);
+    assert_eq!(cpu.a(), 0x01);
+    assert!(cpu.flags.h() && !cpu.flags.c());
+    assert_eq!(cpu.elapsed_cycles(), 4 + 4 + 4 + 12 + 8 + 8);
 }
@@ -1,13 +1,61 @@
-use crate::{Cpu, Memory};
+use crate::{Bus, Cpu};
 
-pub fn xor_a(_: &mut Memory, cpu: &mut Cpu) {
-    // 0xAF XOR A 1 4 Z 0 0 0
+macro_rules! impl_xor_r {
+    ($($code:tt $name:ident $field:ident),* $(,)?) => {
+        $(
+            pub fn $name(_: &mut dyn Bus, cpu: &mut Cpu) {
+                cpu.increment_program_counter();
+
+                let val = cpu.$field();
+                cpu.xor_a(val);
+            }
+        )*
+    }
+}
+
+impl_xor_r! {
+    0xA8 xor_b b,
+    0xA9 xor_c c,
+    0xAA xor_d d,
+    0xAB xor_e e,
+    0xAC xor_h h,
+    0xAD xor_l l,
+    0xAF xor_a a,
+}
+
+pub fn xor_ptr_hl(memory: &mut dyn Bus, cpu: &mut Cpu) {
+    cpu.increment_program_counter();
+
+    let val = memory.read_byte(cpu.hl());
+    cpu.xor_a(val);
+}
+
+pub fn xor_d8(memory: &mut dyn Bus, cpu: &mut Cpu) {
+    cpu.increment_program_counter();
+
+    let val = memory.read_byte(cpu.program_counter());
     cpu.increment_program_counter();
-    cpu.clock_cycles(4);
 
-    cpu.set_a(0);
-    cpu.flags.set_zero();
-    cpu.flags.clear_half_carry();
-    cpu.flags.clear_subtract();
-    cpu.flags.clear_carry();
+    cpu.xor_a(val);
+}
+
+#[test]
+fn xor_family() {
+    let mut memory = super::test_memory(&[0xAF, 0xEE, 0xFF, 0xA9]);
+    let mut cpu = super::test_cpu();
+
+    cpu.set_a(0x5A);
+    cpu.flags.set_half_carry();
+    super::execute(&mut memory, &mut cpu);
+    assert_eq!(cpu.a(), 0);
+    assert!(cpu.flags.z() && !cpu.flags.n() && !cpu.flags.h() && !cpu.flags.c());
+
+    super::execute(&mut memory, &mut cpu);
+    assert_eq!(cpu.a(), 0xFF);
+    assert!(!cpu.flags.z());
+
+    cpu.set_c(0x0F);
+    super::execute(&mut memory, &mut cpu);
+    assert_eq!(cpu.a(), 0xF0);
+    assert_eq!(cpu.elapsed_cycles(), 4 + 8 + 4);
 }
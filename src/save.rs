@@ -0,0 +1,82 @@
+use crate::cartridge::Mapper;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// The battery backed RAM of a cartridge, stored in a `.sav` file.
+///
+/// The file holds the raw contents of the RAM, followed by the state of the real time clock for
+/// cartridges that have one. This is the layout other emulators use, so saves can be moved between
+/// them.
+pub struct SaveFile {
+    path: PathBuf,
+    /// The data that was last loaded or written, to skip writing when nothing changed
+    last_saved: Vec<u8>,
+}
+
+impl SaveFile {
+    /// The save of `rom` is named after the rom, in `directory` or else next to the rom.
+    pub fn for_rom(rom: &Path, directory: Option<&Path>) -> SaveFile {
+        let file_name = rom.with_extension("sav");
+        let path = match (directory, file_name.file_name()) {
+            (Some(directory), Some(file_name)) => directory.join(file_name),
+            _ => file_name,
+        };
+        SaveFile {
+            path,
+            last_saved: Vec::new(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Loads the save into the mapper. Returns `false` if there is no save yet.
+    pub fn load(&mut self, mapper: &mut dyn Mapper) -> io::Result<bool> {
+        let data = match fs::read(&self.path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(e),
+        };
+        mapper.load_save_data(&data);
+        self.last_saved = mapper.save_data();
+        Ok(true)
+    }
+
+    /// Writes the save if it changed since it was last loaded or written. The file is replaced
+    /// atomically, so a crash while saving does not corrupt the previous save.
+    pub fn write(&mut self, mapper: &dyn Mapper) -> io::Result<()> {
+        let data = mapper.save_data();
+        if data == self.last_saved {
+            return Ok(());
+        }
+        let temporary = self.path.with_extension("sav.tmp");
+        fs::write(&temporary, &data)?;
+        fs::rename(&temporary, &self.path)?;
+        self.last_saved = data;
+        Ok(())
+    }
+}
+
+#[test]
+fn save_file_round_trip() {
+    use crate::cartridge::Mbc1;
+
+    let directory = std::env::temp_dir().join(format!("gameboy_save_{}", std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    let mut save = SaveFile::for_rom(Path::new("roms/game.gb"), Some(&directory));
+    assert_eq!(save.path(), directory.join("game.sav"));
+
+    let mut mapper = Mbc1::new(&[], 0x2000);
+    assert!(!save.load(&mut mapper).unwrap());
+    mapper.ram_mut()[0x123] = 0x45;
+    save.write(&mapper).unwrap();
+    assert_eq!(fs::read(save.path()).unwrap().len(), 0x2000);
+
+    let mut loaded = Mbc1::new(&[], 0x2000);
+    assert!(save.load(&mut loaded).unwrap());
+    assert_eq!(loaded.ram()[0x123], 0x45);
+    fs::remove_dir_all(&directory).unwrap();
+}
@@ -47,6 +47,16 @@ impl Video for MinifbVideo {
             right: self.window.is_key_down(Key::Right),
         }
     }
+    fn tilt_state(&mut self) -> TiltState {
+        let axis = |negative, positive| {
+            self.window.is_key_down(positive) as i8 as f32
+                - self.window.is_key_down(negative) as i8 as f32
+        };
+        TiltState {
+            x: axis(Key::J, Key::L),
+            y: axis(Key::I, Key::K),
+        }
+    }
 
     fn render(&mut self) {
         self.window
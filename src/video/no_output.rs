@@ -26,10 +26,10 @@ impl Video for NoOutput {
     fn render(&mut self) {}
     fn set_tile_pixel(
         &mut self,
-        tile_index: u16,
-        row_index: u16,
-        pixel_index: usize,
-        color: Color,
+        _tile_index: u16,
+        _row_index: u16,
+        _pixel_index: usize,
+        _color: Color,
     ) {
     }
 }
@@ -1,4 +1,3 @@
-use super::Tile;
 use drawille::*;
 use gameboy_emulator::*;
 use std::io::{stdout, Stdout, Write};
@@ -38,7 +37,8 @@ impl TerminalVideo {
             "{}{}",
             termion::clear::All,
             termion::cursor::Goto(1, 1),
-        );
+        )
+        .unwrap();
 
         Self {
             canvas,
@@ -69,7 +69,7 @@ impl Video for TerminalVideo {
         true
     }
     fn render(&mut self) {
-        let stdout: &mut Stdout = &mut *self.hide_cursor;
+        let stdout: &mut Stdout = &mut self.hide_cursor;
         writeln!(
             stdout,
             "{}{}{}",